@@ -0,0 +1,194 @@
+//! Fixed, reproducible workloads for tracking render performance across
+//! changes, e.g. from a Criterion `benches/render.rs`
+//!
+//! ```no_run
+//! let (camera, world) = ray_tracer::bench::standard_scene();
+//! let image = camera.render_seeded(&world, ray_tracer::bench::SEED);
+//! ```
+
+use crate::core::Camera;
+use crate::core::Color;
+use crate::geo::material;
+use crate::geo::scenes;
+use crate::geo::HittableList;
+use crate::geo::Point3;
+use crate::geo::Sphere;
+use crate::geo::Triangle;
+
+/// Seed used to build the standard scene, also suitable for `render_seeded`
+pub const SEED: u64 = 1;
+
+/// Ray Tracing in One Weekend final scene with a small, low sample camera so
+/// a single render takes well under a second in release builds
+pub fn standard_scene() -> (Camera, HittableList) {
+    let (world, _) = scenes::rtiow_final(SEED);
+
+    let camera = Camera::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_height(72)
+        .samples_per_pixel(4)
+        .max_depth(8)
+        .vertical_fov(20.0)
+        .look_from(13.0, 2.0, 3.0)
+        .look_at(0.0, 0.0, 0.0)
+        .vup(0.0, 1.0, 0.0)
+        .defocus_angle(0.6)
+        .focus_distance(10.0)
+        .quiet(true)
+        .initialize();
+
+    (camera, world)
+}
+
+/// Latitude/longitude sphere made of individual triangles sitting on a
+/// ground sphere, for workloads dominated by many small primitives
+pub fn mesh_scene() -> (Camera, HittableList) {
+    let mut world = HittableList::new();
+
+    world.add(
+        Sphere::builder()
+            .center(0.0, -1000.0, 0.0)
+            .radius(1000.0)
+            .material(material::Type::from(material::LambertianParams {
+                albedo: Color::new(0.5, 0.5, 0.5),
+                reflectance: 1.0,
+                uniform: false,
+            }))
+            .build(),
+    );
+
+    let material = material::Type::from(material::LambertianParams {
+        albedo: Color::new(0.7, 0.3, 0.2),
+        reflectance: 1.0,
+        uniform: false,
+    });
+
+    const STACKS: usize = 24;
+    const SLICES: usize = 48;
+
+    let point = |stack: usize, slice: usize| {
+        let theta = std::f64::consts::PI * stack as f64 / STACKS as f64;
+        let phi = 2.0 * std::f64::consts::PI * slice as f64 / SLICES as f64;
+        Point3::new(
+            theta.sin() * phi.cos(),
+            1.0 + theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    };
+
+    for stack in 0..STACKS {
+        for slice in 0..SLICES {
+            let a = point(stack, slice);
+            let b = point(stack + 1, slice);
+            let c = point(stack + 1, slice + 1);
+            let d = point(stack, slice + 1);
+
+            // the quads at the poles collapse to a single triangle
+            if stack != 0 {
+                world.add(Triangle::new(a, c, d, material));
+            }
+            if stack != STACKS - 1 {
+                world.add(Triangle::new(a, b, c, material));
+            }
+        }
+    }
+
+    let camera = Camera::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_height(72)
+        .samples_per_pixel(4)
+        .max_depth(8)
+        .vertical_fov(30.0)
+        .look_from(0.0, 2.0, 6.0)
+        .look_at(0.0, 1.0, 0.0)
+        .vup(0.0, 1.0, 0.0)
+        .quiet(true)
+        .initialize();
+
+    (camera, world)
+}
+
+/// Vertex and index buffers of a latitude/longitude sphere of radius 1
+/// around (0, 1, 0), `2 * slices * (stacks - 1)` counter-clockwise faces,
+/// e.g. `sphere_mesh(224, 224)` for a model of about 100k triangles
+pub fn sphere_mesh(stacks: usize, slices: usize) -> (Vec<Point3>, Vec<[usize; 3]>) {
+    let mut vertices = Vec::with_capacity((stacks + 1) * slices);
+    for stack in 0..=stacks {
+        for slice in 0..slices {
+            let theta = std::f64::consts::PI * stack as f64 / stacks as f64;
+            let phi = 2.0 * std::f64::consts::PI * slice as f64 / slices as f64;
+            vertices.push(Point3::new(
+                theta.sin() * phi.cos(),
+                1.0 + theta.cos(),
+                theta.sin() * phi.sin(),
+            ));
+        }
+    }
+
+    let index = |stack: usize, slice: usize| stack * slices + slice % slices;
+
+    let mut indices = Vec::with_capacity(2 * slices * stacks.saturating_sub(1));
+    for stack in 0..stacks {
+        for slice in 0..slices {
+            let a = index(stack, slice);
+            let b = index(stack + 1, slice);
+            let c = index(stack + 1, slice + 1);
+            let d = index(stack, slice + 1);
+
+            // the quads at the poles collapse to a single triangle
+            if stack != 0 {
+                indices.push([a, d, c]);
+            }
+            if stack != stacks - 1 {
+                indices.push([a, c, b]);
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Hittable;
+    use crate::geo::Vec3;
+
+    #[test]
+    fn test_standard_scene() {
+        let (camera_a, world_a) = standard_scene();
+        let (camera_b, world_b) = standard_scene();
+
+        assert_eq!(camera_a, camera_b);
+        assert_eq!((camera_a.image_width(), camera_a.image_height()), (128, 72));
+
+        assert_eq!(world_a.objects().len(), world_b.objects().len());
+        assert_eq!(world_a.bounding_box(), world_b.bounding_box());
+    }
+
+    #[test]
+    fn test_mesh_scene() {
+        let (_, world) = mesh_scene();
+
+        // ground plus two triangles per quad, one at each pole
+        assert_eq!(world.len(), 1 + 24 * 48 * 2 - 2 * 48);
+    }
+
+    #[test]
+    fn test_sphere_mesh() {
+        let (vertices, indices) = sphere_mesh(24, 48);
+        assert_eq!(vertices.len(), 25 * 48);
+        assert_eq!(indices.len(), 2 * 48 * 23);
+
+        // same faces as the mesh scene, wound to face outward
+        let (_, world) = mesh_scene();
+        assert_eq!(indices.len(), world.len() - 1);
+        for face in &indices {
+            let [a, b, c] = face.map(|i| vertices[i]);
+            let triangle = Triangle::new(a, b, c, material::Type::default());
+            let centroid = (Vec3::from(a) + Vec3::from(b) + Vec3::from(c)) / 3.0;
+            let outward = centroid - Vec3::new(0.0, 1.0, 0.0);
+            assert!(triangle.normal().dot(&outward) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::core::Camera;
+use crate::core::Color;
+use crate::core::ImageError;
+use crate::geo::Hittable;
+
+/// Running sum of one sample per pixel passes for progressive previews, the
+/// image refines with every pass and can be shown in between
+///
+/// ```no_run
+/// # use ray_tracer::core::Accumulator;
+/// let (world, camera) = ray_tracer::geo::scenes::hello_world();
+/// let mut accumulator = Accumulator::new(camera.image_width(), camera.image_height());
+/// loop {
+///     accumulator.add_pass(&camera, &world).unwrap();
+///     let preview = accumulator.image();
+///     // display preview
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    width: usize,
+    height: usize,
+    sums: Vec<Color>,
+    passes: u32,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Accumulator {
+            width,
+            height,
+            sums: vec![Color::BLACK; width * height],
+            passes: 0,
+        }
+    }
+
+    /// Render one more sample of every pixel with `camera` and add it, the
+    /// camera must match the accumulator's dimensions
+    ///
+    /// Passes are numbered like samples within a pixel so stratified
+    /// `SamplePairing`s cycle through their cells
+    pub fn add_pass<T: Hittable>(&mut self, camera: &Camera, world: &T) -> Result<(), ImageError> {
+        let size = (camera.image_width(), camera.image_height());
+        if size != (self.width, self.height) {
+            return Err(ImageError::DimensionMismatch(
+                (self.width, self.height),
+                size,
+            ));
+        }
+
+        camera.render_pass(world, &mut self.sums, self.passes);
+        self.passes += 1;
+
+        Ok(())
+    }
+
+    /// Average of every pass so far, row-major, black before the first pass
+    pub fn image(&self) -> Vec<Color> {
+        let scale = 1.0 / self.passes.max(1) as f64;
+        self.sums.iter().map(|sum| *sum * scale).collect()
+    }
+
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Start over, e.g. after the camera or scene changed
+    pub fn reset(&mut self) {
+        self.sums.fill(Color::BLACK);
+        self.passes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Background;
+    use crate::geo::material;
+    use crate::geo::HittableList;
+    use crate::geo::Sphere;
+    use crate::geo::Vec3;
+
+    fn camera() -> Camera {
+        Camera::new()
+            .image_height(4)
+            .background(Background::Solid(Color::new(0.2, 0.4, 0.6)))
+            .quiet(true)
+            .initialize()
+    }
+
+    #[test]
+    fn test_empty() {
+        let accumulator = Accumulator::new(3, 2);
+        assert_eq!(accumulator.passes(), 0);
+        assert_eq!(accumulator.image(), vec![Color::BLACK; 6]);
+    }
+
+    #[test]
+    fn test_add_pass() {
+        let camera = camera();
+        let mut accumulator = Accumulator::new(4, 4);
+
+        // every sample of an empty world is the background
+        for passes in 1..=3 {
+            accumulator.add_pass(&camera, &HittableList::new()).unwrap();
+            assert_eq!(accumulator.passes(), passes);
+        }
+
+        for pixel in accumulator.image() {
+            assert!((Vec3::from(pixel) - Vec3::new(0.2, 0.4, 0.6)).length() < 1e-12);
+        }
+
+        accumulator.reset();
+        assert_eq!(accumulator.passes(), 0);
+        assert_eq!(accumulator.image(), vec![Color::BLACK; 16]);
+    }
+
+    #[test]
+    fn test_average() {
+        // a debug sphere filling the view, each pass adds one jittered sample
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(0.0, 0.0, -1.0)
+                .radius(0.9)
+                .material(material::Type::debug())
+                .build(),
+        );
+
+        let camera = camera();
+        let mut accumulator = Accumulator::new(4, 4);
+        for _ in 0..8 {
+            accumulator.add_pass(&camera, &world).unwrap();
+        }
+
+        let image = accumulator.image();
+        for (pixel, sum) in image.iter().zip(&accumulator.sums) {
+            assert_eq!(*pixel, *sum * 0.125);
+            assert!((0.0..=1.0).contains(&pixel.x()));
+        }
+
+        // debug shading varies across the sphere
+        assert!(image.iter().any(|pixel| *pixel != image[0]));
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let mut accumulator = Accumulator::new(5, 4);
+        assert_eq!(
+            accumulator.add_pass(&camera(), &HittableList::new()),
+            Err(ImageError::DimensionMismatch((5, 4), (4, 4)))
+        );
+        assert_eq!(accumulator.passes(), 0);
+    }
+}
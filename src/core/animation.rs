@@ -0,0 +1,284 @@
+use rayon::prelude::*;
+
+use crate::core::Camera;
+use crate::core::Progress;
+use crate::geo::Hittable;
+
+/// Render `frames` images of `world` into `out_dir` as `frame_0001.ppm`,
+/// `frame_0002.ppm`, ... with the camera for each frame returned by
+/// `camera_fn` for a normalized time `t` going from 0 on the first frame to
+/// 1 on the last, e.g. orbiting `look_from` around the scene for a turntable
+///
+/// Progress is reported per frame across the whole sequence, the frames
+/// themselves render quietly. Returns the paths of the written frames
+///
+/// ```sh
+/// ffmpeg -framerate 30 -i out/frame_%04d.ppm -pix_fmt yuv420p turntable.mp4
+/// ```
+pub async fn render_sequence<T, F>(
+    camera_fn: F,
+    world: &T,
+    frames: u32,
+    out_dir: &str,
+) -> Result<Vec<String>, std::io::Error>
+where
+    T: Hittable,
+    F: Fn(f64) -> Camera,
+{
+    std::fs::create_dir_all(out_dir)?;
+    if frames == 0 {
+        return Ok(vec![]);
+    }
+
+    // the first frame's camera decides whether the sequence reports progress
+    let first = camera_fn(frame_time(0, frames));
+    let progress = Progress::new(frames as usize).quiet(first.quiet).render(15);
+    let cameras =
+        std::iter::once(first).chain((1..frames).map(|frame| camera_fn(frame_time(frame, frames))));
+
+    let mut paths = vec![];
+
+    for (number, mut camera) in (1..).zip(cameras) {
+        camera.output = frame_path(out_dir, number);
+        camera.quiet = true;
+
+        camera.render(world).await;
+
+        paths.push(camera.output);
+        progress.inc();
+    }
+
+    Ok(paths)
+}
+
+/// Like `render_sequence` but renders up to `threads` frames at once, each
+/// frame on a single thread, instead of one frame at a time across all cores
+///
+/// Suits many small frames, e.g. a low resolution preview turntable, where a
+/// single frame has too few pixels to keep every core busy. Large frames
+/// render faster with `render_sequence`, whose per-frame parallelism keeps
+/// memory to one frame and balances uneven frames.
+///
+/// Frames run on their own pool of `threads` (0 for one per core, never
+/// more than the cores available) so the outer frame parallelism does not
+/// stack on top of rayon's global pool
+pub fn render_sequence_parallel<T, F>(
+    camera_fn: F,
+    world: &T,
+    frames: u32,
+    out_dir: &str,
+    threads: usize,
+) -> Result<Vec<String>, std::io::Error>
+where
+    T: Hittable,
+    F: Fn(f64) -> Camera + Sync,
+{
+    std::fs::create_dir_all(out_dir)?;
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let threads = if threads == 0 {
+        cores
+    } else {
+        threads.min(cores)
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(std::io::Error::other)?;
+
+    if frames == 0 {
+        return Ok(vec![]);
+    }
+
+    // the first frame's camera decides whether the sequence reports progress
+    let first = camera_fn(frame_time(0, frames));
+    let progress = Progress::new(frames as usize).quiet(first.quiet).render(15);
+
+    // a failed frame ends the collect early, dropping the progress short of
+    // `frames`, which stops its thread
+    pool.install(|| {
+        rayon::iter::once(first)
+            .chain(
+                (1..frames)
+                    .into_par_iter()
+                    .map(|frame| camera_fn(frame_time(frame, frames))),
+            )
+            .enumerate()
+            .map(|(frame, camera)| {
+                let path = frame_path(out_dir, frame as u32 + 1);
+                camera.render_serial(world).save_blocking(&path)?;
+
+                progress.inc();
+                Ok(path)
+            })
+            .collect()
+    })
+}
+
+/// Normalized time of a frame, a single frame sits at 0
+pub fn frame_time(frame: u32, frames: u32) -> f64 {
+    if frames <= 1 {
+        0.0
+    } else {
+        frame as f64 / (frames - 1) as f64
+    }
+}
+
+/// Path of the 1-based `number` frame in `out_dir`
+pub fn frame_path(out_dir: &str, number: u32) -> String {
+    std::path::Path::new(out_dir)
+        .join(format!("frame_{number:04}.ppm"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Background;
+    use crate::core::Color;
+    use crate::geo::material;
+    use crate::geo::HittableList;
+    use crate::geo::Sphere;
+    use crate::geo::Vec3;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_frame_time() {
+        assert_eq!(frame_time(0, 1), 0.0);
+        assert_eq!(frame_time(0, 5), 0.0);
+        assert_eq!(frame_time(2, 5), 0.5);
+        assert_eq!(frame_time(4, 5), 1.0);
+    }
+
+    #[test]
+    fn test_frame_path() {
+        assert_eq!(
+            frame_path("out", 1),
+            format!("out{}frame_0001.ppm", std::path::MAIN_SEPARATOR)
+        );
+        assert!(frame_path("out", 60).ends_with("frame_0060.ppm"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_render_sequence() {
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(0.0, 0.0, 0.0)
+                .radius(0.5)
+                .material(material::Type::debug())
+                .build(),
+        );
+
+        let out_dir =
+            std::env::temp_dir().join(format!("ray-tracer-animation-{}", std::process::id()));
+        let out_dir = out_dir.to_str().unwrap();
+
+        // orbit around the origin
+        let times = Mutex::new(vec![]);
+        let camera_fn = |t: f64| {
+            times.lock().unwrap().push(t);
+            let angle = t * std::f64::consts::TAU;
+            let [x, y, z] = Vec3::new(3.0, 0.0, 0.0)
+                .rotate_around(&Vec3::new(0.0, 1.0, 0.0), angle)
+                .to_array();
+            Camera::new()
+                .image_height(4)
+                .samples_per_pixel(1)
+                .quiet(true)
+                .look_from(x, y, z)
+                .look_at(0.0, 0.0, 0.0)
+                .initialize()
+        };
+
+        let paths = render_sequence(camera_fn, &world, 3, out_dir)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            paths,
+            (1..=3).map(|n| frame_path(out_dir, n)).collect::<Vec<_>>()
+        );
+        for path in &paths {
+            let ppm = std::fs::read_to_string(path).unwrap();
+            assert!(ppm.starts_with("P3\n4 4\n255\n"));
+        }
+
+        // one camera per frame, none built only to read its settings
+        let times = times.into_inner().unwrap();
+        assert_eq!(times, [0.0, 0.5, 1.0]);
+
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_render_sequence_parallel() {
+        // a solid background renders the same however the work is split
+        let world = HittableList::new();
+        let camera_fn = |t: f64| {
+            Camera::new()
+                .image_height(3)
+                .samples_per_pixel(2)
+                .quiet(true)
+                .background(Background::Solid(Color::new(t, 0.5, 1.0 - t)))
+                .initialize()
+        };
+
+        let root = std::env::temp_dir().join(format!(
+            "ray-tracer-animation-parallel-{}",
+            std::process::id()
+        ));
+        let serial_dir = root.join("serial");
+        let parallel_dir = root.join("parallel");
+        let serial_dir = serial_dir.to_str().unwrap();
+        let parallel_dir = parallel_dir.to_str().unwrap();
+
+        let serial = render_sequence(camera_fn, &world, 5, serial_dir)
+            .await
+            .unwrap();
+        for threads in [0, 1, 3, usize::MAX] {
+            let parallel =
+                render_sequence_parallel(camera_fn, &world, 5, parallel_dir, threads).unwrap();
+
+            assert_eq!(
+                parallel,
+                (1..=5)
+                    .map(|n| frame_path(parallel_dir, n))
+                    .collect::<Vec<_>>()
+            );
+            for (a, b) in serial.iter().zip(&parallel) {
+                assert!(!std::path::Path::new(&format!("{b}.tmp")).exists());
+                assert_eq!(
+                    std::fs::read_to_string(a).unwrap(),
+                    std::fs::read_to_string(b).unwrap()
+                );
+            }
+        }
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_render_sequence_parallel_write_error() {
+        let out_dir =
+            std::env::temp_dir().join(format!("ray-tracer-animation-error-{}", std::process::id()));
+        let out_dir = out_dir.to_str().unwrap();
+
+        // a directory in the way of the second frame's temporary file
+        std::fs::create_dir_all(format!("{}.tmp", frame_path(out_dir, 2))).unwrap();
+
+        // reports progress, which must not wait for frames that never finish
+        let camera_fn = |_: f64| {
+            Camera::new()
+                .image_height(2)
+                .samples_per_pixel(1)
+                .quiet(false)
+                .initialize()
+        };
+        let result = render_sequence_parallel(camera_fn, &HittableList::new(), 4, out_dir, 1);
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use crate::core::Color;
+use crate::core::EnvironmentMap;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Radiance for rays that escape the scene without hitting anything
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Background {
+    /// Vertical white to light blue gradient
+    #[default]
+    Sky,
+    Solid(Color),
+    /// Blend between two colors along any direction
+    Gradient(Gradient),
+    /// Shared so cloning a camera does not copy the panorama
+    Environment(Arc<EnvironmentMap>),
+    /// Caller supplied sky, see `BackgroundFn`
+    Function(BackgroundFn),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Sky => Gradient::default().color(ray),
+            Background::Solid(color) => *color,
+            Background::Gradient(gradient) => gradient.color(ray),
+            Background::Environment(environment) => environment.sample(ray.direction()),
+            Background::Function(function) => (function.0)(ray),
+        }
+    }
+}
+
+/// Procedural background the crate does not ship, e.g. a star field or an
+/// analytic sky, called with every ray that escapes the scene
+///
+/// Called from every render thread at once, hence `Send + Sync`. Two are
+/// equal only when they share the same closure.
+#[derive(Clone)]
+pub struct BackgroundFn(pub Arc<dyn Fn(&Ray) -> Color + Send + Sync>);
+
+impl std::fmt::Debug for BackgroundFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BackgroundFn(..)")
+    }
+}
+
+impl PartialEq for BackgroundFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// `start` for rays pointing against `axis`, `end` for rays along it,
+/// blended linearly in the cosine between them. The default is the sky,
+/// white at the bottom to light blue at the top.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gradient {
+    pub start: Color,
+    pub end: Color,
+    pub axis: Vec3,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            start: Color::WHITE,
+            end: Color::new(0.5, 0.7, 1.0),
+            axis: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+}
+
+impl Gradient {
+    pub fn color(&self, ray: &Ray) -> Color {
+        let unit_direction = ray.direction().unit();
+        let a = 0.5 * (unit_direction.dot(&self.axis.unit()) + 1.0);
+
+        Color::lerp(a, self.start, self.end)
+    }
+}
+
+impl From<Gradient> for Background {
+    fn from(gradient: Gradient) -> Self {
+        Background::Gradient(gradient)
+    }
+}
+
+impl From<EnvironmentMap> for Background {
+    fn from(environment: EnvironmentMap) -> Self {
+        Background::Environment(Arc::new(environment))
+    }
+}
+
+impl From<BackgroundFn> for Background {
+    fn from(function: BackgroundFn) -> Self {
+        Background::Function(function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Image;
+    use crate::geo::Point3;
+    use crate::geo::Vec3;
+    use crate::test::assert;
+
+    fn ray(x: f64, y: f64, z: f64) -> Ray {
+        Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(x, y, z))
+    }
+
+    #[test]
+    fn test_sky() {
+        let background = Background::default();
+        assert_eq!(background, Background::Sky);
+        assert_eq!(background.color(&ray(0.0, -1.0, 0.0)), Color::WHITE);
+        assert_eq!(
+            background.color(&ray(0.0, 1.0, 0.0)),
+            Color::new(0.5, 0.7, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_default_gradient_is_sky() {
+        let sky = Color::new(0.5, 0.7, 1.0);
+
+        for background in [Background::from(Gradient::default()), Background::Sky] {
+            assert_eq!(background.color(&ray(0.0, -1.0, 0.0)), Color::WHITE);
+            assert_eq!(background.color(&ray(0.0, 1.0, 0.0)), sky);
+
+            // halfway at the horizon
+            let horizon = background.color(&ray(1.0, 0.0, 0.0));
+            assert::float(horizon.x(), 0.75, 12);
+            assert::float(horizon.y(), 0.85, 12);
+            assert::float(horizon.z(), 1.0, 12);
+        }
+    }
+
+    #[test]
+    fn test_gradient_axis() {
+        // horizon gradient along x, reversed colors, axis length does not matter
+        let background = Background::from(Gradient {
+            start: Color::BLUE,
+            end: Color::RED,
+            axis: Vec3::new(-3.0, 0.0, 0.0),
+        });
+
+        assert_eq!(background.color(&ray(-1.0, 0.0, 0.0)), Color::RED);
+        assert_eq!(background.color(&ray(1.0, 0.0, 0.0)), Color::BLUE);
+        assert_eq!(
+            background.color(&ray(0.0, 1.0, 0.0)),
+            Color::new(0.5, 0.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_solid() {
+        let background = Background::Solid(Color::RED);
+        assert_eq!(background.color(&ray(0.3, -0.2, 1.0)), Color::RED);
+    }
+
+    #[test]
+    fn test_environment() {
+        let mut image = Image::new(2, 2);
+        image.pixels = vec![Color::new(3.0, 3.0, 3.0); 4];
+
+        let background = Background::from(EnvironmentMap::new(image));
+        assert_eq!(
+            background.color(&ray(0.3, -0.2, 1.0)),
+            Color::new(3.0, 3.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn test_function() {
+        // black sky with a sun disk straight up
+        let sun = BackgroundFn(Arc::new(|ray: &Ray| {
+            if ray.direction().unit().y > 0.99 {
+                Color::new(10.0, 10.0, 10.0)
+            } else {
+                Color::BLACK
+            }
+        }));
+        let background = Background::from(sun.clone());
+
+        assert_eq!(
+            background.color(&ray(0.0, 1.0, 0.05)),
+            Color::new(10.0, 10.0, 10.0)
+        );
+        assert_eq!(background.color(&ray(0.0, 1.0, 1.0)), Color::BLACK);
+
+        // equal to clones of the same closure only
+        assert_eq!(background, Background::Function(sun));
+        let other = BackgroundFn(Arc::new(|_: &Ray| Color::BLACK));
+        assert_ne!(background, Background::Function(other));
+        assert_eq!(format!("{background:?}"), "Function(BackgroundFn(..))");
+    }
+}
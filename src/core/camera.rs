@@ -1,20 +1,125 @@
 use rayon::prelude::*;
+use std::io::IsTerminal;
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::RwLock;
 use tokio::time;
 
+use crate::core::permute;
 use crate::core::ppm;
 use crate::core::random_f64;
+use crate::core::save_atomic;
+use crate::core::seed_for;
+use crate::core::with_seed;
+use crate::core::Background;
+use crate::core::BackgroundFn;
+#[cfg(feature = "serde")]
+use crate::core::CameraMeta;
+use crate::core::Checkpoint;
 use crate::core::Color;
+use crate::core::Image;
+use crate::core::Light;
 use crate::core::Progress;
+#[cfg(feature = "serde")]
+use crate::core::RenderMeta;
+use crate::core::RenderStats;
 use crate::geo::degrees_to_radians;
+use crate::geo::material;
 use crate::geo::random_unit_disk;
+use crate::geo::square_to_disk;
+use crate::geo::square_to_polygon;
+use crate::geo::HitRecord;
 use crate::geo::Hittable;
 use crate::geo::Point3;
 use crate::geo::Ray;
 use crate::geo::Vec3;
 
+/// How the samples of a pixel choose their point within the pixel (anti
+/// aliasing) and their point on the lens (depth of field)
+///
+/// Stratified pairings use the largest square grid the samples fill, e.g.
+/// 3x3 for 10 samples, and draw the samples left over independently.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SamplePairing {
+    /// Both points drawn independently at random for every sample
+    #[default]
+    Independent,
+    /// Both points stratified over a grid, the nth pixel cell paired with
+    /// the nth lens cell, so lens position follows pixel position
+    Correlated,
+    /// Both points stratified, lens cells shuffled per pixel so every sample
+    /// gets a distinct (pixel, lens) pair covering both evenly
+    Decorrelated,
+}
+
+/// Unit of work `render` hands to each rayon task, progress advances by the
+/// samples of each finished task
+///
+/// Idle threads steal waiting tasks, so smaller tasks balance uneven scenes
+/// (e.g. glass next to empty sky) better at the cost of more updates to the
+/// shared image and progress counter.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Parallelism {
+    /// Every pixel is a task
+    #[default]
+    Pixel,
+    /// Every row is a task
+    Scanline,
+    /// Every run of this many consecutive pixels (row-major, across row ends)
+    /// is a task, see `CameraBuilder::chunk_pixels`
+    Chunk(usize),
+}
+
+/// Camera settings `CameraBuilder::try_initialize` rejects, each would render
+/// a blank, distorted or NaN filled image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraError {
+    /// Vertical field of view in degrees must be in (0, 180)
+    InvalidFieldOfView(f64),
+    /// Focus distance must be positive and finite
+    InvalidFocusDistance(f64),
+    /// Defocus angle in degrees must be in [0, 180)
+    InvalidDefocusAngle(f64),
+    /// Aspect ratio must be positive and finite
+    InvalidAspectRatio(f64),
+    /// Image (width, height) has no pixels
+    EmptyImage(usize, usize),
+    /// `look_from` and `look_at` are the same point, there is no view direction
+    NoViewDirection,
+    /// `vup` is zero or parallel to the view direction, there is no "up"
+    DegenerateVup,
+}
+
+impl std::fmt::Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CameraError::InvalidFieldOfView(fov) => {
+                write!(f, "vertical fov {fov} must be between 0 and 180 degrees")
+            }
+            CameraError::InvalidFocusDistance(distance) => {
+                write!(f, "focus distance {distance} must be positive")
+            }
+            CameraError::InvalidDefocusAngle(angle) => {
+                write!(f, "defocus angle {angle} must be between 0 and 180 degrees")
+            }
+            CameraError::InvalidAspectRatio(ratio) => {
+                write!(f, "aspect ratio {ratio} must be positive")
+            }
+            CameraError::EmptyImage(width, height) => {
+                write!(f, "image size {width}x{height} has no pixels")
+            }
+            CameraError::NoViewDirection => {
+                write!(f, "look_from and look_at are the same point")
+            }
+            CameraError::DegenerateVup => {
+                write!(f, "vup is zero or parallel to the view direction")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
 pub struct CameraBuilder {
     aspect_ratio: f64,
     image_height: f64,
@@ -30,10 +135,52 @@ pub struct CameraBuilder {
     look_at: Point3,
     /// Camera-relative "up" direction
     vup: Vec3,
+    /// Bank angle in degrees around the view direction, tilts the horizon
+    roll: f64,
     /// Variation angle of rays through each pixel
     defocus_angle: f64,
     /// Distance from camera lookfrom point to plane of perfect focus
     focus_distance: f64,
+    /// Point whose distance from lookfrom overrides focus_distance
+    focus_on: Option<Point3>,
+    /// Dither saved images to reduce 8-bit banding
+    dither: bool,
+    /// Gamma saved images are encoded with
+    gamma: f64,
+    /// Save linear values, bypassing gamma
+    linear: bool,
+    /// Suppress render progress output
+    quiet: bool,
+    /// Clamp each channel of every sample to this maximum to suppress fireflies
+    clamp_radiance: Option<f64>,
+    /// Radiance of rays that escape the scene
+    background: Background,
+    /// Warn when a scatter gains energy (attenuation > 1)
+    check_energy: bool,
+    /// Shade empty materials with a magenta checker instead of black
+    missing_material: bool,
+    /// Distribution of pixel and lens positions across a pixel's samples
+    sample_pairing: SamplePairing,
+    /// Sides of a polygonal aperture, fewer than 3 for a circular one
+    aperture_blades: u32,
+    /// Path `render` saves the image to
+    output: String,
+    /// Time between saves of the in-progress image during `render`
+    save_interval: Option<time::Duration>,
+    /// Split `render` into pixel or row tasks
+    parallelism: Parallelism,
+    /// Analytic lights added to diffuse hits with shadow rays
+    lights: Vec<Light>,
+    /// Distance along bounce and shadow rays ignored to avoid self-intersection
+    hit_epsilon: f64,
+    /// Write a JSON sidecar of the render settings next to the saved image
+    save_meta: bool,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CameraBuilder {
@@ -47,8 +194,26 @@ impl CameraBuilder {
             look_from: Point3::new(0.0, 0.0, 0.0),
             look_at: Point3::new(0.0, 0.0, -1.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
+            roll: 0.0,
             defocus_angle: 0.0,
             focus_distance: 10.0,
+            focus_on: None,
+            dither: false,
+            gamma: Color::GAMMA,
+            linear: false,
+            quiet: false,
+            clamp_radiance: None,
+            background: Background::Sky,
+            check_energy: false,
+            missing_material: false,
+            sample_pairing: SamplePairing::Independent,
+            aperture_blades: 0,
+            output: String::from("image.ppm"),
+            save_interval: Some(time::Duration::from_secs(2)),
+            parallelism: Parallelism::Pixel,
+            lights: vec![],
+            hit_epsilon: 0.001,
+            save_meta: false,
         }
     }
 
@@ -62,11 +227,15 @@ impl CameraBuilder {
         self
     }
 
+    /// Rays averaged per pixel, 0 is raised to 1 on initialize
     pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> CameraBuilder {
         self.samples_per_pixel = samples_per_pixel;
         self
     }
 
+    /// Bounces a ray may take before it is given up as black. At 0 no ray
+    /// gathers any light, not even the background, so every pixel is black
+    /// (initialize warns).
     pub fn max_depth(mut self, max_depth: u32) -> CameraBuilder {
         self.max_depth = max_depth;
         self
@@ -77,6 +246,13 @@ impl CameraBuilder {
         self
     }
 
+    /// Set `vertical_fov` from a lens focal length and sensor height, both in
+    /// millimeters, e.g. a 50mm lens on a full frame (24mm tall) sensor
+    pub fn focal_length(self, focal_length: f64, sensor_height: f64) -> CameraBuilder {
+        let theta = 2.0 * (sensor_height / (2.0 * focal_length)).atan();
+        self.vertical_fov(theta.to_degrees())
+    }
+
     pub fn look_from(mut self, x: f64, y: f64, z: f64) -> CameraBuilder {
         self.look_from = Point3::new(x, y, z);
         self
@@ -92,6 +268,13 @@ impl CameraBuilder {
         self
     }
 
+    /// Rotate the camera around its view direction by `degrees`, positive
+    /// values bank counterclockwise so the scene appears turned clockwise
+    pub fn roll(mut self, degrees: f64) -> CameraBuilder {
+        self.roll = degrees;
+        self
+    }
+
     pub fn defocus_angle(mut self, defocus_angle: f64) -> CameraBuilder {
         self.defocus_angle = defocus_angle;
         self
@@ -102,20 +285,254 @@ impl CameraBuilder {
         self
     }
 
+    /// Focus at the distance of `point` from `look_from` (measured at
+    /// `initialize`, so call order does not matter), e.g. the center of a
+    /// sphere, overriding any explicit `focus_distance`
+    pub fn focus_on(mut self, point: Point3) -> CameraBuilder {
+        self.focus_on = Some(point);
+        self
+    }
+
+    pub fn dither(mut self, dither: bool) -> CameraBuilder {
+        self.dither = dither;
+        self
+    }
+
+    /// Gamma saved images are encoded with, defaults to `Color::GAMMA` (2.0),
+    /// 2.2 approximates the sRGB curve most displays expect
+    pub fn gamma(mut self, gamma: f64) -> CameraBuilder {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Save linear radiance (clamped to [0,1]) without gamma encoding
+    pub fn linear(mut self, linear: bool) -> CameraBuilder {
+        self.linear = linear;
+        self
+    }
+
+    pub fn quiet(mut self, quiet: bool) -> CameraBuilder {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Clamp every channel of each sample's radiance to `max` before it is
+    /// averaged into the pixel. Removes fireflies from rare bright paths (glass,
+    /// metal caustics) at the cost of bias: clamped energy is lost, darkening
+    /// highlights. Off by default.
+    pub fn clamp_radiance(mut self, max: f64) -> CameraBuilder {
+        self.clamp_radiance = Some(max);
+        self
+    }
+
+    /// Light scenes with e.g. an `EnvironmentMap` instead of the default sky
+    pub fn background(mut self, background: impl Into<Background>) -> CameraBuilder {
+        self.background = background.into();
+        self
+    }
+
+    /// Color escaping rays with a closure instead of a built-in background,
+    /// see `BackgroundFn`
+    pub fn background_fn(
+        self,
+        background: Arc<dyn Fn(&Ray) -> Color + Send + Sync>,
+    ) -> CameraBuilder {
+        self.background(BackgroundFn(background))
+    }
+
+    /// Aim at the center of `world` and back away along the current view
+    /// direction (`look_from` - `look_at`) until its bounding sphere fits in
+    /// both the vertical and horizontal field of view, focusing on the center
+    ///
+    /// Call after setting `aspect_ratio` and `vertical_fov`. Worlds without
+    /// a bounding box leave the camera unchanged.
+    pub fn frame<T: Hittable>(mut self, world: &T) -> CameraBuilder {
+        let bbox = world.bounding_box();
+        if bbox.is_empty() {
+            return self;
+        }
+
+        let center = Vec3::from(bbox.centroid());
+        let radius = bbox.size().length() / 2.0;
+
+        let direction = Vec3::from(self.look_from) - Vec3::from(self.look_at);
+        let direction = if direction.near_zero() {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            direction.unit()
+        };
+
+        // the narrower of the two fields of view limits how much fits
+        let half_vertical = degrees_to_radians(self.vertical_fov) / 2.0;
+        let half_horizontal = (half_vertical.tan() * self.aspect_ratio).atan();
+        let half_fov = half_vertical.min(half_horizontal);
+
+        let distance = radius / half_fov.sin();
+
+        self.look_at = Point3::from(center);
+        self.look_from = Point3::from(center + direction * distance);
+        self.focus_distance = distance;
+        self
+    }
+
+    /// Debug mis-specified materials by warning when any bounce returns an
+    /// attenuation above 1.0 in some channel, see `material::energy_warnings`.
+    /// Off by default, it adds a check to every bounce.
+    pub fn check_energy(mut self, check_energy: bool) -> CameraBuilder {
+        self.check_energy = check_energy;
+        self
+    }
+
+    /// Render objects left with an `Empty` material as a bright magenta and
+    /// black checker, the classic missing texture pattern, instead of black
+    /// so unassigned materials stand out. Off by default.
+    pub fn missing_material(mut self, missing_material: bool) -> CameraBuilder {
+        self.missing_material = missing_material;
+        self
+    }
+
+    /// Stratify pixel and lens samples, see `SamplePairing`. Stratified lens
+    /// samples cover the whole aperture rather than only its edge.
+    pub fn sample_pairing(mut self, sample_pairing: SamplePairing) -> CameraBuilder {
+        self.sample_pairing = sample_pairing;
+        self
+    }
+
+    /// Shape the lens aperture as a regular polygon with this many sides,
+    /// e.g. 6 for hexagonal out of focus highlights (bokeh). 0, the default,
+    /// keeps the circular aperture. Only visible with a `defocus_angle`.
+    pub fn aperture_blades(mut self, aperture_blades: u32) -> CameraBuilder {
+        self.aperture_blades = aperture_blades;
+        self
+    }
+
+    /// Path `render` writes the PPM image to, defaults to `image.ppm`
+    pub fn output(mut self, output: &str) -> CameraBuilder {
+        self.output = output.to_string();
+        self
+    }
+
+    /// Overwrite the output with the image rendered so far every `seconds`
+    /// (atomically, readers never see a partial file) so long renders can be
+    /// inspected while running, 0 saves only once finished
+    pub fn save_interval(mut self, seconds: f64) -> CameraBuilder {
+        self.save_interval = (seconds > 0.0).then(|| time::Duration::from_secs_f64(seconds));
+        self
+    }
+
+    /// Render pixel by pixel (the default), row by row or in runs of pixels,
+    /// see `Parallelism`
+    pub fn parallelism(mut self, parallelism: Parallelism) -> CameraBuilder {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Render in tasks of `chunk_pixels` consecutive pixels (at least 1),
+    /// a tuning knob between `Parallelism::Pixel` and `Parallelism::Scanline`
+    pub fn chunk_pixels(self, chunk_pixels: usize) -> CameraBuilder {
+        self.parallelism(Parallelism::Chunk(chunk_pixels.max(1)))
+    }
+
+    /// Add a point or spot light. At every diffuse hit a shadow ray is cast
+    /// towards each light and its unoccluded contribution added on top of
+    /// the path traced light, a cheap alternative to an emissive object.
+    pub fn light(mut self, light: impl Into<Light>) -> CameraBuilder {
+        self.lights.push(light.into());
+        self
+    }
+
+    /// Distance along every ray from a surface below which hits are ignored,
+    /// defaults to 0.001. Floating point error in hit points grows with the
+    /// size of their coordinates, so large scenes (e.g. kilometers of
+    /// terrain) need a larger epsilon to avoid surfaces shadowing themselves
+    /// (acne) and tiny ones a smaller one so thin gaps do not leak light.
+    pub fn hit_epsilon(mut self, hit_epsilon: f64) -> CameraBuilder {
+        self.hit_epsilon = hit_epsilon;
+        self
+    }
+
+    /// Save the settings `render` or `save_seeded` used next to the output, e.g.
+    /// `image.json` for `image.ppm`, see `RenderMeta`. Needs the serde
+    /// feature, off by default.
+    pub fn save_meta(mut self, save_meta: bool) -> CameraBuilder {
+        self.save_meta = save_meta;
+        self
+    }
+
+    /// Same as `initialize` but rejects settings that cannot produce a
+    /// sensible image instead of rendering NaNs or nothing
+    pub fn try_initialize(&self) -> Result<Camera, CameraError> {
+        let valid_angle = |degrees: f64| degrees.is_finite() && (0.0..180.0).contains(&degrees);
+
+        if !valid_angle(self.vertical_fov) || self.vertical_fov == 0.0 {
+            return Err(CameraError::InvalidFieldOfView(self.vertical_fov));
+        }
+
+        if !valid_angle(self.defocus_angle) {
+            return Err(CameraError::InvalidDefocusAngle(self.defocus_angle));
+        }
+
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
+        }
+
+        let width = (self.image_height * self.aspect_ratio) as usize;
+        let height = self.image_height as usize;
+        if width == 0 || height == 0 {
+            return Err(CameraError::EmptyImage(width, height));
+        }
+
+        let view = Vec3::from(self.look_from) - Vec3::from(self.look_at);
+        if view.length_squared() == 0.0 || !view.length_squared().is_finite() {
+            return Err(CameraError::NoViewDirection);
+        }
+
+        // relative to the length of vup so its scale does not matter
+        let vup_length = self.vup.length();
+        if vup_length == 0.0
+            || !vup_length.is_finite()
+            || self.vup.cross(&view.unit()).length() < 1e-9 * vup_length
+        {
+            return Err(CameraError::DegenerateVup);
+        }
+
+        let focus_distance = self.effective_focus_distance();
+        if !(focus_distance.is_finite() && focus_distance > 0.0) {
+            return Err(CameraError::InvalidFocusDistance(focus_distance));
+        }
+
+        Ok(self.initialize())
+    }
+
+    fn effective_focus_distance(&self) -> f64 {
+        match self.focus_on {
+            Some(point) => (Vec3::from(self.look_from) - Vec3::from(point)).length(),
+            None => self.focus_distance,
+        }
+    }
+
+    /// Build the camera without validating settings, see `try_initialize`
     pub fn initialize(&self) -> Camera {
         let aspect_ratio = self.aspect_ratio;
         let image_height = self.image_height;
         let image_width = image_height * aspect_ratio;
 
-        let samples_per_pixel = self.samples_per_pixel;
+        // at least one sample so the per pixel average is never a division by zero
+        let samples_per_pixel = self.samples_per_pixel.max(1);
+        let pixel_samples_scale = 1.0 / samples_per_pixel as f64;
 
         let max_depth = self.max_depth;
+        if max_depth == 0 {
+            eprintln!("warning: max_depth is 0, rays never gather light and every pixel is black");
+        }
+
+        let focus_distance = self.effective_focus_distance();
 
         // use vertical fov to calculate viewport height
         let camera_delta_v = Vec3::from(self.look_from) - Vec3::from(self.look_at);
         let theta = degrees_to_radians(self.vertical_fov);
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_distance;
+        let viewport_height = 2.0 * h * focus_distance;
 
         // camera center aka eye point where all rays are cast from
         // right-handed coordinates
@@ -132,6 +549,10 @@ impl CameraBuilder {
         let u = self.vup.cross(&w).unit();
         let v = w.cross(&u);
 
+        // bank by rotating u and v around w
+        let (sin_roll, cos_roll) = degrees_to_radians(self.roll).sin_cos();
+        let (u, v) = (cos_roll * u + sin_roll * v, cos_roll * v - sin_roll * u);
+
         // vectors along viewport edges
         // vector across viewport horizontal edge
         let viewport_u = viewport_width * u;
@@ -144,7 +565,7 @@ impl CameraBuilder {
 
         // Calculate the camera defocus disk basis vectors.
         let defocus_angle = self.defocus_angle;
-        let defocus_radius = self.focus_distance * (degrees_to_radians(defocus_angle / 2.0)).tan();
+        let defocus_radius = focus_distance * (degrees_to_radians(defocus_angle / 2.0)).tan();
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
 
@@ -154,7 +575,7 @@ impl CameraBuilder {
         // subtract focal to move from camera to viewport
         // subtract half viewport u + v to move from center to upper left corner
         let viewport_upper_left =
-            center - (self.focus_distance * w) - viewport_u / 2.0 - viewport_v / 2.0;
+            center - (focus_distance * w) - viewport_u / 2.0 - viewport_v / 2.0;
 
         let pixel_00 = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
 
@@ -162,6 +583,7 @@ impl CameraBuilder {
             image_width,
             image_height,
             samples_per_pixel,
+            pixel_samples_scale,
             max_depth,
             center,
             pixel_00,
@@ -170,15 +592,32 @@ impl CameraBuilder {
             defocus_angle,
             defocus_disk_u,
             defocus_disk_v,
+            dither: self.dither,
+            gamma: self.gamma,
+            linear: self.linear,
+            quiet: self.quiet,
+            clamp_radiance: self.clamp_radiance,
+            background: self.background.clone(),
+            check_energy: self.check_energy,
+            missing_material: self.missing_material,
+            sample_pairing: self.sample_pairing,
+            aperture_blades: self.aperture_blades,
+            output: self.output.clone(),
+            save_interval: self.save_interval,
+            parallelism: self.parallelism,
+            lights: self.lights.clone(),
+            hit_epsilon: self.hit_epsilon,
+            save_meta: self.save_meta,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Camera {
     image_width: f64,
     image_height: f64,
     samples_per_pixel: u32,
+    pixel_samples_scale: f64,
     max_depth: u32,
     center: Vec3,
     pixel_00: Vec3,
@@ -187,23 +626,58 @@ pub struct Camera {
     defocus_angle: f64,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
+    dither: bool,
+    gamma: f64,
+    linear: bool,
+    pub(crate) quiet: bool,
+    clamp_radiance: Option<f64>,
+    background: Background,
+    check_energy: bool,
+    missing_material: bool,
+    sample_pairing: SamplePairing,
+    aperture_blades: u32,
+    pub(crate) output: String,
+    save_interval: Option<time::Duration>,
+    parallelism: Parallelism,
+    lights: Vec<Light>,
+    hit_epsilon: f64,
+    save_meta: bool,
 }
 
 impl Camera {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> CameraBuilder {
         CameraBuilder::new()
     }
 
     pub fn debug<T: Hittable>(&self, world: &T, x: u32, y: u32) {
-        let ray = self.get_ray(x, y);
-        let color = ray_color(&ray, world, self.max_depth);
+        let ray = self.get_ray(x, y, 0);
+        let color = self.ray_color(&ray, world, self.max_depth);
         eprintln!("ray={:?}", ray);
         eprintln!("color={:?}", color);
     }
 
     pub async fn render<T: Hittable>(&self, world: &T) {
+        let progress = Progress::new(self.progress_max()).quiet(self.quiet);
+        self.render_with_progress(world, progress).await;
+    }
+
+    /// Total work `render` reports to its progress, in camera samples rather
+    /// than pixels so the bar and ETA follow the actual rendering effort
+    pub fn progress_max(&self) -> usize {
+        self.image_width() * self.image_height() * self.samples_per_pixel as usize
+    }
+
+    /// Render with a caller configured `Progress`, e.g. one with an `on_update` callback
+    /// progress is advanced by the samples each pixel or row took so its max should be `progress_max`
+    pub async fn render_with_progress<T: Hittable>(&self, world: &T, progress: Progress) {
+        let start = time::Instant::now();
         let width = self.image_width();
         let height = self.image_height();
+        let quiet = self.quiet;
+
+        // output settings, pixels are filled in on every save
+        let output_ppm = self.output_ppm(vec![]);
 
         // pre-allocate vector with correct pixel array size
         // wrap with RwLock to allow shared access across threads
@@ -211,53 +685,521 @@ impl Camera {
 
         // channel + thread to aggregate pixel updates
         let pixels_aggregator = Arc::clone(&pixels);
-        let (tx, rx) = mpsc::channel::<(usize, Color)>();
+        let (tx, rx) = mpsc::channel::<PixelUpdate>();
         let aggregator = tokio::spawn(async move {
-            while let Ok((index, color)) = rx.recv() {
+            while let Ok(update) = rx.recv() {
                 let mut pixels = pixels_aggregator.write().unwrap();
-                pixels[index] = color;
+                match update {
+                    PixelUpdate::Pixel(index, color) => pixels[index] = color,
+                    PixelUpdate::Run(index, colors) => {
+                        pixels[index..index + colors.len()].copy_from_slice(&colors)
+                    }
+                }
             }
         });
 
         // spawn background thrad to save periodically
-        let pixels_saver = Arc::clone(&pixels);
-        let saver = tokio::spawn(async move {
-            let mut timer = time::interval(time::Duration::from_secs(2));
-            loop {
-                timer.tick().await;
-                let pixels = pixels_saver.read().unwrap().clone();
-                save_ppm(width, height, &pixels, false).await;
-            }
+        let saver = self.save_interval.map(|interval| {
+            let pixels_saver = Arc::clone(&pixels);
+            let output = self.output.clone();
+            let mut ppm = output_ppm.clone();
+            tokio::spawn(async move {
+                let mut timer = time::interval(interval);
+                loop {
+                    timer.tick().await;
+                    ppm.pixels = pixels_saver.read().unwrap().clone();
+                    save_ppm(&output, &ppm, quiet).await;
+                }
+            })
         });
 
         // wrap render in block so it drops progress thread correctly
         // printing the final progress bar update before saved message
-        let progress = Progress::new(pixels.read().unwrap().len());
         let progress = progress.render(15);
 
-        (0..width * height).into_par_iter().for_each(|index| {
-            let y = (index / width) as u32;
-            let x = (index % width) as u32;
-
-            // calculate pixel and send to aggregator channel
-            let pixel = self.get_pixel(world, x, y);
-            tx.send((index, pixel)).unwrap();
-
-            // pixel done, update progress
-            progress.inc();
-        });
+        match self.parallelism {
+            Parallelism::Pixel => (0..width * height).into_par_iter().for_each(|index| {
+                let y = (index / width) as u32;
+                let x = (index % width) as u32;
+
+                // calculate pixel and send to aggregator channel
+                let mut stats = RenderStats::default();
+                let pixel = self.get_pixel_counted(world, x, y, &mut stats);
+                tx.send(PixelUpdate::Pixel(index, pixel)).unwrap();
+
+                // pixel done, update progress by the samples it took
+                progress.inc_by(stats.samples as usize);
+            }),
+            Parallelism::Scanline => (0..height).into_par_iter().for_each(|y| {
+                // calculate whole row and send to aggregator channel
+                let mut stats = RenderStats::default();
+                let row = (0..width)
+                    .map(|x| self.get_pixel_counted(world, x as u32, y as u32, &mut stats))
+                    .collect();
+                tx.send(PixelUpdate::Run(y * width, row)).unwrap();
+
+                // row done, update progress by the samples it took
+                progress.inc_by(stats.samples as usize);
+            }),
+            Parallelism::Chunk(chunk) => {
+                let chunk = chunk.max(1);
+                let pixels = width * height;
+                (0..pixels.div_ceil(chunk)).into_par_iter().for_each(|n| {
+                    // calculate run of pixels and send to aggregator channel
+                    let start = n * chunk;
+                    let mut stats = RenderStats::default();
+                    let run = (start..(start + chunk).min(pixels))
+                        .map(|index| {
+                            let (x, y) = ((index % width) as u32, (index / width) as u32);
+                            self.get_pixel_counted(world, x, y, &mut stats)
+                        })
+                        .collect();
+                    tx.send(PixelUpdate::Run(start, run)).unwrap();
+
+                    // run done, update progress by the samples it took
+                    progress.inc_by(stats.samples as usize);
+                })
+            }
+        }
 
         // close channel and wait for aggregator to finish
         drop(tx);
         aggregator.await.unwrap();
-        saver.abort();
+        if let Some(saver) = saver {
+            saver.abort();
+        }
 
         // print progress one last time
         drop(progress);
 
         // one last save
-        let pixels = pixels.read().unwrap().clone();
-        save_ppm(width, height, &pixels, true).await;
+        let ppm = ppm::V3 {
+            pixels: pixels.read().unwrap().clone(),
+            ..output_ppm
+        };
+        save_ppm(&self.output, &ppm, true).await;
+
+        if self.save_meta {
+            self.save_render_meta(None, start.elapsed()).await;
+        }
+    }
+
+    /// Save an image from `render_seeded` to the output like `render` does,
+    /// with a sidecar recording `seed_value` when `save_meta` is set
+    pub async fn save_seeded(&self, image: &Image, seed_value: u64, elapsed: time::Duration) {
+        save_ppm(&self.output, &self.output_ppm(image.pixels.clone()), true).await;
+
+        if self.save_meta {
+            self.save_render_meta(Some(seed_value), elapsed).await;
+        }
+    }
+
+    /// Output file settings of this camera around `pixels`
+    fn output_ppm(&self, pixels: Vec<Color>) -> ppm::V3 {
+        ppm::V3 {
+            width: self.image_width(),
+            height: self.image_height(),
+            pixels,
+            dither: self.dither,
+            gamma: self.gamma,
+            linear: self.linear,
+            comments: vec![],
+            sixteen_bit: false,
+            binary: false,
+        }
+    }
+
+    /// Settings of this camera for a render that took `elapsed`, `seed` for
+    /// renders that reseed, e.g. `render_seeded`
+    #[cfg(feature = "serde")]
+    pub fn render_meta(&self, seed: Option<u64>, elapsed: time::Duration) -> RenderMeta {
+        RenderMeta {
+            width: self.image_width(),
+            height: self.image_height(),
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            seed,
+            elapsed_seconds: elapsed.as_secs_f64(),
+            camera: CameraMeta {
+                center: self.center.to_array(),
+                pixel_00: self.pixel_00.to_array(),
+                pixel_delta_u: self.pixel_delta_u.to_array(),
+                pixel_delta_v: self.pixel_delta_v.to_array(),
+                defocus_angle: self.defocus_angle,
+                defocus_disk_u: self.defocus_disk_u.to_array(),
+                defocus_disk_v: self.defocus_disk_v.to_array(),
+                aperture_blades: self.aperture_blades,
+                hit_epsilon: self.hit_epsilon,
+                clamp_radiance: self.clamp_radiance,
+                gamma: self.gamma,
+                linear: self.linear,
+                dither: self.dither,
+            },
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    async fn save_render_meta(&self, seed: Option<u64>, elapsed: time::Duration) {
+        let meta = self.render_meta(seed, elapsed);
+        if let Err(error) = meta.save(&RenderMeta::sidecar_path(&self.output)).await {
+            eprintln!("{error}");
+        }
+    }
+
+    #[cfg(not(feature = "serde"))]
+    async fn save_render_meta(&self, _seed: Option<u64>, _elapsed: time::Duration) {
+        eprintln!("warning: save_meta needs the serde feature, no metadata saved");
+    }
+
+    /// Render one sample per pixel at a time, calling `sink` with the current
+    /// averaged image every `every` samples and once more after the final sample
+    pub fn render_streaming<T, F>(&self, world: &T, every: u32, mut sink: F) -> Image
+    where
+        T: Hittable,
+        F: FnMut(&Image),
+    {
+        let width = self.image_width();
+        let height = self.image_height();
+        let every = every.max(1);
+
+        let mut sums = vec![Color::BLACK; width * height];
+        let mut image = Image::new(width, height);
+
+        for sample in 1..=self.samples_per_pixel {
+            self.render_pass(world, &mut sums, sample - 1);
+
+            if sample % every == 0 || sample == self.samples_per_pixel {
+                let scale = 1.0 / sample as f64;
+                for (pixel, sum) in image.pixels.iter_mut().zip(&sums) {
+                    *pixel = *sum * scale;
+                }
+
+                sink(&image);
+            }
+        }
+
+        image
+    }
+
+    /// Render without progress output or saving, reseeding the random
+    /// generator for every pixel from `seed_value` and the pixel index so the
+    /// image is identical between runs regardless of thread scheduling, e.g.
+    /// for benchmarks and regression tests, see `save_seeded` to save it
+    ///
+    /// The threads' own generators are restored afterwards, so random values
+    /// drawn around the render are unaffected by it
+    pub fn render_seeded<T: Hittable>(&self, world: &T, seed_value: u64) -> Image {
+        let width = self.image_width();
+        let mut image = Image::new(width, self.image_height());
+
+        image
+            .pixels
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, pixel)| {
+                let y = (index / width) as u32;
+                let x = (index % width) as u32;
+
+                *pixel = with_seed(seed_for(seed_value, index as u64), || {
+                    self.get_pixel(world, x, y)
+                });
+            });
+
+        image
+    }
+
+    /// Render like `render_seeded` but seeding every sample of every pixel,
+    /// saving progress to `checkpoint_path` after every `every` samples per
+    /// pixel so an interrupted render can continue with `render_resume`
+    pub fn render_checkpointed<T: Hittable>(
+        &self,
+        world: &T,
+        seed_value: u64,
+        checkpoint_path: &str,
+        every: u32,
+    ) -> Result<Image, std::io::Error> {
+        let checkpoint = Checkpoint::new(self.image_width(), self.image_height(), seed_value);
+        self.continue_checkpoint(world, checkpoint, checkpoint_path, every)
+    }
+
+    /// Continue a render saved by `render_checkpointed`, adding samples until
+    /// every pixel has `samples_per_pixel`, e.g. after the process was killed
+    /// or to refine a finished render with a higher sample count
+    ///
+    /// Samples continue the seeded sequence so the result is identical to
+    /// rendering all samples in one go
+    pub fn render_resume<T: Hittable>(
+        &self,
+        world: &T,
+        checkpoint_path: &str,
+        every: u32,
+    ) -> Result<Image, std::io::Error> {
+        let checkpoint = Checkpoint::load(checkpoint_path)?;
+
+        if (checkpoint.width, checkpoint.height) != (self.image_width(), self.image_height()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "checkpoint is {}x{}, camera renders {}x{}",
+                    checkpoint.width,
+                    checkpoint.height,
+                    self.image_width(),
+                    self.image_height()
+                ),
+            ));
+        }
+
+        self.continue_checkpoint(world, checkpoint, checkpoint_path, every)
+    }
+
+    fn continue_checkpoint<T: Hittable>(
+        &self,
+        world: &T,
+        mut checkpoint: Checkpoint,
+        checkpoint_path: &str,
+        every: u32,
+    ) -> Result<Image, std::io::Error> {
+        let width = self.image_width();
+        let every = every.max(1);
+        let seed_value = checkpoint.seed;
+
+        loop {
+            let done = checkpoint
+                .samples
+                .iter()
+                .copied()
+                .min()
+                .unwrap_or(self.samples_per_pixel);
+            if done >= self.samples_per_pixel {
+                break;
+            }
+            let target = done.saturating_add(every).min(self.samples_per_pixel);
+
+            checkpoint
+                .sums
+                .par_iter_mut()
+                .zip(checkpoint.samples.par_iter_mut())
+                .enumerate()
+                .for_each(|(index, (sum, samples))| {
+                    let y = (index / width) as u32;
+                    let x = (index % width) as u32;
+                    let pixel_seed = seed_for(seed_value, index as u64);
+
+                    while *samples < target {
+                        *sum += with_seed(seed_for(pixel_seed, *samples as u64), || {
+                            self.sample_pixel(world, x, y, *samples)
+                        });
+                        *samples += 1;
+                    }
+                });
+
+            checkpoint.save(checkpoint_path)?;
+        }
+
+        Ok(checkpoint.image())
+    }
+
+    /// Render without progress output or saving, also counting the rays
+    /// traced, returns the averaged pixels in row-major order
+    ///
+    /// Counters accumulate per rayon task and are summed once at the end
+    pub fn render_with_stats<T: Hittable>(&self, world: &T) -> (Vec<Color>, RenderStats) {
+        let start = time::Instant::now();
+        let width = self.image_width();
+        let mut pixels = vec![Color::BLACK; width * self.image_height()];
+
+        let stats = pixels
+            .par_iter_mut()
+            .enumerate()
+            .fold(RenderStats::default, |mut stats, (index, pixel)| {
+                let y = (index / width) as u32;
+                let x = (index % width) as u32;
+
+                let mut sum = Color::BLACK;
+                for sample in 0..self.samples_per_pixel {
+                    sum += self.sample_pixel_counted(world, x, y, sample, &mut stats);
+                }
+                *pixel = sum * self.pixel_samples_scale;
+
+                stats
+            })
+            .reduce(RenderStats::default, |a, b| a + b);
+
+        let stats = RenderStats {
+            elapsed: start.elapsed(),
+            ..stats
+        };
+
+        (pixels, stats)
+    }
+
+    /// Render on the calling thread alone into a PPM with this camera's
+    /// output settings, for callers that parallelize across cameras instead,
+    /// see `animation::render_sequence_parallel`
+    pub(crate) fn render_serial<T: Hittable>(&self, world: &T) -> ppm::V3 {
+        let width = self.image_width();
+        let height = self.image_height();
+        let pixels = (0..width * height)
+            .map(|index| self.get_pixel(world, (index % width) as u32, (index / width) as u32))
+            .collect();
+
+        self.output_ppm(pixels)
+    }
+
+    /// Render `band_height` rows at a time straight into a binary (P6) PPM
+    /// at `path`, each band written and freed before the next starts, e.g.
+    /// for 8K images whose whole buffer of `Color`s would not fit in memory
+    ///
+    /// Peak memory is a single band, rows within a band render in parallel.
+    /// Progress is reported like `render` unless `quiet`, the file is
+    /// written to `<path>.tmp` and renamed once complete.
+    pub fn render_banded<T: Hittable>(
+        &self,
+        world: &T,
+        path: &str,
+        band_height: usize,
+    ) -> Result<(), std::io::Error> {
+        save_atomic(path, |writer| {
+            self.render_banded_to(world, writer, band_height)
+        })
+    }
+
+    /// Same as `render_banded` but writing the PPM to `writer`, e.g. a
+    /// socket or a compressing stream, stops at the first failed write
+    pub fn render_banded_to<T: Hittable, W: std::io::Write>(
+        &self,
+        world: &T,
+        writer: &mut W,
+        band_height: usize,
+    ) -> Result<(), std::io::Error> {
+        let width = self.image_width();
+        let height = self.image_height();
+        let band_height = band_height.max(1);
+
+        // output settings, pixels are passed in one band at a time
+        let ppm = ppm::V3 {
+            binary: true,
+            ..self.output_ppm(vec![])
+        };
+        ppm.write_header(writer)?;
+
+        // dropped on any early return, which stops its thread short of max
+        let progress = Progress::new(self.progress_max())
+            .quiet(self.quiet)
+            .render(15);
+        let mut band = Vec::with_capacity(width * band_height.min(height));
+
+        for top in (0..height).step_by(band_height) {
+            let rows = band_height.min(height - top);
+            band.clear();
+            band.resize(width * rows, Color::BLACK);
+
+            band.par_chunks_mut(width)
+                .enumerate()
+                .for_each(|(row, pixels)| {
+                    let y = (top + row) as u32;
+                    let mut stats = RenderStats::default();
+                    for (x, pixel) in pixels.iter_mut().enumerate() {
+                        *pixel = self.get_pixel_counted(world, x as u32, y, &mut stats);
+                    }
+                    progress.inc_by(stats.samples as usize);
+                });
+
+            ppm.write_rows(writer, top, &band)?;
+        }
+
+        Ok(())
+    }
+
+    /// Render without progress output or saving into RGBA8 bytes encoded
+    /// with this camera's gamma, linear and dither settings, returned with
+    /// the image width and height, e.g. to upload as a GPU texture
+    pub fn render_rgba8<T: Hittable>(&self, world: &T) -> (Vec<u8>, usize, usize) {
+        let (pixels, _) = self.render_with_stats(world);
+        let image = Image {
+            width: self.image_width(),
+            height: self.image_height(),
+            pixels,
+        };
+
+        let bytes = image.to_rgba8_with(self.gamma, self.linear, self.dither);
+        (bytes, image.width, image.height)
+    }
+
+    /// Render the same view at several resolutions, each scale factor dividing
+    /// the image dimensions, e.g. `&[1, 2, 4]` for full, half and quarter size
+    ///
+    /// Samples per pixel grow with the square of the scale so every image
+    /// takes the same number of samples per unit of viewport area, making
+    /// differences between them down to resolution (aliasing) alone
+    pub fn render_multiscale<T: Hittable>(&self, world: &T, scales: &[u32]) -> Vec<Image> {
+        scales
+            .iter()
+            .map(|&scale| self.scaled(scale).render_streaming(world, u32::MAX, |_| {}))
+            .collect()
+    }
+
+    /// Diagnostic render of where noise concentrates, each pixel colored by
+    /// the variance of its samples' luminance instead of their average
+    ///
+    /// Variance v maps to v / (1 + v) on a black, blue, green, yellow, red
+    /// heatmap so images rendered with different settings compare directly.
+    /// Returns the pixels in row-major order, all black with a single sample.
+    pub fn render_debug_variance<T: Hittable>(&self, world: &T) -> Vec<Color> {
+        let width = self.image_width();
+        let mut pixels = vec![Color::BLACK; width * self.image_height()];
+
+        pixels
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, pixel)| {
+                let y = (index / width) as u32;
+                let x = (index % width) as u32;
+
+                // welford's running mean and sum of squared differences
+                let mut mean = 0.0;
+                let mut m2 = 0.0;
+                for sample in 0..self.samples_per_pixel {
+                    let value = self.sample_pixel(world, x, y, sample).luminance();
+                    let delta = value - mean;
+                    mean += delta / (sample + 1) as f64;
+                    m2 += delta * (value - mean);
+                }
+
+                let variance = if self.samples_per_pixel > 1 {
+                    m2 / (self.samples_per_pixel - 1) as f64
+                } else {
+                    0.0
+                };
+
+                *pixel = heatmap(variance / (1.0 + variance));
+            });
+
+        pixels
+    }
+
+    // camera covering the same viewport with pixels `scale` times larger
+    fn scaled(&self, scale: u32) -> Camera {
+        let scale = scale.max(1);
+        let factor = scale as f64;
+
+        let viewport_upper_left = self.pixel_00 - 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        let pixel_delta_u = self.pixel_delta_u * factor;
+        let pixel_delta_v = self.pixel_delta_v * factor;
+        let samples_per_pixel = self
+            .samples_per_pixel
+            .saturating_mul(scale.saturating_mul(scale));
+
+        Camera {
+            image_width: self.image_width / factor,
+            image_height: self.image_height / factor,
+            samples_per_pixel,
+            pixel_samples_scale: 1.0 / samples_per_pixel as f64,
+            pixel_00: viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v),
+            pixel_delta_u,
+            pixel_delta_v,
+            ..self.clone()
+        }
     }
 
     pub fn image_width(&self) -> usize {
@@ -268,31 +1210,122 @@ impl Camera {
         self.image_height as usize
     }
 
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Eye point rays start from, the center of the lens
+    pub fn center(&self) -> Point3 {
+        Point3::from(self.center)
+    }
+
+    /// Center of the upper left pixel on the focus plane
+    pub fn pixel_00(&self) -> Point3 {
+        Point3::from(self.pixel_00)
+    }
+
+    /// Offset from one pixel center to the next one to the right
+    pub fn pixel_delta_u(&self) -> Vec3 {
+        self.pixel_delta_u
+    }
+
+    /// Offset from one pixel center to the next one below
+    pub fn pixel_delta_v(&self) -> Vec3 {
+        self.pixel_delta_v
+    }
+
     fn get_pixel<T: Hittable>(&self, world: &T, x: u32, y: u32) -> Color {
-        let mut pixel_vec3 = Vec3::from(Color::new(0.0, 0.0, 0.0));
+        self.get_pixel_counted(world, x, y, &mut RenderStats::default())
+    }
+
+    fn get_pixel_counted<T: Hittable>(
+        &self,
+        world: &T,
+        x: u32,
+        y: u32,
+        stats: &mut RenderStats,
+    ) -> Color {
+        let pixel: Color = (0..self.samples_per_pixel)
+            .map(|sample| self.sample_pixel_counted(world, x, y, sample, stats))
+            .sum();
+
+        pixel * self.pixel_samples_scale
+    }
 
-        for _sample in 0..self.samples_per_pixel {
-            let ray = self.get_ray(x, y);
-            let color = ray_color(&ray, world, self.max_depth);
-            pixel_vec3 += Vec3::from(color);
+    /// `sample` is the index of this sample among the pixel's samples
+    fn sample_pixel<T: Hittable>(&self, world: &T, x: u32, y: u32, sample: u32) -> Color {
+        self.sample_pixel_counted(world, x, y, sample, &mut RenderStats::default())
+    }
+
+    fn sample_pixel_counted<T: Hittable>(
+        &self,
+        world: &T,
+        x: u32,
+        y: u32,
+        sample: u32,
+        stats: &mut RenderStats,
+    ) -> Color {
+        let ray = self.get_ray(x, y, sample);
+        stats.samples += 1;
+        let color = self.trace(&ray, world, self.max_depth, stats);
+
+        match self.clamp_radiance {
+            Some(max) => Color::new(color.x().min(max), color.y().min(max), color.z().min(max)),
+            None => color,
         }
+    }
 
-        let pixel_vec3 = pixel_vec3 / self.samples_per_pixel as f64;
+    /// Add sample number `sample` to the running sum of every pixel
+    pub(crate) fn render_pass<T: Hittable>(&self, world: &T, sums: &mut [Color], sample: u32) {
+        let width = self.image_width();
 
-        Color::from(pixel_vec3)
+        sums.par_iter_mut().enumerate().for_each(|(index, sum)| {
+            let y = (index / width) as u32;
+            let x = (index % width) as u32;
+            *sum += self.sample_pixel(world, x, y, sample);
+        });
     }
 
-    fn get_ray(&self, x: u32, y: u32) -> Ray {
+    /// Ray from the center of the lens through the center of pixel (x, y),
+    /// without the jitter and defocus of rendered samples
+    pub fn center_ray(&self, x: u32, y: u32) -> Ray {
+        let pixel_center =
+            self.pixel_00 + (x as f64 * self.pixel_delta_u) + (y as f64 * self.pixel_delta_v);
+
+        Ray::new(Point3::from(self.center), pixel_center - self.center)
+    }
+
+    /// First surface under the center of pixel (x, y), e.g. to find what was
+    /// clicked in a viewer. See `HittableList::hit_with_index` for which object
+    /// it belongs to.
+    pub fn trace_pixel<T: Hittable>(&self, world: &T, x: u32, y: u32) -> Option<HitRecord> {
+        world.hit(&self.center_ray(x, y), self.hit_epsilon, f64::INFINITY)
+    }
+
+    /// Camera ray for the `sample`'th sample of pixel (x, y), jittered within
+    /// the pixel and across the lens the same way rendering does
+    pub fn get_ray(&self, x: u32, y: u32, sample: u32) -> Ray {
         // ray originating from defocus disk and directed
         // at a randomly sampled point around pixel (x, y)
-        let offset = sample_square();
+        let (offset, lens) = self.sample_offsets(x, y, sample);
 
         let pixel_sample = self.pixel_00
-            + ((x as f64 + offset.x()) * self.pixel_delta_u)
-            + ((y as f64 + offset.y()) * self.pixel_delta_v);
+            + ((x as f64 + offset[0] - 0.5) * self.pixel_delta_u)
+            + ((y as f64 + offset[1] - 0.5) * self.pixel_delta_v);
 
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
+        } else if self.aperture_blades >= 3 {
+            let [u, v] = lens.unwrap_or_else(|| [random_f64(), random_f64()]);
+            let p = square_to_polygon(u, v, self.aperture_blades);
+            self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
+        } else if let Some([u, v]) = lens {
+            let p = square_to_disk(u, v);
+            self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
         } else {
             self.defocus_disk_sample()
         };
@@ -301,48 +1334,186 @@ impl Camera {
         Ray::new(Point3::from(ray_origin), ray_direction)
     }
 
+    // position within the pixel and on the lens, both in [0, 1)^2, for the
+    // sample'th sample of pixel (x, y), lens is None for independent sampling
+    // which draws directly on the lens disk instead
+    fn sample_offsets(&self, x: u32, y: u32, sample: u32) -> ([f64; 2], Option<[f64; 2]>) {
+        if self.sample_pairing == SamplePairing::Independent {
+            let offset = sample_square();
+            return ([offset.x() + 0.5, offset.y() + 0.5], None);
+        }
+
+        // largest square grid the samples fill completely, a partly used grid
+        // would leave its last cells unsampled in every pixel and bias the
+        // average position, samples past it are drawn independently
+        let side = ((self.samples_per_pixel as f64).sqrt().floor() as u32).max(1);
+        let cells = side * side;
+        if sample >= cells {
+            return (
+                [random_f64(), random_f64()],
+                Some([random_f64(), random_f64()]),
+            );
+        }
+
+        let jittered = |cell: u32| {
+            [
+                ((cell % side) as f64 + random_f64()) / side as f64,
+                ((cell / side) as f64 + random_f64()) / side as f64,
+            ]
+        };
+
+        let lens_cell = match self.sample_pairing {
+            SamplePairing::Decorrelated => {
+                let pixel = y as u64 * self.image_width() as u64 + x as u64;
+                permute(sample, cells, seed_for(0, pixel) as u32)
+            }
+            _ => sample,
+        };
+
+        (jittered(sample), Some(jittered(lens_cell)))
+    }
+
     fn defocus_disk_sample(&self) -> Vec3 {
         let p = random_unit_disk();
-        let defocus_p = self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v);
-        defocus_p
+        self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
-}
 
-fn lerp(t: f64, start: Vec3, end: Vec3) -> Vec3 {
-    (1.0 - t) * start + t * end
-}
+    fn ray_color<T: Hittable>(&self, ray: &Ray, world: &T, depth: u32) -> Color {
+        self.trace(ray, world, depth, &mut RenderStats::default())
+    }
+
+    // unoccluded light from every analytic light reflected at a diffuse hit
+    fn direct_light<T: Hittable>(&self, ray: &Ray, hit: &HitRecord, world: &T) -> Color {
+        let mut color = Color::BLACK;
+        if self.lights.is_empty() {
+            return color;
+        }
+
+        let outgoing = -ray.direction().unit();
+
+        for light in &self.lights {
+            let Some(sample) = light.sample(&hit.p) else {
+                continue;
+            };
+
+            let cos_theta = hit.normal.dot(&sample.direction);
+            if cos_theta <= 0.0 {
+                continue;
+            }
+
+            let Some(brdf) = hit
+                .material
+                .diffuse_brdf(&hit.normal, &outgoing, &sample.direction)
+            else {
+                continue;
+            };
+
+            // same offset as bounces to avoid self-intersection, stopping short of the light
+            let shadow_ray = Ray::new(hit.p, sample.direction);
+            if world
+                .hit(&shadow_ray, self.hit_epsilon, sample.distance)
+                .is_some()
+            {
+                continue;
+            }
 
-fn ray_color<T: Hittable>(ray: &Ray, world: &T, depth: u32) -> Color {
-    // eprintln!("ray_color: depth={depth}, ray={:?}", ray);
+            color += brdf * sample.irradiance * cos_theta;
+        }
 
-    // exceeded ray bounce limit, stop gathering light
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
+        color
     }
 
-    // lower bound t=0.001 to avoid self-intersect near surface
-    if let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) {
-        if let Some(scatter_record) = hit.material.scatter(ray, hit) {
-            // early return if color is provided, e.g. Debug material
-            if let Some(color) = scatter_record.color {
-                return color;
+    // ray_color counting into plain per-thread stats, cheaper than atomics
+    fn trace<T: Hittable>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        depth: u32,
+        stats: &mut RenderStats,
+    ) -> Color {
+        // exceeded ray bounce limit, stop gathering light
+        if depth == 0 {
+            stats.truncated += 1;
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        stats.rays += 1;
+
+        // lower bound of t to avoid self-intersect near surface
+        if let Some(hit) = world.hit(ray, self.hit_epsilon, f64::INFINITY) {
+            stats.hits += 1;
+
+            if self.missing_material && hit.material.is_empty() {
+                return missing_material_color(&hit.p);
             }
 
-            let attentuation = Vec3::from(scatter_record.attenuation);
-            let next_ray_color = Vec3::from(ray_color(&scatter_record.ray, world, depth - 1));
-            return Color::from(attentuation * next_ray_color);
+            let emitted = hit.material.emitted(&hit) + self.direct_light(ray, &hit, world);
+
+            if let Some(scatter_record) = hit.material.scatter(ray, hit) {
+                // early return if color is provided, e.g. Debug material
+                if let Some(color) = scatter_record.color {
+                    return color;
+                }
+
+                // dispersive dielectrics weight their single traced channel by 3
+                // on purpose, their expected attenuation never exceeds 1
+                if self.check_energy
+                    && scatter_record.gains_energy()
+                    && !hit.material.is_dielectric()
+                {
+                    material::warn_energy_gain(&hit.material, scatter_record.attenuation);
+                }
+
+                // the path keeps its dispersion channel through other materials
+                let scattered = scatter_record
+                    .ray
+                    .with_channel(scatter_record.ray.channel().or(ray.channel()));
+
+                return emitted
+                    + scatter_record.attenuation * self.trace(&scattered, world, depth - 1, stats);
+            }
+
+            return emitted;
         }
 
-        return Color::new(0.0, 0.0, 0.0);
+        stats.escapes += 1;
+        self.background.color(ray)
     }
+}
+
+// finished pixels sent to the aggregator of `render_with_progress`, a single
+// pixel stays off the heap, rows and chunks are runs starting at an index
+enum PixelUpdate {
+    Pixel(usize, Color),
+    Run(usize, Vec<Color>),
+}
+
+// 3d checker of unit cells alternating magenta and black
+fn missing_material_color(p: &Point3) -> Color {
+    let cells = p.x.floor() as i64 + p.y.floor() as i64 + p.z.floor() as i64;
 
-    let unit_direction = ray.direction().unit();
-    let a = 0.5 * (unit_direction.y() + 1.0);
-    let white = Color::new(1.0, 1.0, 1.0);
-    let blue = Color::new(0.5, 0.7, 1.0);
+    if cells.rem_euclid(2) == 0 {
+        Color::new(1.0, 0.0, 1.0)
+    } else {
+        Color::BLACK
+    }
+}
 
-    // Color::new(0.0, 0.0, 0.0)
-    Color::from(lerp(a, white.into(), blue.into()))
+// t in [0, 1] from black through blue, green and yellow to red
+fn heatmap(t: f64) -> Color {
+    const STOPS: [Color; 5] = [
+        Color::BLACK,
+        Color::BLUE,
+        Color::GREEN,
+        Color::new(1.0, 1.0, 0.0),
+        Color::RED,
+    ];
+
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let n = (scaled as usize).min(STOPS.len() - 2);
+    let f = scaled - n as f64;
+
+    STOPS[n] * (1.0 - f) + STOPS[n + 1] * f
 }
 
 fn sample_square() -> Point3 {
@@ -350,22 +1521,1529 @@ fn sample_square() -> Point3 {
     Point3::new(random_f64() - 0.5, random_f64() - 0.5, 0.0)
 }
 
-async fn save_ppm(width: usize, height: usize, pixels: &Vec<Color>, last: bool) {
+async fn save_ppm(filepath: &str, ppm: &ppm::V3, silent: bool) {
     let timer = time::Instant::now();
 
-    let ppm = ppm::V3 {
-        width,
-        height,
-        pixels: pixels.clone(),
-    };
-
-    if let Err(error) = ppm.save("image.ppm").await {
+    if let Err(error) = ppm.save(filepath).await {
         eprintln!("{error}");
     };
 
-    if !last {
+    if !silent {
         // carriage return and clear line from cursor to end
-        eprint!("\r\x1b[K");
+        if std::io::stderr().is_terminal() {
+            eprint!("\r\x1b[K");
+        }
+
         eprintln!("saved ({:?})", timer.elapsed());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::seed;
+    use crate::core::EnvironmentMap;
+    use crate::core::PointLight;
+    use crate::geo::HittableList;
+    use crate::geo::Mat4;
+    use crate::geo::Sphere;
+    use crate::geo::Triangle;
+    use crate::test::assert;
+
+    fn diffuse_world() -> HittableList {
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(0.0, 0.0, -1.0)
+                .radius(0.5)
+                .material(material::Type::from(material::LambertianParams {
+                    albedo: Color::new(0.5, 0.5, 0.5),
+                    reflectance: 1.0,
+                    uniform: false,
+                }))
+                .build(),
+        );
+        world
+    }
+
+    fn mean_squared_error(a: &Image, b: &Image) -> f64 {
+        let sum: f64 = a
+            .pixels
+            .iter()
+            .zip(&b.pixels)
+            .map(|(a, b)| (Vec3::from(a) - Vec3::from(b)).length_squared())
+            .sum();
+        sum / a.pixels.len() as f64
+    }
+
+    #[test]
+    fn test_samples_per_pixel_zero() {
+        let camera = Camera::new().samples_per_pixel(0).initialize();
+        assert_eq!(camera.samples_per_pixel, 1);
+        assert_eq!(camera.pixel_samples_scale, 1.0);
+    }
+
+    #[test]
+    fn test_samples_per_pixel_zero_render() {
+        // renders like a single sample, never inf or nan
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(0)
+            .initialize();
+        let (pixels, stats) = camera.render_with_stats(&diffuse_world());
+
+        assert_eq!(stats.samples, 4 * 4);
+        for pixel in pixels {
+            assert!(pixel.x().is_finite() && pixel.y().is_finite() && pixel.z().is_finite());
+            assert_ne!(pixel, Color::BLACK);
+        }
+    }
+
+    #[test]
+    fn test_pixel_samples_scale_finite() {
+        for samples_per_pixel in [0, 1, 10, u32::MAX] {
+            let camera = Camera::new()
+                .samples_per_pixel(samples_per_pixel)
+                .initialize();
+            assert!(camera.pixel_samples_scale.is_finite());
+            assert!(camera.pixel_samples_scale > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_pixel_geometry() {
+        // 90 degree fov, 4x4 pixels on a 2x2 viewport one unit down -z
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(3)
+            .max_depth(7)
+            .focus_distance(1.0)
+            .initialize();
+
+        assert_eq!(camera.samples_per_pixel(), 3);
+        assert_eq!(camera.max_depth(), 7);
+        assert_eq!(camera.center(), Point3::new(0.0, 0.0, 0.0));
+
+        let (du, dv, p00) = (
+            camera.pixel_delta_u(),
+            camera.pixel_delta_v(),
+            camera.pixel_00(),
+        );
+        for (actual, expected) in [
+            (du.x(), 0.5),
+            (du.y(), 0.0),
+            (du.z(), 0.0),
+            (dv.x(), 0.0),
+            (dv.y(), -0.5),
+            (dv.z(), 0.0),
+            (p00.x(), -0.75),
+            (p00.y(), 0.75),
+            (p00.z(), -1.0),
+        ] {
+            assert::float(actual, expected, 12);
+        }
+
+        // rays without defocus leave the center towards a point within the pixel
+        for (x, y) in [(0, 0), (3, 1), (2, 3)] {
+            let ray = camera.get_ray(x, y, 0);
+            assert_eq!(*ray.origin(), camera.center());
+
+            let target = Vec3::from(camera.pixel_00())
+                + x as f64 * camera.pixel_delta_u()
+                + y as f64 * camera.pixel_delta_v();
+            let offset = *ray.direction() - target;
+            assert!(
+                offset.x().abs() <= 0.25 + 1e-12 && offset.y().abs() <= 0.25 + 1e-12,
+                "{offset:?}"
+            );
+            assert::float(offset.z(), 0.0, 12);
+        }
+    }
+
+    #[test]
+    fn test_render_debug_variance() {
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(16)
+            .background(Background::Solid(Color::new(0.5, 0.5, 0.5)))
+            .initialize();
+
+        // every sample of the background is the same color
+        let empty = camera.render_debug_variance(&HittableList::new());
+        assert_eq!(empty.len(), camera.image_width() * camera.image_height());
+        assert!(empty.iter().all(|pixel| *pixel == Color::BLACK));
+
+        // bounces off the sphere pick up different parts of the sky, the
+        // corners only see the sky across a single pixel
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(16)
+            .initialize();
+        let pixels = camera.render_debug_variance(&diffuse_world());
+        let width = camera.image_width();
+        let (corner, center) = (pixels[0], pixels[4 * width + width / 2]);
+        assert!(center.z() > 10.0 * corner.z(), "{center:?} {corner:?}");
+
+        // a single sample has no variance
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(1)
+            .initialize();
+        let pixels = camera.render_debug_variance(&diffuse_world());
+        assert!(pixels.iter().all(|pixel| *pixel == Color::BLACK));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        assert_eq!(heatmap(0.0), Color::BLACK);
+        assert_eq!(heatmap(0.25), Color::BLUE);
+        assert_eq!(heatmap(0.5), Color::GREEN);
+        assert_eq!(heatmap(1.0), Color::RED);
+        assert_eq!(heatmap(2.0), Color::RED);
+        assert_eq!(heatmap(0.125), Color::new(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_hit_epsilon() {
+        // diffuse floor far from the origin, where hit points are only
+        // accurate to a few thousandths
+        const OFFSET: f64 = 1e13;
+        let mut world = HittableList::new();
+        let albedo = Color::new(0.5, 0.5, 0.5);
+        let floor = material::Type::from(material::LambertianParams {
+            albedo,
+            reflectance: 1.0,
+            uniform: false,
+        });
+        // slightly tilted, a box padded by a fraction of a unit has no
+        // thickness at these coordinates
+        let corner = |x: f64, y: f64, z: f64| Point3::new(OFFSET + x, OFFSET + y, OFFSET + z);
+        world.add(Triangle::new(
+            corner(-1e4, 0.0, -1e4),
+            corner(-1e4, 0.0, 1e4),
+            corner(1e4, 10.0, 0.0),
+            floor,
+        ));
+
+        let camera = |hit_epsilon: f64| {
+            Camera::new()
+                .image_height(16)
+                .samples_per_pixel(8)
+                .look_from(OFFSET, OFFSET + 100.0, OFFSET)
+                .look_at(OFFSET, OFFSET, OFFSET)
+                .vup(0.0, 0.0, -1.0)
+                .background(Background::Solid(Color::WHITE))
+                .hit_epsilon(hit_epsilon)
+                .quiet(true)
+                .initialize()
+        };
+
+        // every bounce off the floor escapes to the white background, any
+        // darker pixel is the floor hitting itself
+        let acne = |camera: Camera| {
+            camera
+                .render_seeded(&world, 1)
+                .pixels
+                .iter()
+                .filter(|pixel| (pixel.x() - albedo.x()).abs() > 1e-9)
+                .count()
+        };
+
+        assert!(acne(camera(0.001)) > 0);
+        assert_eq!(acne(camera(1.0)), 0);
+    }
+
+    #[test]
+    fn test_trace_pixel() {
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(-1.0, 0.0, -2.0)
+                .radius(0.5)
+                .build(),
+        );
+        world.add(Sphere::builder().center(1.0, 0.0, -2.0).radius(0.5).build());
+
+        let camera = Camera::new()
+            .image_height(5)
+            .defocus_angle(10.0)
+            .initialize();
+
+        // center ray ignores jitter and defocus
+        let ray = camera.center_ray(2, 2);
+        assert_eq!(*ray.origin(), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction().unit(), Vec3::new(0.0, 0.0, -1.0));
+
+        assert!(camera.trace_pixel(&world, 2, 2).is_none());
+
+        // left and right of center, which sphere is under the pixel
+        for (x, expected) in [(1, 0), (3, 1)] {
+            let hit = camera.trace_pixel(&world, x, 2).unwrap();
+            let (index, picked) = world
+                .hit_with_index(&camera.center_ray(x, 2), 0.001, f64::INFINITY)
+                .unwrap();
+            assert_eq!(index, expected);
+            assert_eq!(picked, hit);
+        }
+    }
+
+    #[test]
+    fn test_max_depth_zero() {
+        let world = diffuse_world();
+        let camera = Camera::new().image_height(2).max_depth(0).initialize();
+
+        for y in 0..2 {
+            for x in 0..2 {
+                assert_eq!(camera.get_pixel(&world, x, y), Color::BLACK);
+            }
+        }
+    }
+
+    #[test]
+    fn test_focal_length() {
+        let builder = Camera::new().focal_length(50.0, 24.0);
+        let vertical_fov = builder.vertical_fov;
+        assert::float(vertical_fov, 26.99, 2);
+
+        let world = diffuse_world();
+        let by_focal_length = builder.image_height(8).initialize();
+        let by_fov = Camera::new()
+            .vertical_fov(vertical_fov)
+            .image_height(8)
+            .initialize();
+        assert_eq!(by_focal_length, by_fov);
+
+        for (x, y) in [(0, 0), (4, 4), (7, 2)] {
+            seed(x as u64);
+            let a = by_focal_length.get_pixel(&world, x, y);
+            seed(x as u64);
+            let b = by_fov.get_pixel(&world, x, y);
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_one_sided_light() {
+        // ceiling light at y = 1 facing down, diffuse spheres below and above it
+        let world = |two_sided| {
+            let mut world = HittableList::new();
+            world.add(Triangle::new(
+                Point3::new(-3.0, 1.0, -3.0),
+                Point3::new(3.0, 1.0, -3.0),
+                Point3::new(0.0, 1.0, 3.0),
+                material::Type::from(material::DiffuseLightParams {
+                    emit: Color::new(4.0, 4.0, 4.0),
+                    two_sided,
+                }),
+            ));
+            for y in [0.0, 2.0] {
+                world.add(
+                    Sphere::builder()
+                        .center(0.0, y, 0.0)
+                        .radius(0.3)
+                        .material(material::Type::from(material::LambertianParams {
+                            albedo: Color::new(0.5, 0.5, 0.5),
+                            ..Default::default()
+                        }))
+                        .build(),
+                );
+            }
+            world
+        };
+
+        let camera = Camera::new()
+            .max_depth(4)
+            .background(Background::Solid(Color::BLACK))
+            .initialize();
+
+        // sideways at each sphere, never crossing the light plane
+        let below = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let above = Ray::new(Point3::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        let radiance = |world: &HittableList, ray: &Ray| {
+            (0..2000)
+                .map(|_| camera.ray_color(ray, world, camera.max_depth).x())
+                .sum::<f64>()
+        };
+
+        let one_sided = world(false);
+        assert!(radiance(&one_sided, &below) > 0.0);
+        assert_eq!(radiance(&one_sided, &above), 0.0);
+
+        let two_sided = world(true);
+        assert!(radiance(&two_sided, &below) > 0.0);
+        assert!(radiance(&two_sided, &above) > 0.0);
+
+        // looking straight at the light from below sees it, from above does not
+        let up = Ray::new(Point3::new(1.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let down = Ray::new(Point3::new(1.0, 1.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(
+            camera.ray_color(&up, &one_sided, 1),
+            Color::new(4.0, 4.0, 4.0)
+        );
+        assert_eq!(camera.ray_color(&down, &one_sided, 1), Color::BLACK);
+    }
+
+    #[test]
+    fn test_try_initialize() {
+        assert_eq!(
+            Camera::new().try_initialize(),
+            Ok(Camera::new().initialize())
+        );
+
+        // tiny but valid
+        assert!(Camera::new()
+            .image_height(1)
+            .vertical_fov(179.0)
+            .try_initialize()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_try_initialize_field_of_view() {
+        for fov in [0.0, -10.0, 180.0, 270.0, f64::NAN] {
+            let error = Camera::new()
+                .vertical_fov(fov)
+                .try_initialize()
+                .unwrap_err();
+            assert!(matches!(error, CameraError::InvalidFieldOfView(_)), "{fov}");
+        }
+    }
+
+    #[test]
+    fn test_try_initialize_focus_distance() {
+        for distance in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                Camera::new().focus_distance(distance).try_initialize(),
+                Err(CameraError::InvalidFocusDistance(distance))
+            );
+        }
+
+        // focusing on the eye point is a zero distance too
+        assert_eq!(
+            Camera::new()
+                .focus_on(Point3::new(0.0, 0.0, 0.0))
+                .try_initialize(),
+            Err(CameraError::InvalidFocusDistance(0.0))
+        );
+    }
+
+    #[test]
+    fn test_try_initialize_defocus_angle() {
+        for angle in [-1.0, 180.0] {
+            assert_eq!(
+                Camera::new().defocus_angle(angle).try_initialize(),
+                Err(CameraError::InvalidDefocusAngle(angle))
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_initialize_image_size() {
+        assert_eq!(
+            Camera::new().aspect_ratio(0.0).try_initialize(),
+            Err(CameraError::InvalidAspectRatio(0.0))
+        );
+        assert_eq!(
+            Camera::new().image_height(0).try_initialize(),
+            Err(CameraError::EmptyImage(0, 0))
+        );
+        assert_eq!(
+            Camera::new()
+                .image_height(1)
+                .aspect_ratio(0.5)
+                .try_initialize(),
+            Err(CameraError::EmptyImage(0, 1))
+        );
+    }
+
+    #[test]
+    fn test_try_initialize_view() {
+        assert_eq!(
+            Camera::new()
+                .look_from(1.0, 2.0, 3.0)
+                .look_at(1.0, 2.0, 3.0)
+                .try_initialize(),
+            Err(CameraError::NoViewDirection)
+        );
+
+        // looking straight down with the default +y up
+        let error = Camera::new()
+            .look_from(0.0, 10.0, 0.0)
+            .look_at(0.0, 0.0, 0.0)
+            .try_initialize();
+        assert_eq!(error, Err(CameraError::DegenerateVup));
+
+        // initialize would have produced NaNs
+        let camera = Camera::new()
+            .look_from(0.0, 10.0, 0.0)
+            .look_at(0.0, 0.0, 0.0)
+            .initialize();
+        assert!(camera.pixel_delta_u.x.is_nan());
+
+        // looking straight down, up is the -y side and zero vup
+        assert_eq!(
+            Camera::new()
+                .vup(0.0, -3.0, 0.0)
+                .look_at(0.0, 1.0, 0.0)
+                .try_initialize(),
+            Err(CameraError::DegenerateVup)
+        );
+        assert_eq!(
+            Camera::new().vup(0.0, 0.0, 0.0).try_initialize(),
+            Err(CameraError::DegenerateVup)
+        );
+
+        // any other up works
+        assert!(Camera::new()
+            .look_from(0.0, 10.0, 0.0)
+            .look_at(0.0, 0.0, 0.0)
+            .vup(0.0, 0.0, -1.0)
+            .try_initialize()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_camera_error_display() {
+        assert_eq!(
+            CameraError::DegenerateVup.to_string(),
+            "vup is zero or parallel to the view direction"
+        );
+        assert_eq!(
+            CameraError::InvalidFieldOfView(180.0).to_string(),
+            "vertical fov 180 must be between 0 and 180 degrees"
+        );
+    }
+
+    #[test]
+    fn test_render_banded_write_error() {
+        // accepts the header, fails on the first band
+        struct Full(usize);
+        impl std::io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(std::io::Error::other("disk full"));
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // a drawing progress must not keep the failed render waiting
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(1)
+            .quiet(false)
+            .initialize();
+        let error = camera
+            .render_banded_to(&HittableList::new(), &mut Full(64), 2)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "disk full");
+    }
+
+    #[test]
+    fn test_render_banded() {
+        let path =
+            std::env::temp_dir().join(format!("ray-tracer-banded-{}.ppm", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // bands that do not divide the height, the last one is shorter
+        let builder = || {
+            Camera::new()
+                .aspect_ratio(1.5)
+                .image_height(40)
+                .samples_per_pixel(4)
+                .quiet(true)
+        };
+        let header = b"P6\n60 40\n255\n";
+
+        // exact on a solid background
+        let camera = builder()
+            .background(Background::Solid(Color::new(0.0, 1.0, 0.5)))
+            .initialize();
+        camera.render_banded(&HittableList::new(), path, 7).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let (expected, _, _) = camera.render_rgba8(&HittableList::new());
+        let expected: Vec<u8> = expected.chunks(4).flat_map(|p| p[..3].to_vec()).collect();
+        assert_eq!(bytes[..header.len()], *header);
+        assert_eq!(bytes[header.len()..], expected);
+
+        // within the jitter of the samples across the sky's gradient
+        let camera = builder().initialize();
+        camera
+            .render_banded(&HittableList::new(), path, 16)
+            .unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let (expected, _, _) = camera.render_rgba8(&HittableList::new());
+        let expected: Vec<u8> = expected.chunks(4).flat_map(|p| p[..3].to_vec()).collect();
+        assert_eq!(bytes.len(), header.len() + 60 * 40 * 3);
+        for (a, b) in bytes[header.len()..].iter().zip(&expected) {
+            assert!(a.abs_diff(*b) <= 2, "{a} {b}");
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_render_rgba8() {
+        let camera = Camera::new()
+            .aspect_ratio(2.0)
+            .image_height(3)
+            .samples_per_pixel(2)
+            .background(Background::Solid(Color::new(0.0, 1.0, 0.5)))
+            .initialize();
+
+        let (bytes, width, height) = camera.render_rgba8(&HittableList::new());
+        assert_eq!((width, height), (6, 3));
+        assert_eq!(bytes.len(), width * height * 4);
+        assert_eq!(bytes[4 * 7..4 * 8], [0, 255, 181, 255]);
+    }
+
+    #[test]
+    fn test_render_with_stats() {
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(3)
+            .max_depth(5)
+            .initialize();
+        let samples = (camera.image_width() * camera.image_height() * 3) as u64;
+
+        // nothing to hit, every camera ray escapes straight away
+        let (pixels, stats) = camera.render_with_stats(&HittableList::new());
+        assert_eq!(pixels.len(), 64);
+        assert_eq!(stats.samples, samples);
+        assert_eq!(stats.rays, samples);
+        assert_eq!(stats.escapes, samples);
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.average_path_length(), 1.0);
+        assert!(stats.elapsed > std::time::Duration::ZERO);
+
+        // lossless diffuse sphere, every path ends escaping or truncated
+        let (pixels, stats) = camera.render_with_stats(&diffuse_world());
+        assert!(pixels.iter().any(|p| *p != Color::BLACK));
+        assert_eq!(stats.samples, samples);
+        assert_eq!(stats.rays, stats.hits + stats.escapes);
+        assert_eq!(stats.escapes + stats.truncated, samples);
+        assert!(stats.hits > 0);
+        assert!(stats.average_path_length() > 1.0);
+        assert!(stats.average_path_length() <= 5.0);
+
+        // mirrors facing each other never escape
+        let mut world = HittableList::new();
+        for z in [-1.0, 1.0] {
+            world.add(Triangle::new(
+                Point3::new(-100.0, -100.0, z),
+                Point3::new(100.0, -100.0, z),
+                Point3::new(0.0, 100.0, z),
+                material::Type::from(material::MetalParams {
+                    albedo: Color::WHITE,
+                    ..Default::default()
+                }),
+            ));
+        }
+        let (_, stats) = camera.render_with_stats(&world);
+        assert_eq!(stats.truncated, samples);
+        assert_eq!(stats.rays, samples * 5);
+        assert_eq!(stats.escapes, 0);
+    }
+
+    #[test]
+    fn test_focus_on() {
+        let look_from = Point3::new(13.0, 2.0, 3.0);
+        let look_at = Point3::new(0.0, 0.0, 0.0);
+        let distance = (Vec3::from(look_from) - Vec3::from(look_at)).length();
+
+        let builder = || {
+            Camera::new()
+                .image_height(8)
+                .look_from(look_from.x(), look_from.y(), look_from.z())
+                .look_at(look_at.x(), look_at.y(), look_at.z())
+                .defocus_angle(0.6)
+        };
+
+        let expected = builder().focus_distance(distance).initialize();
+
+        assert_eq!(builder().focus_on(look_at).initialize(), expected);
+
+        // overrides an explicit focus_distance set before or after
+        assert_eq!(
+            builder().focus_distance(3.0).focus_on(look_at).initialize(),
+            expected
+        );
+        assert_eq!(
+            builder().focus_on(look_at).focus_distance(3.0).initialize(),
+            expected
+        );
+        assert_ne!(builder().focus_distance(3.0).initialize(), expected);
+
+        // focus on the sphere in front of the origin
+        let sphere = Point3::new(0.0, 0.0, -4.0);
+        assert_eq!(
+            Camera::new().image_height(8).focus_on(sphere).initialize(),
+            Camera::new()
+                .image_height(8)
+                .focus_distance(4.0)
+                .initialize()
+        );
+    }
+
+    #[test]
+    fn test_background() {
+        let world = HittableList::new();
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(1)
+            .background(Background::Solid(Color::new(0.2, 0.4, 0.6)))
+            .initialize();
+
+        for (x, y) in [(0, 0), (3, 1), (2, 3)] {
+            assert_eq!(camera.get_pixel(&world, x, y), Color::new(0.2, 0.4, 0.6));
+        }
+    }
+
+    #[test]
+    fn test_background_fn() {
+        // black sky with a sun disk straight up, called from every render thread
+        let sun = Color::new(20.0, 18.0, 15.0);
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(1)
+            .look_from(0.0, 0.0, 0.0)
+            .look_at(0.0, 1.0, 0.0)
+            .vup(0.0, 0.0, 1.0)
+            .vertical_fov(10.0)
+            .background_fn(Arc::new(move |ray: &Ray| {
+                if ray.direction().unit().y > 0.999 {
+                    sun
+                } else {
+                    Color::BLACK
+                }
+            }))
+            .initialize();
+        assert!(matches!(camera.background, Background::Function(_)));
+
+        let (pixels, _) = camera.render_with_stats(&HittableList::new());
+        assert_eq!(pixels[0], Color::BLACK);
+        assert_eq!(pixels[4 * 8 + 4], sun);
+    }
+
+    #[test]
+    fn test_background_environment() {
+        // panorama where only the view direction (-z, center of the image) is lit
+        let mut image = Image::new(8, 4);
+        for y in 0..4 {
+            image.pixels[y * 8 + 5] = Color::new(8.0, 8.0, 8.0);
+            image.pixels[y * 8 + 6] = Color::new(8.0, 8.0, 8.0);
+        }
+
+        let world = HittableList::new();
+        let camera = Camera::new()
+            .image_height(4)
+            .vertical_fov(10.0)
+            .background(EnvironmentMap::new(image))
+            .initialize();
+
+        let pixel = camera.get_pixel(&world, 2, 2);
+        assert!(pixel.x() > 1.0, "{pixel:?}");
+
+        let behind = Camera::new()
+            .image_height(4)
+            .vertical_fov(10.0)
+            .look_at(0.0, 0.0, 1.0)
+            .background(camera.background.clone())
+            .initialize();
+        assert_eq!(behind.get_pixel(&world, 2, 2), Color::BLACK);
+    }
+
+    #[test]
+    fn test_check_energy() {
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(0.0, 0.0, -1.0)
+                .radius(0.5)
+                .material(material::Type::from(material::LambertianParams {
+                    albedo: Color::new(0.9, 0.9, 0.9),
+                    reflectance: 0.5,
+                    ..Default::default()
+                }))
+                .build(),
+        );
+
+        let before = material::energy_warnings();
+        let camera = Camera::new()
+            .image_height(2)
+            .check_energy(true)
+            .initialize();
+        for _ in 0..100 {
+            camera.get_pixel(&world, 1, 1);
+        }
+        assert!(material::energy_warnings() > before);
+    }
+
+    #[test]
+    fn test_render_seeded() {
+        let world = diffuse_world();
+        let camera = Camera::new().image_height(16).initialize();
+
+        let a = camera.render_seeded(&world, 3);
+        let b = camera.render_seeded(&world, 3);
+        assert_eq!(a, b);
+        assert_eq!((a.width, a.height), (16, 16));
+
+        let c = camera.render_seeded(&world, 4);
+        assert_ne!(a, c);
+
+        // the worker rendering the pixels carries on with its own generator
+        // as if nothing was rendered, here also the calling thread
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            seed(11);
+            let expected = random_f64();
+            seed(11);
+            camera.render_seeded(&world, 3);
+            assert_eq!(random_f64(), expected);
+        });
+    }
+
+    #[test]
+    fn test_portal_bounded_by_max_depth() {
+        // portal quad at z = -1 that sends rays back to z = 1 with the same
+        // heading, so they teleport forever until max_depth stops them
+        let portal = material::Type::from(material::PortalParams {
+            link_transform: Mat4::translation(0.0, 0.0, 2.0),
+        });
+
+        // large enough that slightly diverging rays stay on it for every trip
+        let [a, b, c, d] = [
+            (-100.0, -100.0),
+            (100.0, -100.0),
+            (100.0, 100.0),
+            (-100.0, 100.0),
+        ]
+        .map(|(x, y)| Point3::new(x, y, -1.0));
+        let mut world = HittableList::new();
+        world.add(Triangle::new(a, b, c, portal));
+        world.add(Triangle::new(a, c, d, portal));
+
+        let camera = Camera::new()
+            .image_height(2)
+            .vertical_fov(10.0)
+            .max_depth(20)
+            .initialize();
+        assert_eq!(camera.get_pixel(&world, 0, 0), Color::BLACK);
+    }
+
+    #[test]
+    fn test_render_multiscale() {
+        let world = HittableList::new();
+        let color = Color::new(0.25, 0.5, 0.75);
+        let camera = Camera::new()
+            .aspect_ratio(2.0)
+            .image_height(16)
+            .samples_per_pixel(2)
+            .background(Background::Solid(color))
+            .initialize();
+
+        let images = camera.render_multiscale(&world, &[1, 2, 4]);
+        let sizes: Vec<_> = images.iter().map(|i| (i.width, i.height)).collect();
+        assert_eq!(sizes, [(32, 16), (16, 8), (8, 4)]);
+
+        for image in &images {
+            for pixel in &image.pixels {
+                for n in 0..3 {
+                    assert::float(pixel[n], color[n], 9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scaled() {
+        let camera = Camera::new()
+            .image_height(16)
+            .samples_per_pixel(3)
+            .initialize();
+        let scaled = camera.scaled(4);
+
+        assert_eq!(scaled.samples_per_pixel, 48);
+        assert_eq!((scaled.image_width(), scaled.image_height()), (4, 4));
+
+        // both cameras span the same viewport corners
+        let corner = |c: &Camera| c.pixel_00 - 0.5 * (c.pixel_delta_u + c.pixel_delta_v);
+        let far = |c: &Camera| corner(c) + c.image_width * c.pixel_delta_u;
+        for n in 0..3 {
+            assert::float(corner(&scaled)[n], corner(&camera)[n], 9);
+            assert::float(far(&scaled)[n], far(&camera)[n], 9);
+        }
+
+        assert_eq!(camera.scaled(0), camera.scaled(1));
+        assert_eq!(camera.scaled(1), camera);
+
+        // the squared scale saturates rather than overflowing
+        assert_eq!(camera.scaled(65536).samples_per_pixel, u32::MAX);
+        assert_eq!(camera.scaled(u32::MAX).samples_per_pixel, u32::MAX);
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut world = HittableList::new();
+        let centers = [
+            (-30.0, 2.0, -5.0),
+            (12.0, -8.0, 40.0),
+            (3.0, 25.0, 0.0),
+            (0.0, 0.0, -60.0),
+        ];
+        for (x, y, z) in centers {
+            world.add(Sphere::builder().center(x, y, z).radius(2.0).build());
+        }
+
+        for aspect_ratio in [0.5, 1.0, 16.0 / 9.0] {
+            let camera = Camera::new()
+                .aspect_ratio(aspect_ratio)
+                .vertical_fov(40.0)
+                .look_from(1.0, 1.0, 1.0)
+                .frame(&world)
+                .initialize();
+
+            // project each center through the camera onto the viewport plane
+            let normal = camera.pixel_delta_u.cross(&camera.pixel_delta_v);
+            let upper_left = camera.pixel_00 - 0.5 * (camera.pixel_delta_u + camera.pixel_delta_v);
+
+            for (x, y, z) in centers {
+                let direction = Vec3::new(x, y, z) - camera.center;
+                let t = (camera.pixel_00 - camera.center).dot(&normal) / direction.dot(&normal);
+                assert!(
+                    t > 0.0,
+                    "{aspect_ratio} ({x}, {y}, {z}) is behind the camera"
+                );
+
+                let offset = camera.center + t * direction - upper_left;
+                let u = offset.dot(&camera.pixel_delta_u) / camera.pixel_delta_u.length_squared();
+                let v = offset.dot(&camera.pixel_delta_v) / camera.pixel_delta_v.length_squared();
+
+                assert!(
+                    (0.0..=camera.image_width).contains(&u),
+                    "{aspect_ratio} u={u}"
+                );
+                assert!(
+                    (0.0..=camera.image_height).contains(&v),
+                    "{aspect_ratio} v={v}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_empty_world() {
+        let framed = Camera::new().frame(&HittableList::new()).initialize();
+        assert_eq!(framed, Camera::new().initialize());
+    }
+
+    #[test]
+    fn test_missing_material() {
+        // sphere filling the whole view with no material assigned
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::builder()
+                .center(0.0, 0.0, -11.0)
+                .radius(10.0)
+                .material(material::Type::empty())
+                .build(),
+        );
+
+        let render = |missing_material| {
+            Camera::new()
+                .image_height(16)
+                .samples_per_pixel(1)
+                .missing_material(missing_material)
+                .initialize()
+                .render_streaming(&world, 1, |_| {})
+        };
+
+        let magenta = Color::new(1.0, 0.0, 1.0);
+        let checker = render(true);
+        assert!(checker
+            .pixels
+            .iter()
+            .all(|p| *p == magenta || *p == Color::BLACK));
+        assert!(checker.pixels.contains(&magenta));
+        assert!(checker.pixels.contains(&Color::BLACK));
+
+        let off = render(false);
+        assert!(off.pixels.iter().all(|p| *p == Color::BLACK));
+    }
+
+    #[test]
+    fn test_missing_material_color() {
+        let magenta = Color::new(1.0, 0.0, 1.0);
+        assert_eq!(missing_material_color(&Point3::new(0.5, 0.5, 0.5)), magenta);
+        assert_eq!(
+            missing_material_color(&Point3::new(1.5, 0.5, 0.5)),
+            Color::BLACK
+        );
+        assert_eq!(
+            missing_material_color(&Point3::new(-0.5, 0.5, 0.5)),
+            Color::BLACK
+        );
+        assert_eq!(
+            missing_material_color(&Point3::new(-0.5, -0.5, 0.5)),
+            magenta
+        );
+    }
+
+    // chi-square of how evenly points fill a 4 x 4 grid over every 2d
+    // projection of the 4d (pixel x, pixel y, lens u, lens v) sample space
+    fn projection_unevenness(points: &[[f64; 4]]) -> f64 {
+        let mut total = 0.0;
+        for a in 0..4 {
+            for b in a + 1..4 {
+                let mut counts = [0.0; 16];
+                for point in points {
+                    let i = (point[a] * 4.0) as usize;
+                    let j = (point[b] * 4.0) as usize;
+                    counts[j * 4 + i] += 1.0;
+                }
+
+                let expected = points.len() as f64 / 16.0;
+                total += counts
+                    .iter()
+                    .map(|c| (c - expected) * (c - expected) / expected)
+                    .sum::<f64>();
+            }
+        }
+        total
+    }
+
+    fn mean_unevenness(pairing: Option<SamplePairing>) -> f64 {
+        let camera = Camera::new()
+            .image_height(8)
+            .samples_per_pixel(16)
+            .defocus_angle(2.0)
+            .sample_pairing(pairing.unwrap_or_default())
+            .initialize();
+
+        let mut total = 0.0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let points: Vec<[f64; 4]> = (0..16)
+                    .map(|sample| match pairing {
+                        Some(_) => {
+                            let ([px, py], lens) = camera.sample_offsets(x, y, sample);
+                            let [lu, lv] = lens.unwrap();
+                            [px, py, lu, lv]
+                        }
+                        // naive independent sampling of all four dimensions
+                        None => [random_f64(), random_f64(), random_f64(), random_f64()],
+                    })
+                    .collect();
+                total += projection_unevenness(&points);
+            }
+        }
+        total / 64.0
+    }
+
+    #[test]
+    fn test_sample_pairing_coverage() {
+        let independent = mean_unevenness(None);
+        let correlated = mean_unevenness(Some(SamplePairing::Correlated));
+        let decorrelated = mean_unevenness(Some(SamplePairing::Decorrelated));
+
+        assert!(decorrelated < independent, "{decorrelated} < {independent}");
+        assert!(decorrelated < correlated, "{decorrelated} < {correlated}");
+    }
+
+    #[test]
+    fn test_sample_pairing_decorrelated_distinct_cells() {
+        let camera = Camera::new()
+            .image_height(2)
+            .samples_per_pixel(9)
+            .sample_pairing(SamplePairing::Decorrelated)
+            .initialize();
+
+        // every sample lands in its own pixel cell and its own lens cell
+        let cell = |[u, v]: [f64; 2]| ((u * 3.0) as usize, (v * 3.0) as usize);
+        let mut pixel_cells = vec![];
+        let mut lens_cells = vec![];
+        for sample in 0..9 {
+            let (offset, lens) = camera.sample_offsets(1, 0, sample);
+            pixel_cells.push(cell(offset));
+            lens_cells.push(cell(lens.unwrap()));
+        }
+
+        pixel_cells.sort();
+        pixel_cells.dedup();
+        lens_cells.sort();
+        lens_cells.dedup();
+        assert_eq!(pixel_cells.len(), 9);
+        assert_eq!(lens_cells.len(), 9);
+    }
+
+    #[test]
+    fn test_sample_pairing_unbiased() {
+        // 10 samples fill a 3x3 grid plus one, none of them may favor a part
+        // of the pixel or lens, e.g. by never reaching a grid's last row
+        for pairing in [SamplePairing::Correlated, SamplePairing::Decorrelated] {
+            let camera = Camera::new()
+                .aspect_ratio(1.0)
+                .image_height(32)
+                .samples_per_pixel(10)
+                .sample_pairing(pairing)
+                .initialize();
+
+            let mut sum = [0.0; 4];
+            for y in 0..32 {
+                for x in 0..32 {
+                    for sample in 0..10 {
+                        let ([px, py], lens) = camera.sample_offsets(x, y, sample);
+                        let [lu, lv] = lens.unwrap();
+                        for (total, value) in sum.iter_mut().zip([px, py, lu, lv]) {
+                            *total += value;
+                        }
+                    }
+                }
+            }
+
+            for total in sum {
+                let mean = total / (32 * 32 * 10) as f64;
+                assert!((mean - 0.5).abs() < 0.02, "{pairing:?} {mean}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_aperture_blades() {
+        let camera = Camera::new()
+            .image_height(4)
+            .defocus_angle(10.0)
+            .aperture_blades(4)
+            .initialize();
+
+        // square aperture with corners at +-u and +-v, every origin inside it
+        let radius = camera.defocus_disk_u.length();
+        let mut corner_reach: f64 = 0.0;
+        for sample in 0..500 {
+            let ray = camera.get_ray(1, 1, sample);
+            let offset = Vec3::from(ray.origin()) - camera.center;
+            let u = offset.dot(&camera.defocus_disk_u) / (radius * radius);
+            let v = offset.dot(&camera.defocus_disk_v) / (radius * radius);
+            assert!(u.abs() + v.abs() <= 1.0 + 1e-9, "{u} {v}");
+            corner_reach = corner_reach.max(v.abs());
+        }
+
+        // reaches toward the corners further than a disk of the same area would
+        assert!(corner_reach > 0.85, "{corner_reach}");
+
+        let circular = Camera::new().defocus_angle(10.0).initialize();
+        assert_eq!(circular.aperture_blades, 0);
+    }
+
+    #[test]
+    fn test_roll() {
+        let level = Camera::new().initialize();
+        let banked = Camera::new().roll(90.0).initialize();
+
+        // rotated a quarter turn, rows run the way columns did and vice versa
+        let level_u = level.pixel_delta_u.unit();
+        let level_v = level.pixel_delta_v.unit();
+        let banked_u = banked.pixel_delta_u.unit();
+        let banked_v = banked.pixel_delta_v.unit();
+        for n in 0..3 {
+            assert::float(banked_u[n], -level_v[n], 9);
+            assert::float(banked_v[n], level_u[n], 9);
+        }
+
+        // the center of the image still looks the same way
+        let center = |c: &Camera| c.pixel_00 + 49.5 * (c.pixel_delta_u + c.pixel_delta_v);
+        for n in 0..3 {
+            assert::float(center(&banked)[n], center(&level)[n], 9);
+        }
+
+        assert_eq!(Camera::new().roll(0.0).initialize(), level);
+    }
+
+    /// Sphere that takes a while to hit, stands in for an expensive scene
+    struct SlowSphere(Sphere);
+
+    impl Hittable for SlowSphere {
+        fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<crate::geo::HitRecord> {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            self.0.hit(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self) -> crate::geo::Aabb {
+            self.0.bounding_box()
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    // pixel count of a P3 file, None unless it is complete and well formed
+    fn ppm_pixels(contents: &str) -> Option<usize> {
+        let mut tokens = contents.split_whitespace();
+        if tokens.next()? != "P3" {
+            return None;
+        }
+        let width: usize = tokens.next()?.parse().ok()?;
+        let height: usize = tokens.next()?.parse().ok()?;
+        if tokens.next()? != "255" {
+            return None;
+        }
+
+        let values: Option<Vec<u8>> = tokens.map(|t| t.parse().ok()).collect();
+        (values?.len() == width * height * 3).then_some(width * height)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_save_interval() {
+        let output =
+            std::env::temp_dir().join(format!("ray-tracer-progressive-{}.ppm", std::process::id()));
+        let output = output.to_str().unwrap().to_string();
+
+        let world = SlowSphere(
+            Sphere::builder()
+                .center(0.0, 0.0, -1.0)
+                .radius(0.5)
+                .material(material::Type::debug())
+                .build(),
+        );
+
+        let camera = Camera::new()
+            .image_height(16)
+            .samples_per_pixel(1)
+            .max_depth(1)
+            .quiet(true)
+            .output(&output)
+            .save_interval(0.02)
+            .initialize();
+
+        // watch the output while rendering, collecting every distinct version
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let watcher = {
+            let done = Arc::clone(&done);
+            let output = output.clone();
+            std::thread::spawn(move || {
+                let mut versions: Vec<String> = vec![];
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    if let Ok(contents) = std::fs::read_to_string(&output) {
+                        if versions.last() != Some(&contents) {
+                            versions.push(contents);
+                        }
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(2));
+                }
+                versions
+            })
+        };
+
+        camera.render(&world).await;
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        let versions = watcher.join().unwrap();
+
+        assert!(versions.len() > 2, "{} versions", versions.len());
+        for version in &versions {
+            assert_eq!(ppm_pixels(version), Some(16 * 16));
+        }
+
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_gamma() {
+        let output =
+            std::env::temp_dir().join(format!("ray-tracer-gamma-{}.ppm", std::process::id()));
+        let output = output.to_str().unwrap();
+
+        let render = |builder: CameraBuilder| async move {
+            builder
+                .image_height(2)
+                .samples_per_pixel(1)
+                .background(Background::Solid(Color::new(0.5, 0.5, 0.5)))
+                .quiet(true)
+                .save_interval(0.0)
+                .output(output)
+                .initialize()
+                .render(&HittableList::new())
+                .await;
+            std::fs::read_to_string(output).unwrap()
+        };
+
+        let pixels = |ppm: String| ppm.lines().skip(3).collect::<Vec<_>>().join("\n");
+
+        assert_eq!(
+            pixels(render(Camera::new()).await),
+            ["181 181 181"; 4].join("\n")
+        );
+        assert_eq!(
+            pixels(render(Camera::new().gamma(2.2)).await),
+            ["186 186 186"; 4].join("\n")
+        );
+        assert_eq!(
+            pixels(render(Camera::new().linear(true)).await),
+            ["128 128 128"; 4].join("\n")
+        );
+
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_point_light_shadow() {
+        let gray = material::Type::from(material::LambertianParams {
+            albedo: Color::new(0.5, 0.5, 0.5),
+            ..Default::default()
+        });
+
+        // ground quad at y = 0 facing up, sphere hovering between it and the light
+        let mut world = HittableList::new();
+        let corner = |x: f64, z: f64| Point3::new(x, 0.0, z);
+        world.add(Triangle::new(
+            corner(-10.0, -10.0),
+            corner(-10.0, 10.0),
+            corner(10.0, -10.0),
+            gray,
+        ));
+        world.add(Triangle::new(
+            corner(10.0, -10.0),
+            corner(-10.0, 10.0),
+            corner(10.0, 10.0),
+            gray,
+        ));
+        world.add(
+            Sphere::builder()
+                .center(0.0, 1.0, 0.0)
+                .radius(0.5)
+                .material(gray)
+                .build(),
+        );
+
+        let builder = || {
+            Camera::new()
+                .max_depth(1)
+                .background(Background::Solid(Color::BLACK))
+                .quiet(true)
+        };
+        let light = PointLight {
+            position: Point3::new(0.0, 3.0, 0.0),
+            color: Color::WHITE,
+            intensity: 9.0,
+        };
+
+        // only direct light counts with a single bounce into a black background
+        let shadowed = Ray::new(Point3::new(2.0, 1.0, 0.0), Vec3::new(-2.0, -1.0, 0.0));
+        let open = Ray::new(Point3::new(2.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        let camera = builder().light(light).initialize();
+        assert_eq!(camera.ray_color(&shadowed, &world, 1), Color::BLACK);
+
+        // albedo / pi * intensity / d^2 * cos, light is sqrt(13) away
+        let color = camera.ray_color(&open, &world, 1);
+        let expected = 0.5 / std::f64::consts::PI * 9.0 / 13.0 * (3.0 / 13.0_f64.sqrt());
+        assert::float(color.x(), expected, 12);
+        assert_eq!(color.x(), color.z());
+
+        // without the light both are dark
+        let camera = builder().initialize();
+        assert_eq!(camera.ray_color(&open, &world, 1), Color::BLACK);
+
+        // the sphere's top faces the light, its underside does not
+        let camera = builder().light(light).initialize();
+        let top = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let bottom = Ray::new(Point3::new(0.0, 0.25, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(camera.ray_color(&top, &world, 1).x() > 0.0);
+        assert_eq!(camera.ray_color(&bottom, &world, 1), Color::BLACK);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallelism() {
+        let render = |parallelism: Parallelism| async move {
+            let output = std::env::temp_dir().join(format!(
+                "ray-tracer-parallelism-{parallelism:?}-{}.ppm",
+                std::process::id()
+            ));
+            let output = output.to_str().unwrap().to_string();
+
+            let camera = Camera::new()
+                .aspect_ratio(2.0)
+                .image_height(6)
+                .samples_per_pixel(3)
+                .background(Background::Solid(Color::new(0.5, 0.25, 1.0)))
+                .quiet(true)
+                .save_interval(0.0)
+                .output(&output)
+                .parallelism(parallelism)
+                .initialize();
+
+            // final (cur, max) reported when the progress is dropped
+            let last = Arc::new(std::sync::Mutex::new((0, 0)));
+            let progress = {
+                let last = Arc::clone(&last);
+                Progress::new(camera.progress_max())
+                    .quiet(true)
+                    .on_update(move |cur, max| *last.lock().unwrap() = (cur, max))
+            };
+
+            camera
+                .render_with_progress(&HittableList::new(), progress)
+                .await;
+
+            let ppm = std::fs::read_to_string(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+            let last = *last.lock().unwrap();
+            (ppm, last)
+        };
+
+        let (pixel_ppm, pixel_progress) = render(Parallelism::Pixel).await;
+        let (scanline_ppm, scanline_progress) = render(Parallelism::Scanline).await;
+
+        // runs that do and do not line up with rows or the image's end
+        for chunk in [5, 12, 72, 100] {
+            let (chunk_ppm, chunk_progress) = render(Parallelism::Chunk(chunk)).await;
+            assert_eq!(chunk_progress, (216, 216));
+            assert_eq!(chunk_ppm, pixel_ppm, "{chunk}");
+        }
+
+        assert_eq!(pixel_progress, (216, 216));
+        assert_eq!(scanline_progress, (216, 216));
+        assert_eq!(pixel_ppm, scanline_ppm);
+        assert_eq!(scanline_ppm.lines().count(), 3 + 72);
+    }
+
+    #[test]
+    fn test_save_interval_disabled() {
+        let builder = Camera::new().save_interval(0.0);
+        assert_eq!(builder.initialize().save_interval, None);
+
+        let camera = Camera::new().save_interval(0.5).initialize();
+        assert_eq!(camera.save_interval, Some(time::Duration::from_millis(500)));
+        assert_eq!(camera.output, "image.ppm");
+    }
+
+    #[test]
+    fn test_render_resume() {
+        let world = diffuse_world();
+        let camera = |samples_per_pixel| {
+            Camera::new()
+                .image_height(8)
+                .samples_per_pixel(samples_per_pixel)
+                .initialize()
+        };
+
+        let dir = std::env::temp_dir();
+        let resumed_path = dir.join(format!("ray-tracer-resume-{}.ckpt", std::process::id()));
+        let resumed_path = resumed_path.to_str().unwrap();
+        let single_path = dir.join(format!("ray-tracer-single-{}.ckpt", std::process::id()));
+        let single_path = single_path.to_str().unwrap();
+
+        // 10 samples, then resume the checkpoint up to 20
+        let partial = camera(10)
+            .render_checkpointed(&world, 9, resumed_path, 4)
+            .unwrap();
+        let checkpoint = Checkpoint::load(resumed_path).unwrap();
+        assert!(checkpoint.samples.iter().all(|s| *s == 10));
+        assert_eq!(checkpoint.image(), partial);
+
+        let resumed = camera(20).render_resume(&world, resumed_path, 4).unwrap();
+
+        // all 20 at once
+        let single = camera(20)
+            .render_checkpointed(&world, 9, single_path, 20)
+            .unwrap();
+
+        assert_eq!(resumed, single);
+        assert_eq!(
+            Checkpoint::load(resumed_path).unwrap(),
+            Checkpoint::load(single_path).unwrap()
+        );
+
+        // checkpoint for a different image size is rejected
+        assert!(Camera::new()
+            .image_height(4)
+            .initialize()
+            .render_resume(&world, resumed_path, 4)
+            .is_err());
+
+        std::fs::remove_file(resumed_path).unwrap();
+        std::fs::remove_file(single_path).unwrap();
+    }
+
+    #[test]
+    fn test_clamp_radiance() {
+        let world = diffuse_world();
+        let camera = Camera::new()
+            .image_height(10)
+            .clamp_radiance(0.5)
+            .initialize();
+
+        let mut brightest: f64 = 0.0;
+        for y in 0..10 {
+            for x in 0..10 {
+                let sample = camera.sample_pixel(&world, x, y, 0);
+                brightest = brightest.max(sample.x()).max(sample.y()).max(sample.z());
+            }
+        }
+
+        // sky is brighter than 0.5 so some samples were clamped
+        assert_eq!(brightest, 0.5);
+    }
+
+    #[test]
+    fn test_clamp_radiance_default_off() {
+        let camera = Camera::new().initialize();
+        assert_eq!(camera.clamp_radiance, None);
+    }
+
+    #[test]
+    fn test_render_streaming() {
+        let world = diffuse_world();
+        let camera = Camera::new()
+            .image_height(20)
+            .samples_per_pixel(64)
+            .initialize();
+
+        let mut images = vec![];
+        let image = camera.render_streaming(&world, 8, |image| images.push(image.clone()));
+
+        assert_eq!(images.len(), 8);
+        assert_eq!(images.last(), Some(&image));
+        for image in &images {
+            assert_eq!((image.width, image.height), (20, 20));
+        }
+
+        // each pass adds samples so the image converges toward a high sample reference
+        let reference = Camera::new()
+            .image_height(20)
+            .samples_per_pixel(256)
+            .initialize()
+            .render_streaming(&world, 256, |_| {});
+
+        let early = mean_squared_error(&images[0], &reference);
+        let middle = mean_squared_error(&images[3], &reference);
+        let late = mean_squared_error(&images[7], &reference);
+        assert!(early > middle, "{early} > {middle}");
+        assert!(middle > late, "{middle} > {late}");
+    }
+
+    #[test]
+    fn test_render_streaming_uneven() {
+        let world = diffuse_world();
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(10)
+            .initialize();
+
+        let mut calls = 0;
+        camera.render_streaming(&world, 4, |_| calls += 1);
+
+        // samples 4, 8 and the final sample 10
+        assert_eq!(calls, 3);
+    }
+}
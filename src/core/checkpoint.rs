@@ -0,0 +1,203 @@
+use std::io::Read;
+use std::io::Write;
+
+use crate::core::save_atomic;
+use crate::core::Color;
+use crate::core::Image;
+
+/// Render progress that can be saved and resumed later, summed (not averaged)
+/// radiance and the number of samples taken for every pixel
+///
+/// Stored as little endian binary, f64 keeps sums exact across a resume
+///
+/// ```text
+/// RTCK <version u32> <width u64> <height u64> <seed u64>
+/// <r f64> <g f64> <b f64> <samples u32>   (width * height times)
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    pub width: usize,
+    pub height: usize,
+    /// Seed the samples were drawn with, resuming continues the same sequence
+    pub seed: u64,
+    pub sums: Vec<Color>,
+    pub samples: Vec<u32>,
+}
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 1;
+
+impl Checkpoint {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Checkpoint {
+            width,
+            height,
+            seed,
+            sums: vec![Color::BLACK; width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    /// Average radiance of every pixel so far, black where nothing was sampled
+    pub fn image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+
+        for ((pixel, sum), samples) in image.pixels.iter_mut().zip(&self.sums).zip(&self.samples) {
+            if *samples > 0 {
+                *pixel = *sum * (1.0 / *samples as f64);
+            }
+        }
+
+        image
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.width as u64).to_le_bytes())?;
+        writer.write_all(&(self.height as u64).to_le_bytes())?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+
+        for (sum, samples) in self.sums.iter().zip(&self.samples) {
+            for channel in [sum.x(), sum.y(), sum.z()] {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+            writer.write_all(&samples.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, std::io::Error> {
+        let invalid = |message: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+        };
+
+        let mut magic = [0_u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a render checkpoint"));
+        }
+
+        if read_u32(reader)? != VERSION {
+            return Err(invalid("unsupported checkpoint version"));
+        }
+
+        let width = read_u64(reader)? as usize;
+        let height = read_u64(reader)? as usize;
+        let seed = read_u64(reader)?;
+
+        let count = width
+            .checked_mul(height)
+            .ok_or_else(|| invalid("checkpoint dimensions overflow"))?;
+
+        // grow as pixels arrive instead of trusting the header, a corrupt or
+        // truncated file fails on the missing data rather than allocating
+        let mut sums = vec![];
+        let mut samples = vec![];
+        for _ in 0..count {
+            let r = read_f64(reader)?;
+            let g = read_f64(reader)?;
+            let b = read_f64(reader)?;
+            sums.push(Color::new(r, g, b));
+            samples.push(read_u32(reader)?);
+        }
+
+        Ok(Checkpoint {
+            width,
+            height,
+            seed,
+            sums,
+            samples,
+        })
+    }
+
+    pub fn save(&self, filepath: &str) -> Result<(), std::io::Error> {
+        save_atomic(filepath, |writer| self.write(writer))
+    }
+
+    pub fn load(filepath: &str) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::open(filepath)?;
+        Checkpoint::read(&mut std::io::BufReader::new(file))
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, std::io::Error> {
+    let mut bytes = [0_u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, std::io::Error> {
+    let mut bytes = [0_u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> Result<f64, std::io::Error> {
+    let mut bytes = [0_u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut checkpoint = Checkpoint::new(3, 2, 42);
+        checkpoint.sums[1] = Color::new(0.1, 2.5, 1e-9);
+        checkpoint.samples[1] = 7;
+        checkpoint.sums[5] = Color::new(12.0, 0.0, 3.0);
+        checkpoint.samples[5] = 3;
+
+        let mut bytes = vec![];
+        checkpoint.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 4 + 8 * 3 + 6 * (3 * 8 + 4));
+
+        let read = Checkpoint::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, checkpoint);
+    }
+
+    #[test]
+    fn test_image() {
+        let mut checkpoint = Checkpoint::new(2, 1, 0);
+        checkpoint.sums[0] = Color::new(3.0, 6.0, 1.5);
+        checkpoint.samples[0] = 3;
+
+        let image = checkpoint.image();
+        assert_eq!(image.pixels[0], Color::new(1.0, 2.0, 0.5));
+        assert_eq!(image.pixels[1], Color::BLACK);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(Checkpoint::read(&mut b"P3\n".as_slice()).is_err());
+
+        let mut bytes = vec![];
+        Checkpoint::new(2, 2, 0).write(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Checkpoint::read(&mut bytes.as_slice()).is_err());
+
+        bytes[4] = 99;
+        assert!(Checkpoint::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_read_hostile_size() {
+        // a header claiming a huge image with no pixels runs out of input
+        // instead of allocating width * height up front
+        let mut bytes = vec![];
+        Checkpoint::new(0, 0, 0).write(&mut bytes).unwrap();
+        bytes[8..16].copy_from_slice(&(1_u64 << 31).to_le_bytes());
+        bytes[16..24].copy_from_slice(&(1_u64 << 31).to_le_bytes());
+
+        let error = Checkpoint::read(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        bytes[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = Checkpoint::read(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -5,12 +5,171 @@ use crate::geo::Vec3;
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Color(Vec3);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorError {
+    /// Hex string must have 3 or 6 digits, excluding the optional leading `#`
+    InvalidLength(usize),
+    InvalidDigit(char),
+}
+
+impl std::fmt::Display for ColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ColorError::InvalidLength(len) => {
+                write!(f, "invalid hex color length {len}, expected 3 or 6 digits")
+            }
+            ColorError::InvalidDigit(c) => write!(f, "invalid hex color digit '{c}'"),
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
 impl Color {
     pub const MAX_VALUE: u32 = 255;
 
-    pub fn new(rf: f64, gf: f64, bf: f64) -> Self {
+    /// Maximum value of 16-bit channels, see `to_rgb16`
+    pub const MAX_VALUE_16: u32 = 65535;
+
+    /// Default output gamma, encodes with a square root
+    pub const GAMMA: f64 = 2.0;
+
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+    pub const RED: Color = Color::new(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::new(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+
+    pub const fn new(rf: f64, gf: f64, bf: f64) -> Self {
         Color(Vec3::new(rf, gf, bf))
     }
+
+    /// Parse a `#rgb` or `#rrggbb` sRGB hex string (leading `#` optional) into linear color
+    pub fn from_hex(hex: &str) -> Result<Color, ColorError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+        let values = digits
+            .chars()
+            .map(|c| c.to_digit(16).ok_or(ColorError::InvalidDigit(c)))
+            .collect::<Result<Vec<u32>, ColorError>>()?;
+
+        let [r, g, b] = match values.as_slice() {
+            // short form repeats each digit, e.g. f80 is ff8800
+            [r, g, b] => [r * 17, g * 17, b * 17],
+            [r1, r0, g1, g0, b1, b0] => [r1 * 16 + r0, g1 * 16 + g0, b1 * 16 + b0],
+            _ => return Err(ColorError::InvalidLength(values.len())),
+        };
+
+        Ok(Color::from_rgb8([r, g, b].map(|v| v as u8), false))
+    }
+
+    /// Linear color of rgb bytes, decoded with the exact (piecewise) sRGB
+    /// transfer function, or scaled to [0, 1] unchanged when `linear` is set,
+    /// e.g. for image data that is not a color such as a normal map
+    pub fn from_rgb8(rgb: [u8; 3], linear: bool) -> Color {
+        let [r, g, b] = rgb.map(|v| {
+            let v = v as f64 / Color::MAX_VALUE as f64;
+            if linear {
+                v
+            } else {
+                srgb_to_linear(v)
+            }
+        });
+
+        Color::new(r, g, b)
+    }
+
+    /// Linear interpolation from `start` (t=0) to `end` (t=1)
+    pub fn lerp(t: f64, start: Color, end: Color) -> Color {
+        (1.0 - t) * start + t * end
+    }
+
+    /// Perceived brightness of linear rgb with the Rec. 709 weights, e.g. for
+    /// bloom thresholds or per pixel sample variance
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
+
+    /// Gray of the same luminance
+    pub fn to_grayscale(&self) -> Color {
+        let y = self.luminance();
+        Color::new(y, y, y)
+    }
+
+    /// Every channel clamped to [0, 1], e.g. before output or after adding
+    /// up colors that may exceed 1
+    pub fn clamp01(&self) -> Color {
+        self.clamp_to(&Interval::new(0.0, 1.0))
+    }
+
+    /// Every channel clamped to `interval`
+    pub fn clamp_to(&self, interval: &Interval) -> Color {
+        Color::new(
+            interval.clamp(self.x()),
+            interval.clamp(self.y()),
+            interval.clamp(self.z()),
+        )
+    }
+
+    /// Gamma corrected rgb bytes in the [0,255] range
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        self.to_rgb8_with(Color::GAMMA, false)
+    }
+
+    /// Rgb bytes in the [0,255] range encoded with `gamma` (2.2 approximates
+    /// sRGB displays), or the linear values unchanged when `linear` is set,
+    /// e.g. for further processing in another tool
+    ///
+    /// Non positive gammas are treated as linear
+    pub fn to_rgb8_with(&self, gamma: f64, linear: bool) -> [u8; 3] {
+        self.encode(gamma, linear)
+            .map(|c| (256.0 * INTENSITY.clamp(c)) as u8)
+    }
+
+    /// Same as `to_rgb8` but offsets each channel by an ordered (Bayer) dither
+    /// threshold picked from the pixel coordinates before quantizing, breaking up
+    /// banding in smooth gradients while staying reproducible per pixel
+    pub fn to_rgb8_dithered(&self, x: usize, y: usize) -> [u8; 3] {
+        self.to_rgb8_dithered_with(x, y, Color::GAMMA, false)
+    }
+
+    /// Dithered `to_rgb8_with`
+    pub fn to_rgb8_dithered_with(&self, x: usize, y: usize, gamma: f64, linear: bool) -> [u8; 3] {
+        let threshold = BAYER_4X4[y % 4][x % 4];
+
+        // center the offset on zero so average brightness matches `to_rgb8`
+        let offset = (threshold + 0.5) / 16.0 - 0.5;
+
+        self.encode(gamma, linear).map(|c| {
+            (256.0 * c + offset)
+                .floor()
+                .clamp(0.0, Color::MAX_VALUE as f64) as u8
+        })
+    }
+
+    /// Gamma corrected rgb in the [0,65535] range, e.g. for 16-bit PPM
+    pub fn to_rgb16(&self) -> [u16; 3] {
+        self.to_rgb16_with(Color::GAMMA, false)
+    }
+
+    /// 16-bit `to_rgb8_with`, channels are rounded to the nearest of the
+    /// 65536 levels so 1.0 maps to 65535
+    pub fn to_rgb16_with(&self, gamma: f64, linear: bool) -> [u16; 3] {
+        self.encode(gamma, linear)
+            .map(|c| (c.clamp(0.0, 1.0) * Color::MAX_VALUE_16 as f64).round() as u16)
+    }
+
+    fn encode(&self, gamma: f64, linear: bool) -> [f64; 3] {
+        let channel = |c: f64| {
+            if linear || gamma <= 0.0 {
+                c
+            } else {
+                linear_to_gamma(c, gamma)
+            }
+        };
+
+        [channel(self.x()), channel(self.y()), channel(self.z())]
+    }
 }
 
 impl std::ops::Deref for Color {
@@ -20,31 +179,93 @@ impl std::ops::Deref for Color {
     }
 }
 
+impl std::ops::Add<Color> for Color {
+    type Output = Color;
+
+    fn add(self, rhs: Color) -> Color {
+        Color(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::AddAssign<Color> for Color {
+    fn add_assign(&mut self, rhs: Color) {
+        self.0 += rhs.0;
+    }
+}
+
+impl std::ops::Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Color {
+        Color(self.0 * rhs.0)
+    }
+}
+
+impl std::ops::Mul<f64> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: f64) -> Color {
+        Color(self.0 * rhs)
+    }
+}
+
+impl std::ops::Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Color {
+        Color(self * rhs.0)
+    }
+}
+
+impl std::ops::Div<f64> for Color {
+    type Output = Color;
+
+    fn div(self, rhs: f64) -> Color {
+        Color(self.0 / rhs)
+    }
+}
+
+impl std::iter::Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Self {
+        iter.fold(Color::BLACK, |sum, color| sum + color)
+    }
+}
+
 const INTENSITY: Interval = Interval::new(0.0, 0.9999);
 
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    if linear_component > 0.0 {
+// https://en.wikipedia.org/wiki/Ordered_dithering
+const BAYER_4X4: [[f64; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+fn linear_to_gamma(linear_component: f64, gamma: f64) -> f64 {
+    if linear_component <= 0.0 {
+        0.0
+    } else if gamma == 2.0 {
+        // exact and faster than powf for the default
         linear_component.sqrt()
     } else {
-        0.0
+        linear_component.powf(1.0 / gamma)
+    }
+}
+
+// inverse of the sRGB transfer function
+// https://en.wikipedia.org/wiki/SRGB#From_sRGB_to_CIE_XYZ
+fn srgb_to_linear(srgb_component: f64) -> f64 {
+    if srgb_component <= 0.04045 {
+        srgb_component / 12.92
+    } else {
+        ((srgb_component + 0.055) / 1.055).powf(2.4)
     }
 }
 
 impl std::fmt::Display for Color {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let r = self.x();
-        let g = self.y();
-        let b = self.z();
-
-        // transform for gamma 2
-        let r = linear_to_gamma(r);
-        let g = linear_to_gamma(g);
-        let b = linear_to_gamma(b);
-
         // translate [0,1] to rgb byte range [0,255]
-        let r = (256.0 * INTENSITY.clamp(r)) as u32;
-        let g = (256.0 * INTENSITY.clamp(g)) as u32;
-        let b = (256.0 * INTENSITY.clamp(b)) as u32;
+        let [r, g, b] = self.to_rgb8();
 
         write!(f, "{} {} {}", r, g, b)
     }
@@ -71,6 +292,7 @@ impl From<Vec3> for Color {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test::assert;
 
     #[test]
     fn test_default() {
@@ -78,12 +300,240 @@ mod tests {
         assert_eq!(format!("{a}"), "0 0 0");
     }
 
+    #[test]
+    fn test_sum() {
+        let colors = [Color::new(0.5, 0.25, 0.0), Color::new(0.25, 0.5, 1.0)];
+        assert_eq!(
+            colors.into_iter().sum::<Color>(),
+            Color::new(0.75, 0.75, 1.0)
+        );
+        assert_eq!(std::iter::empty().sum::<Color>(), Color::BLACK);
+    }
+
     #[test]
     fn test_display() {
         let a = Color::new(0.0, 1.0, 0.5);
         assert_eq!(format!("{a}"), "0 255 181");
     }
 
+    #[test]
+    fn test_to_rgb8() {
+        let a = Color::new(0.0, 1.0, 0.5);
+        assert_eq!(a.to_rgb8(), [0, 255, 181]);
+    }
+
+    #[test]
+    fn test_to_rgb8_with() {
+        let a = Color::new(0.0, 1.0, 0.5);
+        assert_eq!(a.to_rgb8_with(Color::GAMMA, false), a.to_rgb8());
+        assert_eq!(a.to_rgb8_with(2.2, false), [0, 255, 186]);
+        assert_eq!(a.to_rgb8_with(1.0, false), [0, 255, 128]);
+        assert_eq!(a.to_rgb8_with(2.2, true), [0, 255, 128]);
+        assert_eq!(a.to_rgb8_with(0.0, false), [0, 255, 128]);
+
+        // linear output still clamps
+        assert_eq!(
+            Color::new(-1.0, 4.0, 0.25).to_rgb8_with(2.0, true),
+            [0, 255, 64]
+        );
+    }
+
+    #[test]
+    fn test_luminance() {
+        assert::float(Color::GREEN.luminance(), 0.7152, 12);
+        assert::float(Color::RED.luminance(), 0.2126, 12);
+        assert::float(Color::BLUE.luminance(), 0.0722, 12);
+        assert::float(Color::WHITE.luminance(), 1.0, 12);
+        assert_eq!(Color::BLACK.luminance(), 0.0);
+    }
+
+    #[test]
+    fn test_to_grayscale() {
+        let gray = Color::new(0.2, 0.6, 1.0).to_grayscale();
+        assert_eq!(gray.x(), gray.y());
+        assert_eq!(gray.y(), gray.z());
+        assert::float(gray.x(), 0.2126 * 0.2 + 0.7152 * 0.6 + 0.0722, 12);
+        assert_eq!(Color::WHITE.to_grayscale(), Color::WHITE);
+    }
+
+    #[test]
+    fn test_clamp01() {
+        let color = Color::new(1.5, 0.25, -0.5).clamp01();
+        assert_eq!(color, Color::new(1.0, 0.25, 0.0));
+        assert_eq!(
+            Color::new(0.2, 0.4, 0.6).clamp01(),
+            Color::new(0.2, 0.4, 0.6)
+        );
+
+        let color = Color::new(3.0, 0.5, 0.05).clamp_to(&Interval::new(0.1, 2.0));
+        assert_eq!(color, Color::new(2.0, 0.5, 0.1));
+    }
+
+    #[test]
+    fn test_to_rgb16() {
+        let a = Color::new(0.0, 1.0, 0.5);
+        assert_eq!(a.to_rgb16(), [0, 65535, 46340]);
+        assert_eq!(a.to_rgb16_with(1.0, false), [0, 65535, 32768]);
+        assert_eq!(a.to_rgb16_with(2.2, true), [0, 65535, 32768]);
+
+        // the high byte matches the 8-bit value
+        assert_eq!(a.to_rgb16().map(|c| (c >> 8) as u8), a.to_rgb8());
+
+        assert_eq!(
+            Color::new(-1.0, 4.0, 0.25).to_rgb16_with(2.0, true),
+            [0, 65535, 16384]
+        );
+    }
+
+    #[test]
+    fn test_to_rgb8_dithered_with() {
+        let a = Color::new(0.3, 0.6, 0.9);
+        assert_eq!(
+            a.to_rgb8_dithered_with(3, 7, Color::GAMMA, false),
+            a.to_rgb8_dithered(3, 7)
+        );
+
+        // dithering moves each channel by at most one step
+        for (x, y) in [(0, 0), (1, 2), (3, 3)] {
+            let plain = a.to_rgb8_with(2.2, true);
+            let dithered = a.to_rgb8_dithered_with(x, y, 2.2, true);
+            for (p, d) in plain.iter().zip(dithered) {
+                assert!(p.abs_diff(d) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_rgb8_dithered_deterministic() {
+        let a = Color::new(0.3, 0.6, 0.9);
+        assert_eq!(a.to_rgb8_dithered(3, 7), a.to_rgb8_dithered(3, 7));
+        assert_eq!(a.to_rgb8_dithered(3, 7), a.to_rgb8_dithered(7, 11));
+    }
+
+    #[test]
+    fn test_to_rgb8_dithered_gradient() {
+        // slow gradient spanning only a handful of byte values across the row
+        let width = 512;
+        let row: Vec<Color> = (0..width)
+            .map(|x| {
+                let t = 0.25 + 0.02 * (x as f64 / width as f64);
+                Color::new(t, t, t)
+            })
+            .collect();
+
+        let plain: Vec<u8> = row.iter().map(|c| c.to_rgb8()[0]).collect();
+        let dithered: Vec<u8> = (0..width)
+            .map(|x| row[x].to_rgb8_dithered(x, 0)[0])
+            .collect();
+
+        let longest_run = |bytes: &[u8]| {
+            bytes
+                .chunk_by(|a, b| a == b)
+                .map(|run| run.len())
+                .max()
+                .unwrap()
+        };
+
+        let transitions = |bytes: &[u8]| bytes.windows(2).filter(|w| w[0] != w[1]).count();
+
+        assert!(longest_run(&dithered) < longest_run(&plain));
+        assert!(transitions(&dithered) > transitions(&plain));
+    }
+
+    #[test]
+    fn test_add() {
+        let a = Color::new(0.1, 0.2, 0.3);
+        let b = Color::new(0.4, 0.5, 0.6);
+        assert_eq!(a + b, Color::from(Vec3::from(a) + Vec3::from(b)));
+
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+    }
+
+    #[test]
+    fn test_mul_color() {
+        let a = Color::new(0.1, 0.2, 0.3);
+        let b = Color::new(0.4, 0.5, 0.6);
+        assert_eq!(a * b, Color::from(Vec3::from(a) * Vec3::from(b)));
+    }
+
+    #[test]
+    fn test_mul_f64() {
+        let a = Color::new(0.1, 0.2, 0.3);
+        assert_eq!(a * 2.0, Color::from(Vec3::from(a) * 2.0));
+        assert_eq!(2.0 * a, Color::from(2.0 * Vec3::from(a)));
+        assert_eq!(a / 2.0, Color::from(Vec3::from(a) / 2.0));
+    }
+
+    #[test]
+    fn test_lerp() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let blue = Color::new(0.5, 0.7, 1.0);
+        assert_eq!(Color::lerp(0.0, white, blue), white);
+        assert_eq!(Color::lerp(1.0, white, blue), blue);
+
+        let expected = 0.75 * Vec3::from(white) + 0.25 * Vec3::from(blue);
+        assert_eq!(Color::lerp(0.25, white, blue), Color::from(expected));
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(Color::BLACK, Color::default());
+        assert_eq!(Color::WHITE, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(Color::RED, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(Color::GREEN, Color::new(0.0, 1.0, 0.0));
+        assert_eq!(Color::BLUE, Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(Color::from_hex("#ffffff"), Ok(Color::WHITE));
+        assert_eq!(Color::from_hex("000"), Ok(Color::BLACK));
+        assert_eq!(Color::from_hex("#F00"), Ok(Color::RED));
+
+        let a = Color::from_hex("#ff8800").unwrap();
+        assert_eq!(a.x(), 1.0);
+        assert::float(a.y(), 0.246201, 5);
+        assert_eq!(a.z(), 0.0);
+
+        assert_eq!(Color::from_hex("#f80"), Color::from_hex("#ff8800"));
+    }
+
+    #[test]
+    fn test_from_rgb8() {
+        assert_eq!(Color::from_rgb8([255, 255, 255], false), Color::WHITE);
+        assert_eq!(Color::from_rgb8([0, 0, 0], false), Color::BLACK);
+
+        // sRGB mid gray is about a fifth of white, not the quarter squaring gives
+        let gray = Color::from_rgb8([128, 128, 128], false);
+        assert::float(gray.x(), 0.215861, 5);
+
+        // the linear segment near black
+        let dark = Color::from_rgb8([10, 5, 1], false);
+        assert::float(dark.x(), 10.0 / 255.0 / 12.92, 12);
+        assert::float(dark.y(), 5.0 / 255.0 / 12.92, 12);
+        assert::float(dark.z(), 1.0 / 255.0 / 12.92, 12);
+
+        let raw = Color::from_rgb8([128, 255, 0], true);
+        assert_eq!(raw, Color::new(128.0 / 255.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_from_hex_invalid() {
+        assert_eq!(Color::from_hex(""), Err(ColorError::InvalidLength(0)));
+        assert_eq!(Color::from_hex("#ff88"), Err(ColorError::InvalidLength(4)));
+        assert_eq!(
+            Color::from_hex("#ff88000"),
+            Err(ColorError::InvalidLength(7))
+        );
+        assert_eq!(
+            Color::from_hex("#gg8800"),
+            Err(ColorError::InvalidDigit('g'))
+        );
+        assert_eq!(Color::from_hex("##f80"), Err(ColorError::InvalidDigit('#')));
+    }
+
     #[test]
     fn test_from_color() {
         let a = Color::new(0.0, 0.0, 0.0);
@@ -0,0 +1,184 @@
+use crate::core::Color;
+use crate::core::Image;
+use crate::geo::Vec3;
+
+use std::f64::consts::PI;
+
+/// Equirectangular (latitude / longitude) panorama surrounding the scene,
+/// sampled by direction for rays that escape without hitting anything
+///
+/// https://en.wikipedia.org/wiki/Equirectangular_projection
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMap {
+    image: Image,
+    /// Multiplier applied to every sample, e.g. to brighten a dim capture
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image) -> Self {
+        EnvironmentMap {
+            image,
+            intensity: 1.0,
+        }
+    }
+
+    /// Load a Radiance `.hdr` panorama
+    pub fn load(filepath: &str) -> Result<Self, std::io::Error> {
+        Ok(EnvironmentMap::new(Image::load_hdr(filepath)?))
+    }
+
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Radiance arriving from `direction`, bilinearly filtered, wrapping
+    /// around horizontally at the seam
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        let width = self.image.width;
+        let height = self.image.height;
+        if width == 0 || height == 0 {
+            return Color::BLACK;
+        }
+
+        let (u, v) = direction_to_uv(direction);
+
+        // continuous pixel coordinates with pixel centers at +0.5, top row first
+        let x = u * width as f64 - 0.5;
+        let y = (1.0 - v) * height as f64 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+
+        let column = |x: f64| (x as isize).rem_euclid(width as isize) as usize;
+        let row = |y: f64| (y.max(0.0) as usize).min(height - 1);
+
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (row(y0), row(y0 + 1.0));
+
+        let top = Color::lerp(tx, self.image.get(x0, y0), self.image.get(x1, y0));
+        let bottom = Color::lerp(tx, self.image.get(x0, y1), self.image.get(x1, y1));
+
+        Color::lerp(ty, top, bottom) * self.intensity
+    }
+}
+
+/// Equirectangular coordinates of a direction, both in [0, 1]
+///
+/// `u` is the angle around the y axis starting from -x, increasing toward +z
+/// (so -z, the default camera view direction, is the center of the panorama);
+/// `v` goes from the -y pole at 0 to the +y pole at 1
+///
+/// Any panoramic projection should use this same mapping so environment maps
+/// and panoramic renders line up
+pub fn direction_to_uv(direction: &Vec3) -> (f64, f64) {
+    let d = direction.unit();
+
+    let theta = (-d.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-d.z()).atan2(d.x()) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// Inverse of `direction_to_uv`, returns a unit direction
+pub fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+    let phi = u * 2.0 * PI - PI;
+    let theta = v * PI;
+
+    let y = -theta.cos();
+    let radius = theta.sin();
+
+    Vec3::new(radius * phi.cos(), y, -radius * phi.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    #[test]
+    fn test_direction_to_uv() {
+        let cases = [
+            (Vec3::new(-1.0, 0.0, 0.0), 0.0, 0.5),
+            (Vec3::new(0.0, 0.0, 1.0), 0.25, 0.5),
+            (Vec3::new(1.0, 0.0, 0.0), 0.5, 0.5),
+            (Vec3::new(0.0, 0.0, -1.0), 0.75, 0.5),
+            (Vec3::new(0.0, 1.0, 0.0), 0.5, 1.0),
+            (Vec3::new(0.0, -1.0, 0.0), 0.5, 0.0),
+        ];
+
+        for (direction, u, v) in cases {
+            let (actual_u, actual_v) = direction_to_uv(&direction);
+            // -x sits on the seam, either edge is correct
+            assert::float(actual_u % 1.0, u, 9);
+            assert::float(actual_v, v, 9);
+        }
+    }
+
+    #[test]
+    fn test_uv_to_direction_round_trip() {
+        for direction in [
+            Vec3::new(0.3, 0.4, -0.5),
+            Vec3::new(-2.0, -1.0, 0.5),
+            Vec3::new(0.0, 0.9, 0.1),
+        ] {
+            let (u, v) = direction_to_uv(&direction);
+            let actual = uv_to_direction(u, v);
+            let expected = direction.unit();
+            for n in 0..3 {
+                assert::float(actual[n], expected[n], 9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        // left half red, right half blue, top row brighter
+        let mut image = Image::new(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                let color = if x < 2 { Color::RED } else { Color::BLUE };
+                let brightness = if y == 0 { 4.0 } else { 1.0 };
+                image.pixels[y * 4 + x] = color * brightness;
+            }
+        }
+
+        let environment = EnvironmentMap::new(image);
+
+        // +z (u=0.25) lands in the red half, -z (u=0.75) in the blue half
+        let up = environment.sample(&Vec3::new(0.0, 1.0, 1.0));
+        let down = environment.sample(&Vec3::new(0.0, -1.0, 1.0));
+        assert!(up.x() > down.x());
+        assert_eq!(up.z(), 0.0);
+
+        let blue = environment.sample(&Vec3::new(0.0, 1.0, -1.0));
+        assert_eq!(blue.x(), 0.0);
+        assert!(blue.z() > 1.0);
+
+        // straight up clamps to the top row
+        let pole = environment.sample(&Vec3::new(0.0, 1.0, 0.0));
+        assert::float(pole.x() + pole.z(), 4.0, 9);
+    }
+
+    #[test]
+    fn test_sample_intensity() {
+        let mut image = Image::new(2, 1);
+        image.pixels = vec![Color::WHITE; 2];
+
+        let environment = EnvironmentMap::new(image).intensity(2.5);
+        let color = environment.sample(&Vec3::new(0.2, 0.3, -1.0));
+        assert::float(color.x(), 2.5, 9);
+
+        assert_eq!(
+            EnvironmentMap::new(Image::new(0, 0)).sample(&Vec3::new(0.0, 0.0, -1.0)),
+            Color::BLACK
+        );
+    }
+}
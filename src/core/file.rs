@@ -0,0 +1,60 @@
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+/// Write a file through `write` into `<path>.tmp` and rename it to `path`
+/// once complete, so readers (e.g. an image viewer watching a render) never
+/// see a partly written file. On error the temporary file is removed and
+/// any existing file at `path` is left as it was.
+pub fn save_atomic<F>(path: impl AsRef<Path>, write: F) -> Result<(), std::io::Error>
+where
+    F: FnOnce(&mut BufWriter<std::fs::File>) -> Result<(), std::io::Error>,
+{
+    let path = path.as_ref();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let result = std::fs::File::create(&tmp_path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        // rename tmp to target filepath for fast atomic operation
+        std::fs::rename(&tmp_path, path)
+    });
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_atomic() {
+        let dir = std::env::temp_dir().join(format!("ray-tracer-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.txt");
+
+        save_atomic(&path, |writer| writer.write_all(b"first")).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+        assert!(!dir.join("out.txt.tmp").exists());
+
+        // a failed write keeps the previous file and cleans up after itself
+        let error = save_atomic(&path, |writer| {
+            writer.write_all(b"second")?;
+            Err(std::io::Error::other("failed"))
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "failed");
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+        assert!(!dir.join("out.txt.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
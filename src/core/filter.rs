@@ -0,0 +1,227 @@
+//! Image filters on row-major pixel buffers of `width * height` entries,
+//! independent of the renderer, e.g. to soften or denoise a loaded image.
+//! Each returns a new buffer and clamps coordinates at the edges, so edge
+//! pixels repeat outwards.
+
+use crate::core::Color;
+
+/// Average over the `(2 * radius + 1)` square around each pixel, radius 0
+/// returns the pixels unchanged
+pub fn box_blur(pixels: &[Color], width: usize, height: usize, radius: usize) -> Vec<Color> {
+    let kernel = vec![1.0 / (2 * radius + 1) as f64; 2 * radius + 1];
+    separable(pixels, width, height, &kernel)
+}
+
+/// Gaussian blur of standard deviation `sigma` pixels, cut off at 3 sigma,
+/// a sigma of 0 or less returns the pixels unchanged
+pub fn gaussian_blur(pixels: &[Color], width: usize, height: usize, sigma: f64) -> Vec<Color> {
+    let radius = if sigma > 0.0 {
+        (3.0 * sigma).ceil() as usize
+    } else {
+        0
+    };
+    separable(pixels, width, height, &gaussian_kernel(radius, sigma))
+}
+
+/// Per channel median of each pixel's 3x3 neighborhood, removes isolated
+/// fireflies (and single dark dropouts) while keeping edges sharp
+pub fn median_3x3(pixels: &[Color], width: usize, height: usize) -> Vec<Color> {
+    check_size(pixels, width, height);
+
+    let mut filtered = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mut channels = [[0.0; 9]; 3];
+            for (n, (dx, dy)) in (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .enumerate()
+            {
+                let sx = (x as isize + dx).clamp(0, width as isize - 1) as usize;
+                let sy = (y as isize + dy).clamp(0, height as isize - 1) as usize;
+                let pixel = pixels[sy * width + sx];
+                channels[0][n] = pixel.x();
+                channels[1][n] = pixel.y();
+                channels[2][n] = pixel.z();
+            }
+
+            let [r, g, b] = channels.map(|mut channel| {
+                channel.sort_by(f64::total_cmp);
+                channel[4]
+            });
+            filtered.push(Color::new(r, g, b));
+        }
+    }
+
+    filtered
+}
+
+/// Normalized gaussian weights of deviation `sigma` for offsets
+/// `-radius..=radius`
+pub(crate) fn gaussian_kernel(radius: usize, sigma: f64) -> Vec<f64> {
+    if radius == 0 {
+        return vec![1.0];
+    }
+
+    let weights: Vec<f64> = (-(radius as isize)..=radius as isize)
+        .map(|offset| (-((offset * offset) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Convolve rows then columns with the same odd length kernel
+pub(crate) fn separable(
+    pixels: &[Color],
+    width: usize,
+    height: usize,
+    kernel: &[f64],
+) -> Vec<Color> {
+    check_size(pixels, width, height);
+
+    let mut rows = vec![Color::BLACK; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * width + x] = convolve(kernel, |offset| {
+                let sx = (x as isize + offset).clamp(0, width as isize - 1) as usize;
+                pixels[y * width + sx]
+            });
+        }
+    }
+
+    let mut columns = vec![Color::BLACK; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            columns[y * width + x] = convolve(kernel, |offset| {
+                let sy = (y as isize + offset).clamp(0, height as isize - 1) as usize;
+                rows[sy * width + x]
+            });
+        }
+    }
+
+    columns
+}
+
+fn convolve(kernel: &[f64], sample: impl Fn(isize) -> Color) -> Color {
+    let radius = (kernel.len() / 2) as isize;
+
+    kernel
+        .iter()
+        .enumerate()
+        .fold(Color::BLACK, |sum, (n, weight)| {
+            sum + sample(n as isize - radius) * *weight
+        })
+}
+
+fn check_size(pixels: &[Color], width: usize, height: usize) {
+    assert_eq!(
+        pixels.len(),
+        width * height,
+        "expected {width} x {height} pixels"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    // single bright pixel in the middle of a black image
+    fn spot(size: usize, light: Color) -> Vec<Color> {
+        let mut pixels = vec![Color::BLACK; size * size];
+        pixels[size / 2 * size + size / 2] = light;
+        pixels
+    }
+
+    fn total(pixels: &[Color]) -> f64 {
+        pixels.iter().map(|pixel| pixel.x()).sum()
+    }
+
+    fn lit(pixels: &[Color]) -> usize {
+        pixels.iter().filter(|pixel| pixel.x() > 1e-12).count()
+    }
+
+    #[test]
+    fn test_gaussian_kernel() {
+        assert_eq!(gaussian_kernel(0, 1.0), [1.0]);
+
+        let kernel = gaussian_kernel(4, 4.0 / 3.0);
+        assert_eq!(kernel.len(), 9);
+        assert::float(kernel.iter().sum(), 1.0, 12);
+        assert_eq!(kernel[0], kernel[8]);
+        assert!(kernel[4] > kernel[3] && kernel[3] > kernel[0]);
+    }
+
+    #[test]
+    fn test_box_blur_keeps_energy() {
+        let pixels = spot(9, Color::new(9.0, 9.0, 9.0));
+        let blurred = box_blur(&pixels, 9, 9, 1);
+
+        // the light spreads evenly over its 3x3 neighborhood
+        assert_eq!(lit(&blurred), 9);
+        assert::float(blurred[4 * 9 + 4].x(), 1.0, 12);
+        assert::float(blurred[3 * 9 + 5].x(), 1.0, 12);
+        assert::float(total(&blurred), total(&pixels), 12);
+
+        assert_eq!(box_blur(&pixels, 9, 9, 0), pixels);
+    }
+
+    #[test]
+    fn test_gaussian_blur() {
+        let pixels = spot(15, Color::new(10.0, 10.0, 10.0));
+        let blurred = gaussian_blur(&pixels, 15, 15, 1.0);
+
+        // reaches 3 sigma, falls off with distance and keeps the energy
+        assert_eq!(lit(&blurred), 7 * 7);
+        let row: Vec<f64> = (7..11).map(|x| blurred[7 * 15 + x].x()).collect();
+        assert!(row.windows(2).all(|pair| pair[0] > pair[1]), "{row:?}");
+        assert::float(total(&blurred), total(&pixels), 9);
+
+        assert_eq!(gaussian_blur(&pixels, 15, 15, 0.0), pixels);
+    }
+
+    #[test]
+    fn test_blur_clamps_edges() {
+        // a uniform image stays uniform, the edges see copies of themselves
+        let pixels = vec![Color::new(0.3, 0.5, 0.7); 4 * 3];
+        for blurred in [
+            box_blur(&pixels, 4, 3, 2),
+            gaussian_blur(&pixels, 4, 3, 2.0),
+            median_3x3(&pixels, 4, 3),
+        ] {
+            for (a, b) in blurred.iter().zip(&pixels) {
+                assert::float(a.x(), b.x(), 12);
+                assert::float(a.y(), b.y(), 12);
+                assert::float(a.z(), b.z(), 12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_median_removes_firefly() {
+        let mut pixels = vec![Color::new(0.2, 0.2, 0.2); 5 * 5];
+        pixels[2 * 5 + 2] = Color::new(100.0, 0.2, 0.2);
+        pixels[0] = Color::BLACK;
+
+        let filtered = median_3x3(&pixels, 5, 5);
+        assert!(filtered.iter().all(|p| *p == Color::new(0.2, 0.2, 0.2)));
+
+        // an edge between two flat regions survives
+        let edge: Vec<Color> = (0..5 * 5)
+            .map(|n| {
+                if n % 5 < 2 {
+                    Color::BLACK
+                } else {
+                    Color::WHITE
+                }
+            })
+            .collect();
+        assert_eq!(median_3x3(&edge, 5, 5), edge);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 3 x 3 pixels")]
+    fn test_size_mismatch() {
+        box_blur(&[Color::BLACK; 4], 3, 3, 1);
+    }
+}
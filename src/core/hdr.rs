@@ -0,0 +1,355 @@
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::core::save_atomic;
+use crate::core::Color;
+use crate::core::Image;
+
+/// Largest width or height accepted from a resolution line
+const MAX_SIDE: usize = 1 << 16;
+
+/// https://en.wikipedia.org/wiki/RGBE_image_format
+/// Radiance HDR (.hdr)
+///
+/// Stores linear floating point color without gamma or clamping, so values
+/// above 1.0 (e.g. from bright lights) survive for compositing and tone mapping
+///
+/// Each pixel is 4 bytes, an 8-bit mantissa per channel sharing the exponent
+/// of the brightest channel
+///
+/// ```text
+/// #?RADIANCE
+/// FORMAT=32-bit_rle_rgbe
+///
+/// -Y 2 +X 3
+/// <width * height * 4 bytes of rgbe pixels, top row first>
+/// ```
+pub fn write<W: Write>(writer: &mut W, image: &Image) -> Result<(), std::io::Error> {
+    writeln!(writer, "#?RADIANCE")?;
+    writeln!(writer, "FORMAT=32-bit_rle_rgbe")?;
+    writeln!(writer)?;
+    writeln!(writer, "-Y {} +X {}", image.height, image.width)?;
+
+    // flat (not run length encoded) scanlines are valid for any width
+    for pixel in &image.pixels {
+        writer.write_all(&encode_rgbe(*pixel))?;
+    }
+
+    Ok(())
+}
+
+/// Read a Radiance HDR image, e.g. an equirectangular environment map
+///
+/// Supports flat and run length encoded (new style) scanlines in the standard
+/// `-Y height +X width` orientation
+pub fn read<R: BufRead>(reader: &mut R) -> Result<Image, std::io::Error> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid(format!("missing radiance signature: {line:?}")));
+    }
+
+    // header variables until an empty line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid(String::from("unexpected end of header")));
+        }
+
+        let variable = line.trim();
+        if variable.is_empty() {
+            break;
+        }
+
+        if let Some(format) = variable.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid(format!("unsupported format: {format}")));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse::<usize>().ok(), width.parse::<usize>().ok()),
+        _ => (None, None),
+    };
+    let (Some(height), Some(width)) = (height, width) else {
+        return Err(invalid(format!("unsupported resolution: {line:?}")));
+    };
+
+    if width > MAX_SIDE || height > MAX_SIDE || width.checked_mul(height).is_none() {
+        return Err(invalid(format!("resolution {width}x{height} is too large")));
+    }
+
+    // grow the pixels as scanlines arrive instead of trusting the header
+    let mut pixels = Vec::new();
+    let mut scanline = vec![[0_u8; 4]; width];
+
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|rgbe| decode_rgbe(*rgbe)));
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn read_scanline<R: BufRead>(
+    reader: &mut R,
+    scanline: &mut [[u8; 4]],
+) -> Result<(), std::io::Error> {
+    let width = scanline.len();
+    if width == 0 {
+        return Ok(());
+    }
+
+    let mut first = [0_u8; 4];
+    reader.read_exact(&mut first)?;
+
+    // run length encoded scanlines start with 2 2 followed by the width
+    let encoded = (8..0x8000).contains(&width)
+        && first[0] == 2
+        && first[1] == 2
+        && ((first[2] as usize) << 8 | first[3] as usize) == width;
+
+    if !encoded {
+        scanline[0] = first;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    // each channel is stored separately as runs (count > 128) or literals
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0_u8; 1];
+            reader.read_exact(&mut count)?;
+
+            let (count, run) = if count[0] > 128 {
+                ((count[0] - 128) as usize, true)
+            } else {
+                (count[0] as usize, false)
+            };
+
+            if count == 0 || x + count > width {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "bad scanline run length",
+                ));
+            }
+
+            if run {
+                let mut value = [0_u8; 1];
+                reader.read_exact(&mut value)?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value[0];
+                }
+            } else {
+                let mut values = vec![0_u8; count];
+                reader.read_exact(&mut values)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+            }
+
+            x += count;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn encode_rgbe(color: Color) -> [u8; 4] {
+    let [r, g, b] = [color.x(), color.y(), color.z()].map(|c| c.max(0.0));
+    let brightest = r.max(g).max(b);
+
+    if brightest < 1e-32 || !brightest.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    // shared exponent such that brightest / 2^exponent is in [0.5, 1),
+    // capped at the largest one the exponent byte holds so channels past
+    // 2^127 saturate at the brightest encodable value instead of wrapping
+    let exponent = (brightest.log2().floor() as i32 + 1).min(127);
+    let scale = 256.0 / 2.0_f64.powi(exponent);
+
+    let mantissa = |c: f64| (c * scale).min(255.0) as u8;
+
+    [
+        mantissa(r),
+        mantissa(g),
+        mantissa(b),
+        (exponent + 128) as u8,
+    ]
+}
+
+pub fn decode_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::BLACK;
+    }
+
+    // +0.5 samples the middle of the mantissa bucket rather than its floor
+    let scale = 2.0_f64.powi(e as i32 - (128 + 8));
+    let channel = |m: u8| (m as f64 + 0.5) * scale;
+
+    Color::new(channel(r), channel(g), channel(b))
+}
+
+impl Image {
+    pub fn load_hdr(filepath: &str) -> Result<Image, std::io::Error> {
+        let file = std::fs::File::open(filepath)?;
+        read(&mut std::io::BufReader::new(file))
+    }
+
+    pub fn save_hdr(&self, filepath: &str) -> Result<(), std::io::Error> {
+        save_atomic(filepath, |writer| write(writer, self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    fn assert_relative(a: f64, b: f64) {
+        // 8-bit mantissa keeps roughly 2 significant digits
+        assert!((a - b).abs() <= b.abs() * 0.01, "got {a}, expected {b}");
+    }
+
+    #[test]
+    fn test_rgbe_round_trip() {
+        for color in [
+            Color::new(12.5, 3.0, 0.75),
+            Color::new(0.5, 0.25, 0.125),
+            Color::new(1000.0, 999.0, 998.0),
+        ] {
+            let decoded = decode_rgbe(encode_rgbe(color));
+            let brightest = color.x().max(color.y()).max(color.z());
+            for n in 0..3 {
+                // error is relative to the brightest channel since they share the exponent
+                assert!((decoded[n] - color[n]).abs() <= brightest * 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rgbe_saturates() {
+        assert_eq!(
+            encode_rgbe(Color::new(1e300, 1e300, 1e300)),
+            [255, 255, 255, 255]
+        );
+        assert_eq!(
+            encode_rgbe(Color::new(2.0_f64.powi(127), 0.0, 0.0)),
+            [255, 0, 0, 255]
+        );
+
+        // the top exponent itself still encodes normally
+        let top = Color::new(2.0_f64.powi(126), 0.0, 0.0);
+        assert_eq!(encode_rgbe(top), [128, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_rgbe_black() {
+        assert_eq!(encode_rgbe(Color::BLACK), [0, 0, 0, 0]);
+        assert_eq!(encode_rgbe(Color::new(-1.0, -2.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(decode_rgbe([0, 0, 0, 0]), Color::BLACK);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4] = Color::new(12.5, 6.25, 3.125);
+
+        let mut bytes = vec![];
+        write(&mut bytes, &image).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+        assert!(bytes.starts_with(header));
+
+        let data = &bytes[header.len()..];
+        assert_eq!(data.len(), 3 * 2 * 4);
+
+        let pixel: [u8; 4] = data[4 * 4..5 * 4].try_into().unwrap();
+        let bright = decode_rgbe(pixel);
+        assert_relative(bright.x(), 12.5);
+        assert_relative(bright.y(), 6.25);
+        assert_relative(bright.z(), 3.125);
+
+        let pixel: [u8; 4] = data[0..4].try_into().unwrap();
+        assert::float(decode_rgbe(pixel).x(), 0.0, 9);
+    }
+
+    #[test]
+    fn test_read_flat() {
+        let mut image = Image::new(3, 2);
+        image.pixels[1] = Color::new(0.5, 2.0, 8.0);
+        image.pixels[5] = Color::new(100.0, 0.0, 0.0);
+
+        let mut bytes = vec![];
+        write(&mut bytes, &image).unwrap();
+
+        let read = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((read.width, read.height), (3, 2));
+        for (a, b) in read.pixels.iter().zip(&image.pixels) {
+            assert_eq!(encode_rgbe(*a), encode_rgbe(*b));
+        }
+    }
+
+    #[test]
+    fn test_read_run_length_encoded() {
+        let mut bytes = b"#?RGBE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend([2, 2, 0, 8]);
+        // red: run of 8
+        bytes.extend([128 + 8, 128]);
+        // green: 2 literals then run of 6
+        bytes.extend([2, 0, 255, 128 + 6, 64]);
+        // blue: run of 8 zeros
+        bytes.extend([128 + 8, 0]);
+        // exponent: run of 8
+        bytes.extend([128 + 8, 129]);
+
+        let image = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (8, 1));
+        assert_eq!(encode_rgbe(image.pixels[0]), [128, 0, 0, 129]);
+        assert_eq!(encode_rgbe(image.pixels[1]), [128, 255, 0, 129]);
+        assert_eq!(encode_rgbe(image.pixels[7]), [128, 64, 0, 129]);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(read(&mut b"P3\n3 2\n255\n".as_slice()).is_err());
+        assert!(
+            read(&mut b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n".as_slice()).is_err()
+        );
+        assert!(read(&mut b"#?RADIANCE\n\n+Y 1 +X 1\n".as_slice()).is_err());
+        // truncated pixel data
+        assert!(read(&mut b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x81".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_read_hostile_resolution() {
+        let header = |resolution: &str| format!("#?RADIANCE\n\n{resolution}\n").into_bytes();
+
+        // rejected from the resolution line alone, before anything is allocated
+        for resolution in ["-Y 65537 +X 1", "-Y 1 +X 18446744073709551615"] {
+            let error = read(&mut header(resolution).as_slice()).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                std::io::ErrorKind::InvalidData,
+                "{resolution}"
+            );
+        }
+
+        // a plausible size with no pixel data runs out of input
+        let error = read(&mut header("-Y 65536 +X 65536").as_slice()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::core::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageError {
+    /// Images differ in size, (width, height) of each
+    DimensionMismatch((usize, usize), (usize, usize)),
+    /// Blend weights sum to zero so there is no mean to take
+    ZeroWeight,
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::DimensionMismatch((w1, h1), (w2, h2)) => {
+                write!(f, "image dimensions differ, {w1}x{h1} and {w2}x{h2}")
+            }
+            ImageError::ZeroWeight => write!(f, "image blend weights sum to zero"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Linear (not gamma corrected) pixel buffer in row-major order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![Color::BLACK; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Blend `other` into this image as the weighted mean of the two, e.g.
+    /// merge render passes by weighting each with its samples per pixel
+    pub fn accumulate(
+        &mut self,
+        other: &Image,
+        weight_self: f64,
+        weight_other: f64,
+    ) -> Result<(), ImageError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(ImageError::DimensionMismatch(
+                (self.width, self.height),
+                (other.width, other.height),
+            ));
+        }
+
+        let total = weight_self + weight_other;
+        if total == 0.0 {
+            return Err(ImageError::ZeroWeight);
+        }
+
+        let weight_self = weight_self / total;
+        let weight_other = weight_other / total;
+
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            *pixel = *pixel * weight_self + *other * weight_other;
+        }
+
+        Ok(())
+    }
+
+    /// Gamma corrected bytes with opaque alpha, four per pixel in row-major
+    /// order, e.g. to upload as an RGBA8 texture
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.to_rgba8_with(Color::GAMMA, false, false)
+    }
+
+    /// `to_rgba8` with the encoding of `Color::to_rgb8_with`, dithered by
+    /// pixel position when `dither` is set
+    pub fn to_rgba8_with(&self, gamma: f64, linear: bool, dither: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for (index, pixel) in self.pixels.iter().enumerate() {
+            let [r, g, b] = if dither {
+                let (x, y) = (index % self.width, index / self.width);
+                pixel.to_rgb8_dithered_with(x, y, gamma, linear)
+            } else {
+                pixel.to_rgb8_with(gamma, linear)
+            };
+            bytes.extend_from_slice(&[r, g, b, u8::MAX]);
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    #[test]
+    fn test_new() {
+        let image = Image::new(3, 2);
+        assert_eq!(image.pixels.len(), 6);
+        assert!(image.pixels.iter().all(|p| *p == Color::BLACK));
+    }
+
+    #[test]
+    fn test_get() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4] = Color::RED;
+        assert_eq!(image.get(1, 1), Color::RED);
+    }
+
+    #[test]
+    fn test_to_rgba8() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4] = Color::new(0.0, 1.0, 0.5);
+        image.pixels[5] = Color::new(2.0, -1.0, 0.25);
+
+        let bytes = image.to_rgba8();
+        assert_eq!(bytes.len(), 3 * 2 * 4);
+        assert_eq!(bytes[..4], [0, 0, 0, 255]);
+        assert_eq!(bytes[16..20], [0, 255, 181, 255]);
+        assert_eq!(bytes[20..], [255, 0, 128, 255]);
+
+        let linear = image.to_rgba8_with(Color::GAMMA, true, false);
+        assert_eq!(linear[16..20], [0, 255, 128, 255]);
+    }
+
+    #[test]
+    fn test_accumulate_identical() {
+        let mut image = Image::new(3, 2);
+        image.pixels[1] = Color::new(0.2, 0.4, 0.6);
+        let other = image.clone();
+
+        image.accumulate(&other, 1.0, 1.0).unwrap();
+        assert_eq!(image, other);
+    }
+
+    #[test]
+    fn test_accumulate_weighted_mean() {
+        let mut dark = Image::new(2, 2);
+        dark.pixels = vec![Color::new(0.2, 0.2, 0.2); 4];
+        let mut bright = Image::new(2, 2);
+        bright.pixels = vec![Color::new(1.0, 0.6, 0.0); 4];
+
+        // e.g. a 100 sample pass merged with a 300 sample pass
+        dark.accumulate(&bright, 100.0, 300.0).unwrap();
+        for pixel in &dark.pixels {
+            assert::float(pixel.x(), 0.8, 9);
+            assert::float(pixel.y(), 0.5, 9);
+            assert::float(pixel.z(), 0.05, 9);
+        }
+    }
+
+    #[test]
+    fn test_accumulate_errors() {
+        let mut image = Image::new(3, 2);
+        let original = image.clone();
+
+        let error = image.accumulate(&Image::new(2, 3), 1.0, 1.0);
+        assert_eq!(error, Err(ImageError::DimensionMismatch((3, 2), (2, 3))));
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "image dimensions differ, 3x2 and 2x3"
+        );
+
+        let error = image.accumulate(&Image::new(3, 2), 0.0, 0.0);
+        assert_eq!(error, Err(ImageError::ZeroWeight));
+
+        assert_eq!(image, original);
+    }
+}
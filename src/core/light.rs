@@ -0,0 +1,211 @@
+use crate::core::Color;
+use crate::geo::Point3;
+use crate::geo::Vec3;
+
+/// Infinitely small light shining equally in every direction, a cheap
+/// stand-in for a small `DiffuseLight` that casts hard shadows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Point3,
+    pub color: Color,
+    /// Radiant intensity, the light reaching a surface at distance d falls
+    /// off with 1 / d^2
+    pub intensity: f64,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(0.0, 0.0, 0.0),
+            color: Color::WHITE,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Point light restricted to a cone around `direction`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotLight {
+    pub position: Point3,
+    pub direction: Vec3,
+    /// Angle in degrees between `direction` and the edge of the cone
+    pub cone_angle: f64,
+    /// Width in degrees of the soft edge inside the cone where the light
+    /// fades out, 0 for a hard edge
+    pub falloff_angle: f64,
+    pub color: Color,
+    pub intensity: f64,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            cone_angle: 30.0,
+            falloff_angle: 5.0,
+            color: Color::WHITE,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Analytic light added to diffuse hits by the camera with a shadow ray, see
+/// `CameraBuilder::light`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Light::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
+    }
+}
+
+/// Light arriving at a point from a single light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    /// Unit direction from the point towards the light
+    pub direction: Vec3,
+    /// Distance to the light, shadow rays stop short of it
+    pub distance: f64,
+    /// Irradiance on a surface facing the light, before the cosine term
+    pub irradiance: Color,
+}
+
+impl Light {
+    pub fn position(&self) -> Point3 {
+        match self {
+            Light::Point(light) => light.position,
+            Light::Spot(light) => light.position,
+        }
+    }
+
+    /// Light reaching `p` ignoring occluders, None when `p` is outside a spot
+    /// light's cone or on top of the light
+    pub fn sample(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = Vec3::from(self.position()) - Vec3::from(*p);
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+
+        let (color, intensity, falloff) = match self {
+            Light::Point(light) => (light.color, light.intensity, 1.0),
+            Light::Spot(light) => (light.color, light.intensity, light.falloff(&-direction)),
+        };
+
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: color * (intensity * falloff / distance_squared),
+        })
+    }
+}
+
+impl SpotLight {
+    // fraction of the intensity sent along unit `direction` from the light,
+    // smoothstep from the cone edge to falloff_angle inside it
+    fn falloff(&self, direction: &Vec3) -> f64 {
+        let cos_angle = direction.dot(&self.direction.unit());
+        let cos_outer = self.cone_angle.to_radians().cos();
+        let cos_inner = (self.cone_angle - self.falloff_angle.max(0.0))
+            .max(0.0)
+            .to_radians()
+            .cos();
+
+        if cos_angle < cos_outer {
+            return 0.0;
+        }
+
+        if cos_angle >= cos_inner {
+            return 1.0;
+        }
+
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    #[test]
+    fn test_point_light() {
+        let light = Light::from(PointLight {
+            position: Point3::new(0.0, 2.0, 0.0),
+            color: Color::new(1.0, 0.5, 0.25),
+            intensity: 8.0,
+        });
+
+        let sample = light.sample(&Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.irradiance, Color::new(2.0, 1.0, 0.5));
+
+        // inverse square falloff
+        let far = light.sample(&Point3::new(0.0, -2.0, 0.0)).unwrap();
+        assert_eq!(far.irradiance, sample.irradiance * 0.25);
+
+        assert!(light.sample(&Point3::new(0.0, 2.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_spot_light() {
+        let light = Light::from(SpotLight {
+            position: Point3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(0.0, -2.0, 0.0),
+            cone_angle: 45.0,
+            falloff_angle: 10.0,
+            ..Default::default()
+        });
+
+        // straight down, at the edge of the cone and outside it
+        let center = light.sample(&Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(center.irradiance, Color::WHITE);
+
+        let inside = light.sample(&Point3::new(0.5, 0.0, 0.0)).unwrap();
+        assert_eq!(inside.irradiance, Color::WHITE * (1.0 / 1.25));
+
+        let edge = light.sample(&Point3::new(0.9, 0.0, 0.0)).unwrap();
+        assert!(edge.irradiance.x() < 1.0 / 1.81);
+        assert!(edge.irradiance.x() > 0.0);
+
+        assert!(light.sample(&Point3::new(1.1, 0.0, 0.0)).is_none());
+        assert!(light.sample(&Point3::new(0.0, 2.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_spot_light_hard_edge() {
+        let light = SpotLight {
+            cone_angle: 30.0,
+            falloff_angle: 0.0,
+            ..Default::default()
+        };
+
+        let just_inside = (29.9_f64.to_radians().sin(), -29.9_f64.to_radians().cos());
+        let direction = Vec3::new(just_inside.0, just_inside.1, 0.0);
+        assert::float(light.falloff(&direction), 1.0, 9);
+
+        let just_outside = (30.1_f64.to_radians().sin(), -30.1_f64.to_radians().cos());
+        let direction = Vec3::new(just_outside.0, just_outside.1, 0.0);
+        assert_eq!(light.falloff(&direction), 0.0);
+    }
+}
@@ -0,0 +1,218 @@
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::core::ppm;
+use crate::core::save_atomic;
+
+/// Settings an image was rendered with, saved as a JSON sidecar next to it
+/// so the image can be reproduced later, see `Camera::render_meta`
+///
+/// ```json
+/// {
+///   "width": 400,
+///   "height": 225,
+///   "samples_per_pixel": 100,
+///   "max_depth": 50,
+///   "seed": 7,
+///   "elapsed_seconds": 12.5,
+///   "camera": { "center": [13.0, 2.0, 3.0], ... }
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RenderMeta {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    /// Seed of a seeded render, None when the generators were not reseeded
+    pub seed: Option<u64>,
+    pub elapsed_seconds: f64,
+    pub camera: CameraMeta,
+}
+
+/// Camera geometry and output settings, the initialized values rays are
+/// generated from rather than the builder inputs that led to them
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CameraMeta {
+    pub center: [f64; 3],
+    pub pixel_00: [f64; 3],
+    pub pixel_delta_u: [f64; 3],
+    pub pixel_delta_v: [f64; 3],
+    pub defocus_angle: f64,
+    pub defocus_disk_u: [f64; 3],
+    pub defocus_disk_v: [f64; 3],
+    pub aperture_blades: u32,
+    pub hit_epsilon: f64,
+    pub clamp_radiance: Option<f64>,
+    pub gamma: f64,
+    pub linear: bool,
+    pub dither: bool,
+}
+
+impl RenderMeta {
+    pub fn from_json(json: &str) -> Result<RenderMeta, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("render metadata is always serializable")
+    }
+
+    /// Path of the sidecar for an image, its extension replaced by json,
+    /// e.g. `renders/image.json` for `renders/image.ppm`
+    pub fn sidecar_path(image_path: &str) -> PathBuf {
+        Path::new(image_path).with_extension("json")
+    }
+
+    pub async fn save(&self, filepath: &Path) -> Result<(), std::io::Error> {
+        save_atomic(filepath, |writer| writeln!(writer, "{}", self.to_json()))
+    }
+}
+
+impl ppm::V3 {
+    /// Save the image to `filepath` and `meta` to its sidecar, see
+    /// `RenderMeta::sidecar_path`
+    pub async fn save_with_meta(
+        &self,
+        filepath: &str,
+        meta: &RenderMeta,
+    ) -> Result<(), std::io::Error> {
+        self.save(filepath).await?;
+        meta.save(&RenderMeta::sidecar_path(filepath)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Camera;
+    use crate::core::Color;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            RenderMeta::sidecar_path("renders/image.ppm"),
+            PathBuf::from("renders/image.json")
+        );
+        assert_eq!(
+            RenderMeta::sidecar_path("image"),
+            PathBuf::from("image.json")
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let camera = Camera::new()
+            .image_height(20)
+            .aspect_ratio(2.0)
+            .samples_per_pixel(8)
+            .look_from(1.0, 2.0, 3.0)
+            .defocus_angle(0.5)
+            .initialize();
+        let meta = camera.render_meta(Some(7), std::time::Duration::from_millis(1500));
+
+        assert_eq!(meta.width, 40);
+        assert_eq!(meta.height, 20);
+        assert_eq!(meta.samples_per_pixel, 8);
+        assert_eq!(meta.seed, Some(7));
+        assert_eq!(meta.elapsed_seconds, 1.5);
+        assert_eq!(meta.camera.center, [1.0, 2.0, 3.0]);
+        assert_eq!(meta.camera.defocus_angle, 0.5);
+
+        assert_eq!(RenderMeta::from_json(&meta.to_json()).unwrap(), meta);
+    }
+
+    #[tokio::test]
+    async fn test_save_with_meta() {
+        let dir = std::env::temp_dir().join(format!("ray-tracer-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("image.ppm");
+        let output = output.to_str().unwrap();
+
+        let ppm = ppm::V3 {
+            width: 1,
+            height: 1,
+            pixels: vec![Color::WHITE],
+            dither: false,
+            gamma: Color::GAMMA,
+            linear: false,
+            comments: vec![],
+            sixteen_bit: false,
+            binary: false,
+        };
+        let meta = Camera::new()
+            .image_height(1)
+            .initialize()
+            .render_meta(None, std::time::Duration::ZERO);
+
+        ppm.save_with_meta(output, &meta).await.unwrap();
+
+        let json = std::fs::read_to_string(dir.join("image.json")).unwrap();
+        assert_eq!(RenderMeta::from_json(&json).unwrap(), meta);
+        assert!(std::fs::read_to_string(output).unwrap().starts_with("P3"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_seeded_meta() {
+        let dir =
+            std::env::temp_dir().join(format!("ray-tracer-seeded-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("seeded.ppm");
+
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(2)
+            .output(output.to_str().unwrap())
+            .save_meta(true)
+            .initialize();
+        let image = camera.render_seeded(&crate::geo::HittableList::new(), 9);
+        camera
+            .save_seeded(&image, 9, std::time::Duration::from_secs(2))
+            .await;
+
+        let json = std::fs::read_to_string(dir.join("seeded.json")).unwrap();
+        let meta = RenderMeta::from_json(&json).unwrap();
+        assert_eq!(meta.seed, Some(9));
+        assert_eq!(meta.elapsed_seconds, 2.0);
+        assert!(std::fs::read_to_string(&output).unwrap().starts_with("P3"));
+        assert!(!dir.join("seeded.json.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_render_save_meta() {
+        let dir =
+            std::env::temp_dir().join(format!("ray-tracer-render-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("render.ppm");
+
+        let camera = Camera::new()
+            .image_height(4)
+            .samples_per_pixel(2)
+            .quiet(true)
+            .save_interval(0.0)
+            .output(output.to_str().unwrap())
+            .save_meta(true)
+            .initialize();
+        camera.render(&crate::geo::HittableList::new()).await;
+
+        let json = std::fs::read_to_string(dir.join("render.json")).unwrap();
+        let meta = RenderMeta::from_json(&json).unwrap();
+        assert_eq!(
+            meta,
+            RenderMeta {
+                elapsed_seconds: meta.elapsed_seconds,
+                ..camera.render_meta(None, std::time::Duration::ZERO)
+            }
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
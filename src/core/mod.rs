@@ -1,11 +1,42 @@
+pub mod accumulator;
+pub mod animation;
+pub mod background;
 pub mod camera;
+pub mod checkpoint;
 pub mod color;
+pub mod environment;
+pub mod file;
+pub mod filter;
+pub mod hdr;
+pub mod image;
+pub mod light;
+#[cfg(feature = "serde")]
+pub mod meta;
+#[cfg(feature = "serde")]
+pub mod pose;
+pub mod post;
 pub mod ppm;
 pub mod progress;
 pub mod rand;
+pub mod stats;
+pub mod weighted;
 
+pub use accumulator::*;
+pub use animation::*;
+pub use background::*;
 pub use camera::*;
+pub use checkpoint::*;
 pub use color::*;
+pub use environment::*;
+pub use file::*;
+pub use image::*;
+pub use light::*;
+#[cfg(feature = "serde")]
+pub use meta::*;
+#[cfg(feature = "serde")]
+pub use pose::*;
 pub use ppm::*;
 pub use progress::*;
 pub use rand::*;
+pub use stats::*;
+pub use weighted::*;
@@ -0,0 +1,129 @@
+use serde::Deserialize;
+
+use crate::core::Camera;
+use crate::core::CameraBuilder;
+
+/// Camera definition for pipelines that describe cameras outside of code
+///
+/// ```json
+/// {
+///   "look_from": [13.0, 2.0, 3.0],
+///   "look_at": [0.0, 0.0, 0.0],
+///   "up": [0.0, 1.0, 0.0],
+///   "fov": 20.0,
+///   "aspect": 1.7777,
+///   "defocus_angle": 0.6,
+///   "focus_distance": 10.0
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CameraPose {
+    pub look_from: [f64; 3],
+    pub look_at: [f64; 3],
+    /// Camera-relative "up" direction, defaults to +y
+    #[serde(default = "default_up")]
+    pub up: [f64; 3],
+    /// Vertical field of view in degrees
+    pub fov: f64,
+    pub aspect: f64,
+    pub image_height: Option<u32>,
+    pub defocus_angle: Option<f64>,
+    pub focus_distance: Option<f64>,
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+impl CameraPose {
+    pub fn from_json(json: &str) -> Result<CameraPose, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Builder populated from the pose, fields missing from the pose keep builder defaults
+    pub fn builder(&self) -> CameraBuilder {
+        let [fx, fy, fz] = self.look_from;
+        let [ax, ay, az] = self.look_at;
+        let [ux, uy, uz] = self.up;
+
+        let mut builder = CameraBuilder::new()
+            .look_from(fx, fy, fz)
+            .look_at(ax, ay, az)
+            .vup(ux, uy, uz)
+            .vertical_fov(self.fov)
+            .aspect_ratio(self.aspect);
+
+        if let Some(image_height) = self.image_height {
+            builder = builder.image_height(image_height);
+        }
+
+        if let Some(defocus_angle) = self.defocus_angle {
+            builder = builder.defocus_angle(defocus_angle);
+        }
+
+        if let Some(focus_distance) = self.focus_distance {
+            builder = builder.focus_distance(focus_distance);
+        }
+
+        builder
+    }
+}
+
+impl Camera {
+    pub fn from_pose_json(json: &str) -> Result<Camera, serde_json::Error> {
+        let pose = CameraPose::from_json(json)?;
+        Ok(pose.builder().initialize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pose_json() {
+        let json = r#"{
+            "look_from": [13.0, 2.0, 3.0],
+            "look_at": [0.0, 0.0, 0.0],
+            "up": [0.0, 1.0, 0.0],
+            "fov": 20.0,
+            "aspect": 1.5,
+            "image_height": 200,
+            "defocus_angle": 0.6,
+            "focus_distance": 10.0
+        }"#;
+
+        let camera = Camera::from_pose_json(json).unwrap();
+
+        let expected = Camera::new()
+            .aspect_ratio(1.5)
+            .image_height(200)
+            .vertical_fov(20.0)
+            .look_from(13.0, 2.0, 3.0)
+            .look_at(0.0, 0.0, 0.0)
+            .vup(0.0, 1.0, 0.0)
+            .defocus_angle(0.6)
+            .focus_distance(10.0)
+            .initialize();
+
+        assert_eq!(camera, expected);
+    }
+
+    #[test]
+    fn test_from_pose_json_defaults() {
+        let json = r#"{
+            "look_from": [0.0, 0.0, 0.0],
+            "look_at": [0.0, 0.0, -1.0],
+            "fov": 90.0,
+            "aspect": 1.0
+        }"#;
+
+        let camera = Camera::from_pose_json(json).unwrap();
+        assert_eq!(camera, Camera::new().initialize());
+    }
+
+    #[test]
+    fn test_from_pose_json_invalid() {
+        assert!(Camera::from_pose_json(r#"{ "fov": 90.0 }"#).is_err());
+    }
+}
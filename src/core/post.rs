@@ -0,0 +1,96 @@
+//! Post-processing of rendered linear radiance, applied to the pixel buffer
+//! after rendering and before tone mapping or saving
+
+use crate::core::filter;
+use crate::core::Color;
+
+/// Glow around highlights: pixels brighter than `threshold` (luminance) are
+/// blurred with a gaussian reaching `radius` pixels and added back scaled by
+/// `intensity`, e.g. a threshold of 1.0 only blooms over-exposed pixels
+///
+/// `pixels` is row-major with `width * height` entries
+pub fn bloom(
+    pixels: &mut [Color],
+    width: usize,
+    height: usize,
+    threshold: f64,
+    radius: usize,
+    intensity: f64,
+) {
+    assert_eq!(
+        pixels.len(),
+        width * height,
+        "expected {width} x {height} pixels"
+    );
+
+    let bright: Vec<Color> = pixels
+        .iter()
+        .map(|pixel| {
+            if pixel.luminance() > threshold {
+                *pixel
+            } else {
+                Color::BLACK
+            }
+        })
+        .collect();
+
+    if bright.iter().all(|pixel| *pixel == Color::BLACK) {
+        return;
+    }
+
+    // gaussian reaching radius at 3 sigma
+    let kernel = filter::gaussian_kernel(radius, radius as f64 / 3.0);
+    let blurred = filter::separable(&bright, width, height, &kernel);
+
+    for (pixel, blurred) in pixels.iter_mut().zip(blurred) {
+        *pixel += blurred * intensity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    // single light in the middle of a dim image
+    fn spot(size: usize, light: Color) -> Vec<Color> {
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); size * size];
+        pixels[size / 2 * size + size / 2] = light;
+        pixels
+    }
+
+    #[test]
+    fn test_bloom_widens_light() {
+        let light = Color::new(50.0, 40.0, 30.0);
+        let mut pixels = spot(9, light);
+        let original = pixels.clone();
+
+        bloom(&mut pixels, 9, 9, 1.0, 3, 0.5);
+
+        // footprint above the dim background grows from 1 pixel to the kernel's 7x7
+        let lit = |pixels: &[Color]| pixels.iter().filter(|p| p.x() > 0.1 + 1e-9).count();
+        assert_eq!(lit(&original), 1);
+        assert_eq!(lit(&pixels), 49);
+
+        // brightness falls off with distance from the light
+        let row: Vec<f64> = (4..9).map(|x| pixels[4 * 9 + x].x()).collect();
+        assert!(row.windows(2).all(|pair| pair[0] > pair[1]));
+
+        // the blur keeps the extracted energy, all of it added back at intensity
+        let added: f64 = pixels
+            .iter()
+            .zip(&original)
+            .map(|(a, b)| a.x() - b.x())
+            .sum();
+        assert::float(added, 0.5 * light.x(), 9);
+    }
+
+    #[test]
+    fn test_bloom_below_threshold() {
+        let mut pixels = spot(5, Color::new(0.8, 0.8, 0.8));
+        let original = pixels.clone();
+
+        bloom(&mut pixels, 5, 5, 1.0, 2, 1.0);
+        assert_eq!(pixels, original);
+    }
+}
@@ -1,5 +1,6 @@
 use std::io::Write;
 
+use crate::core::save_atomic;
 use crate::core::Color;
 
 /// https://en.wikipedia.org/wiki/Netpbm
@@ -31,33 +32,274 @@ use crate::core::Color;
 /// 255 255 255
 ///   0   0   0
 /// ```
+///
+/// The binary form (P6) has the same header text, the pixels follow the
+/// single whitespace after the maximum value as raw bytes, one per channel
+/// or two (most significant first) for 16-bit images. About a quarter the
+/// size of P3 and written in rows, so an image can be written band by band
+/// as it renders, see `Camera::render_banded`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct V3 {
     pub width: usize,
     pub height: usize,
     pub pixels: Vec<Color>,
+    /// Apply ordered dithering when quantizing pixels to bytes
+    pub dither: bool,
+    /// Output gamma, see `Color::to_rgb8_with`
+    pub gamma: f64,
+    /// Write linear values without gamma encoding
+    pub linear: bool,
+    /// Lines written as `# ...` comments between the magic number and the
+    /// dimensions, e.g. render settings for provenance
+    pub comments: Vec<String>,
+    /// Write 16-bit values with a maximum of 65535 instead of bytes, enough
+    /// levels that smooth gradients do not band so `dither` is ignored
+    pub sixteen_bit: bool,
+    /// Write the binary form (P6), pixels as raw bytes instead of text
+    pub binary: bool,
 }
 
 impl V3 {
+    /// Add a comment, each line of a multi-line `text` becomes its own comment
+    pub fn with_comment(&mut self, text: &str) -> &mut Self {
+        self.comments.extend(text.lines().map(String::from));
+        self
+    }
+
     pub async fn save(&self, filepath: &str) -> Result<(), std::io::Error> {
-        let tmp_filepath = format!("{filepath}.tmp");
+        self.save_blocking(filepath)
+    }
 
-        let file = std::fs::File::create(&tmp_filepath)?;
-        let mut writer = std::io::BufWriter::new(file);
+    /// Same as `save` for callers outside an async runtime, e.g. rayon tasks
+    pub fn save_blocking(&self, filepath: &str) -> Result<(), std::io::Error> {
+        save_atomic(filepath, |writer| self.write(writer))
+    }
 
-        writeln!(writer, "P3")?;
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.write_header(writer)?;
+        self.write_rows(writer, 0, &self.pixels)
+    }
+
+    /// Magic number, comments, dimensions and maximum value, the pixels
+    /// of every row must follow in order
+    pub fn write_header<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        writeln!(writer, "{}", if self.binary { "P6" } else { "P3" })?;
+        for comment in &self.comments {
+            // a stray newline would end the comment and corrupt the header
+            for line in comment.lines() {
+                writeln!(writer, "# {line}")?;
+            }
+        }
         writeln!(writer, "{} {}", self.width, self.height)?;
-        writeln!(writer, "{}", Color::MAX_VALUE)?;
 
-        for pixel in &self.pixels {
-            writeln!(writer, "{pixel}")?;
+        let max = if self.sixteen_bit {
+            Color::MAX_VALUE_16
+        } else {
+            Color::MAX_VALUE
+        };
+        writeln!(writer, "{max}")
+    }
+
+    /// Whole rows of `pixels` starting at row `y`, ignoring `self.pixels`,
+    /// `y` places the dither pattern
+    pub fn write_rows<W: Write>(
+        &self,
+        writer: &mut W,
+        y: usize,
+        pixels: &[Color],
+    ) -> Result<(), std::io::Error> {
+        if !self.binary {
+            for (index, pixel) in pixels.iter().enumerate() {
+                if self.sixteen_bit {
+                    let [r, g, b] = pixel.to_rgb16_with(self.gamma, self.linear);
+                    writeln!(writer, "{r} {g} {b}")?;
+                } else {
+                    let [r, g, b] = self.to_rgb8(pixel, index, y);
+                    writeln!(writer, "{r} {g} {b}")?;
+                }
+            }
+            return Ok(());
+        }
+
+        let channels = if self.sixteen_bit { 6 } else { 3 };
+        let mut bytes = Vec::with_capacity(pixels.len() * channels);
+
+        for (index, pixel) in pixels.iter().enumerate() {
+            if self.sixteen_bit {
+                for c in pixel.to_rgb16_with(self.gamma, self.linear) {
+                    bytes.extend_from_slice(&c.to_be_bytes());
+                }
+            } else {
+                bytes.extend(self.to_rgb8(pixel, index, y));
+            }
+        }
+
+        writer.write_all(&bytes)
+    }
+
+    // pixel `index` of rows starting at `y`, dithered by its image position
+    fn to_rgb8(&self, pixel: &Color, index: usize, y: usize) -> [u8; 3] {
+        if self.dither {
+            let x = index % self.width;
+            let y = y + index / self.width;
+            pixel.to_rgb8_dithered_with(x, y, self.gamma, self.linear)
+        } else {
+            pixel.to_rgb8_with(self.gamma, self.linear)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> V3 {
+        V3 {
+            width: 2,
+            height: 1,
+            pixels: vec![Color::WHITE, Color::BLACK],
+            dither: false,
+            gamma: Color::GAMMA,
+            linear: false,
+            comments: vec![],
+            sixteen_bit: false,
+            binary: false,
         }
+    }
+
+    // header values and pixel count of a P3 file, skipping comment lines
+    fn parse(contents: &str) -> (usize, usize, u32, usize) {
+        let mut tokens = contents
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.split_whitespace());
+
+        assert_eq!(tokens.next(), Some("P3"));
+        let width = tokens.next().unwrap().parse().unwrap();
+        let height = tokens.next().unwrap().parse().unwrap();
+        let max = tokens.next().unwrap().parse().unwrap();
+        let values = tokens.filter(|t| t.parse::<u32>().is_ok()).count();
+
+        (width, height, max, values)
+    }
+
+    #[test]
+    fn test_write() {
+        let mut bytes = vec![];
+        image().write(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "P3\n2 1\n255\n255 255 255\n0 0 0\n"
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let mut ppm = image();
+        ppm.with_comment("samples_per_pixel=100")
+            .with_comment("render time 1.5s\ncamera look_from=(13, 2, 3)");
+        assert_eq!(ppm.comments.len(), 3);
+
+        // newlines pushed onto the field directly are split too
+        ppm.comments.push(String::from("max_depth=50\nseed=1"));
+
+        let mut bytes = vec![];
+        ppm.write(&mut bytes).unwrap();
+        let contents = String::from_utf8(bytes).unwrap();
+
+        assert!(contents.starts_with(
+            "P3\n\
+             # samples_per_pixel=100\n\
+             # render time 1.5s\n\
+             # camera look_from=(13, 2, 3)\n\
+             # max_depth=50\n\
+             # seed=1\n\
+             2 1\n255\n"
+        ));
+        assert_eq!(parse(&contents), (2, 1, 255, 6));
+    }
+
+    #[test]
+    fn test_write_sixteen_bit() {
+        let mut ppm = image();
+        ppm.sixteen_bit = true;
+        ppm.dither = true;
+        ppm.pixels.push(Color::new(0.5, 0.5, 0.5));
+        ppm.width = 3;
+
+        let mut bytes = vec![];
+        ppm.write(&mut bytes).unwrap();
+        let contents = String::from_utf8(bytes).unwrap();
+        assert_eq!(
+            contents,
+            "P3\n3 1\n65535\n65535 65535 65535\n0 0 0\n46340 46340 46340\n"
+        );
+        assert_eq!(parse(&contents), (3, 1, 65535, 9));
+
+        // linear mid-gray sits halfway
+        ppm.linear = true;
+        let mut bytes = vec![];
+        ppm.write(&mut bytes).unwrap();
+        let contents = String::from_utf8(bytes).unwrap();
+        assert!(contents.ends_with("\n32768 32768 32768\n"));
+    }
+
+    #[test]
+    fn test_write_binary() {
+        let mut ppm = V3 {
+            binary: true,
+            ..image()
+        };
+        ppm.comments.push(String::from("binary"));
+
+        let mut bytes = vec![];
+        ppm.write(&mut bytes).unwrap();
+        assert_eq!(bytes, b"P6\n# binary\n2 1\n255\n\xff\xff\xff\0\0\0");
+
+        ppm.sixteen_bit = true;
+        let mut bytes = vec![];
+        ppm.write(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            b"P6\n# binary\n2 1\n65535\n\xff\xff\xff\xff\xff\xff\0\0\0\0\0\0"
+        );
+    }
+
+    #[test]
+    fn test_write_rows_matches_text() {
+        // the same values as P3 row by row, dithering included
+        let mut text = image();
+        text.width = 3;
+        text.height = 2;
+        text.dither = true;
+        text.pixels = (0..6)
+            .map(|n| Color::new(n as f64 / 10.0, 0.5, 0.25))
+            .collect();
+        let ppm = V3 {
+            binary: true,
+            ..text.clone()
+        };
 
-        writer.flush()?;
+        let mut bytes = vec![];
+        ppm.write_header(&mut bytes).unwrap();
+        let header = bytes.len();
+        ppm.write_rows(&mut bytes, 0, &ppm.pixels[..3]).unwrap();
+        ppm.write_rows(&mut bytes, 1, &ppm.pixels[3..]).unwrap();
 
-        // rename tmp to target filepath for fast atomic operation
-        std::fs::rename(tmp_filepath, filepath)?;
+        let mut contents = vec![];
+        text.write(&mut contents).unwrap();
+        let values: Vec<u8> = String::from_utf8(contents)
+            .unwrap()
+            .lines()
+            .skip(3)
+            .flat_map(|line| {
+                line.split_whitespace()
+                    .map(|v| v.parse().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
 
-        Ok(())
+        assert_eq!(&bytes[..header], b"P6\n3 2\n255\n");
+        assert_eq!(bytes[header..], values);
     }
 }
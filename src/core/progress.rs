@@ -1,17 +1,36 @@
 use std::{
+    io::IsTerminal,
     sync::{atomic, Arc},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 #[derive(Debug)]
 pub struct State {
     cur: atomic::AtomicUsize,
     max: usize,
+    start: Instant,
+    /// Set when the progress is dropped, stops drawing even if `cur` never
+    /// reached `max`, e.g. after the work failed partway
+    stopped: atomic::AtomicBool,
 }
 
 impl State {
     pub fn bar(&self, frame: usize) -> String {
+        self.bar_elapsed(frame, self.start.elapsed())
+    }
+
+    /// Progress without carriage returns or color codes, e.g. for logs
+    pub fn line(&self) -> String {
+        self.line_elapsed(self.start.elapsed())
+    }
+
+    pub fn percent(&self) -> u32 {
+        let cur = self.cur.load(atomic::Ordering::Relaxed);
+        (cur as f64 / self.max as f64 * 100.0) as u32
+    }
+
+    fn bar_elapsed(&self, frame: usize, elapsed: Duration) -> String {
         let bar_width = 48;
 
         let cur = self.cur.load(atomic::Ordering::Relaxed);
@@ -25,18 +44,49 @@ impl State {
         let percent = (percent * 100.0) as u32;
 
         let spinner = if percent == 100 {
-            format!("")
+            String::new()
         } else {
             format!("\x1b[1m\x1b[36m{spinner_frame}\x1b[0m")
         };
 
         let percent = format!("{percent:>3}%");
+        let summary = self.summary(cur, elapsed);
+
+        format!("{spinner}{percent} {filled}{empty} {summary} ")
+    }
+
+    fn line_elapsed(&self, elapsed: Duration) -> String {
+        let cur = self.cur.load(atomic::Ordering::Relaxed);
+        let percent = self.percent();
+        let summary = self.summary(cur, elapsed);
+
+        format!("{percent:>3}% {summary}")
+    }
+
+    fn summary(&self, cur: usize, elapsed: Duration) -> String {
+        let max = self.max;
+
+        // rate of completed units per second, estimate remaining time from it
+        let seconds = elapsed.as_secs_f64();
+        let rate = if seconds > 0.0 {
+            cur as f64 / seconds
+        } else {
+            0.0
+        };
+        let eta = if cur == 0 || rate <= 0.0 {
+            String::from("--:--")
+        } else {
+            let remaining = max.saturating_sub(cur) as f64 / rate;
+            format_duration(Duration::from_secs_f64(remaining))
+        };
+        let elapsed = format_duration(elapsed);
+        let rate = format_rate(rate);
 
         let cur = format_number(cur);
         let max = format_number(max);
         let digits = max.len();
         let cur = format!("{:>width$}", cur, width = digits);
-        format!("{spinner}{percent} {filled}{empty} {cur} / {max} ")
+        format!("{cur} / {max} {elapsed} ETA {eta} | {rate}/s")
     }
 
     pub fn print(&self, frame: usize) {
@@ -45,20 +95,55 @@ impl State {
     }
 }
 
-#[derive(Debug)]
+type UpdateFn = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
 pub struct Progress {
     state: Arc<State>,
     join_handle: Option<thread::JoinHandle<()>>,
+    /// Interactive bar redrawn in place, otherwise plain lines for logs
+    tty: bool,
+    /// Suppress all output
+    quiet: bool,
+    /// Called with (cur, max) on every frame, e.g. to drive a gui widget
+    on_update: Option<UpdateFn>,
+}
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("state", &self.state)
+            .field("join_handle", &self.join_handle)
+            .field("tty", &self.tty)
+            .field("quiet", &self.quiet)
+            .field("on_update", &self.on_update.is_some())
+            .finish()
+    }
 }
 
 impl Drop for Progress {
     fn drop(&mut self) {
+        self.state.stopped.store(true, atomic::Ordering::Relaxed);
         if let Some(join_handle) = self.join_handle.take() {
             join_handle.join().unwrap();
         }
 
-        self.state.print(0);
-        eprintln!();
+        if let Some(on_update) = &self.on_update {
+            on_update(
+                self.state.cur.load(atomic::Ordering::Relaxed),
+                self.state.max,
+            );
+        }
+
+        if self.quiet {
+            return;
+        }
+
+        if self.tty {
+            self.state.print(0);
+            eprintln!();
+        } else {
+            eprintln!("{}", self.state.line());
+        }
     }
 }
 
@@ -67,32 +152,93 @@ impl Progress {
         let state = Arc::new(State {
             cur: atomic::AtomicUsize::new(0),
             max,
+            start: Instant::now(),
+            stopped: atomic::AtomicBool::new(false),
         });
 
         let join_handle = None;
+        let tty = std::io::stderr().is_terminal();
+        let quiet = false;
+        let on_update = None;
+
+        Progress {
+            state,
+            join_handle,
+            tty,
+            quiet,
+            on_update,
+        }
+    }
 
-        Progress { state, join_handle }
+    /// Override stderr terminal detection
+    pub fn tty(mut self, tty: bool) -> Self {
+        self.tty = tty;
+        self
+    }
+
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Report progress to a callback on each frame, in addition to printing
+    /// unless `quiet` is also set
+    pub fn on_update<F>(mut self, on_update: F) -> Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.on_update = Some(Arc::new(on_update));
+        self
     }
 
     pub fn inc(&self) -> usize {
-        self.state.cur.fetch_add(1, atomic::Ordering::Relaxed) + 1
+        self.inc_by(1)
+    }
+
+    /// Advance by `n` units of work at once, e.g. the samples a pixel took
+    /// when pixels do uneven amounts of work, returns the new count
+    pub fn inc_by(&self, n: usize) -> usize {
+        self.state.cur.fetch_add(n, atomic::Ordering::Relaxed) + n
     }
 
     /// Spawn thread that draws at consistent fps
     pub fn render(mut self, fps: u64) -> Self {
+        if self.quiet && self.on_update.is_none() {
+            return self;
+        }
+
         let frame_duration = Duration::from_millis(1000 / fps);
         let state = self.state.clone();
+        let tty = self.tty;
+        let quiet = self.quiet;
+        let on_update = self.on_update.clone();
 
         let join_handle = thread::spawn(move || {
             let mut frame = 0;
+            let mut last_percent = 0;
 
             loop {
                 let cur = state.cur.load(atomic::Ordering::Relaxed);
                 let max = state.max;
-                let done = cur >= max;
+                let done = cur >= max || state.stopped.load(atomic::Ordering::Relaxed);
 
-                // carriage return and clear line from cursor to end
-                state.print(frame);
+                if let Some(on_update) = &on_update {
+                    on_update(cur, max);
+                }
+
+                if quiet {
+                    // callback only
+                } else if tty {
+                    // carriage return and clear line from cursor to end
+                    state.print(frame);
+                } else {
+                    // plain lines every 10%, final line is printed on drop
+                    let percent = state.percent();
+                    if !done && percent / 10 > last_percent / 10 {
+                        eprintln!("{}", state.line());
+                        last_percent = percent;
+                    }
+                }
 
                 if done {
                     break;
@@ -124,3 +270,143 @@ fn format_number(value: usize) -> String {
         .collect::<Vec<_>>()
         .join(",")
 }
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let seconds = seconds % 60;
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+fn format_rate(rate: f64) -> String {
+    if rate >= 1_000_000.0 {
+        format!("{:.1}M", rate / 1_000_000.0)
+    } else if rate >= 1_000.0 {
+        format!("{:.1}k", rate / 1_000.0)
+    } else {
+        format!("{:.0}", rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(cur: usize, max: usize) -> State {
+        State {
+            cur: atomic::AtomicUsize::new(cur),
+            max,
+            start: Instant::now(),
+            stopped: atomic::AtomicBool::new(false),
+        }
+    }
+
+    #[test]
+    fn test_bar_eta() {
+        let bar = state(500, 1000).bar_elapsed(0, Duration::from_secs(10));
+        assert!(bar.contains(" 500 / 1,000 "), "{bar}");
+        assert!(bar.ends_with(" 00:10 ETA 00:10 | 50/s "), "{bar}");
+    }
+
+    #[test]
+    fn test_bar_eta_no_progress() {
+        let bar = state(0, 1000).bar_elapsed(0, Duration::from_secs(10));
+        assert!(bar.ends_with(" 00:10 ETA --:-- | 0/s "), "{bar}");
+
+        let bar = state(0, 1000).bar_elapsed(0, Duration::ZERO);
+        assert!(bar.ends_with(" 00:00 ETA --:-- | 0/s "), "{bar}");
+    }
+
+    #[test]
+    fn test_line() {
+        let line = state(500, 1000).line_elapsed(Duration::from_secs(10));
+        assert_eq!(line, " 50%   500 / 1,000 00:10 ETA 00:10 | 50/s");
+        assert!(!line.contains('\x1b'));
+        assert!(!line.contains('\r'));
+    }
+
+    #[test]
+    fn test_quiet() {
+        let progress = Progress::new(10).quiet(true).render(15);
+        assert!(progress.join_handle.is_none());
+        progress.inc();
+    }
+
+    #[test]
+    fn test_inc_by() {
+        let progress = Progress::new(1000).quiet(true);
+        assert_eq!(progress.inc_by(250), 250);
+        assert_eq!(progress.inc(), 251);
+        assert_eq!(progress.inc_by(0), 251);
+        assert_eq!(progress.inc_by(249), 500);
+
+        let line = progress.state.line_elapsed(Duration::from_secs(10));
+        assert_eq!(line, " 50%   500 / 1,000 00:10 ETA 00:10 | 50/s");
+    }
+
+    #[test]
+    fn test_on_update() {
+        let updates = Arc::new(std::sync::Mutex::new(vec![]));
+        let updates_callback = updates.clone();
+
+        let progress = Progress::new(3)
+            .quiet(true)
+            .on_update(move |cur, max| updates_callback.lock().unwrap().push((cur, max)))
+            .render(1000);
+
+        for _ in 0..3 {
+            progress.inc();
+        }
+
+        drop(progress);
+
+        let updates = updates.lock().unwrap();
+        assert!(!updates.is_empty());
+        assert!(updates.iter().all(|(_, max)| *max == 3));
+        assert_eq!(updates.last(), Some(&(3, 3)));
+    }
+
+    #[test]
+    fn test_drop_unfinished() {
+        // work that fails partway drops the progress short of max, which
+        // must stop the drawing thread rather than wait for it forever
+        let updates = Arc::new(std::sync::Mutex::new(vec![]));
+        let updates_callback = updates.clone();
+
+        let progress = Progress::new(10)
+            .tty(false)
+            .on_update(move |cur, max| updates_callback.lock().unwrap().push((cur, max)))
+            .render(1000);
+        progress.inc_by(4);
+        drop(progress);
+
+        assert_eq!(updates.lock().unwrap().last(), Some(&(4, 10)));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "00:42");
+        assert_eq!(format_duration(Duration::from_secs(754)), "12:34");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(950.0), "950");
+        assert_eq!(format_rate(12_345.0), "12.3k");
+        assert_eq!(format_rate(2_500_000.0), "2.5M");
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(0), "0");
+        assert_eq!(format_number(1000), "1,000");
+        assert_eq!(format_number(2073600), "2,073,600");
+    }
+}
@@ -1,9 +1,171 @@
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+
+thread_local! {
+    // per thread generator so rayon workers never contend on a shared rng
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
+
+/// Reseed the current thread's generator, making the following random values
+/// on this thread reproducible
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+/// Run `f` with the current thread's generator seeded from `seed`, then put
+/// back the generator it had before, so reproducible work can run on shared
+/// threads without disturbing their other random values
+pub fn with_seed<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    // restores on drop so a panic inside `f` doesn't leak the seeded state
+    struct Restore(Option<SmallRng>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(previous) = self.0.take() {
+                RNG.with(|rng| *rng.borrow_mut() = previous);
+            }
+        }
+    }
+
+    let previous = RNG.with(|rng| rng.replace(SmallRng::seed_from_u64(seed)));
+    let _restore = Restore(Some(previous));
+    f()
+}
+
+/// Derive an independent seed for `index` (e.g. a pixel) from a base seed, so
+/// work split across threads in any order draws the same random values
+///
+/// https://prng.di.unimi.it/splitmix64.c
+pub fn seed_for(seed: u64, index: u64) -> u64 {
+    let mut z = seed ^ index.wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Position of `index` in a pseudo-random permutation of `0..len` chosen by
+/// `seed`, without storing the permutation
+///
+/// https://graphics.pixar.com/library/MultiJitteredSampling/paper.pdf
+pub fn permute(index: u32, len: u32, seed: u32) -> u32 {
+    if len <= 1 {
+        return 0;
+    }
+
+    // mask of all bits below the highest bit of len - 1
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    // bijective hash on the masked range, retried until it lands inside len
+    let p = seed;
+    let mut i = index;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < len {
+            break;
+        }
+    }
+
+    ((i as u64 + p as u64) % len as u64) as u32
+}
+
 pub fn random_f64() -> f64 {
     // [0,1)
-    rand::random_range(0.0..1.0)
+    RNG.with(|rng| rng.borrow_mut().random_range(0.0..1.0))
 }
 
 pub fn random_f64_range(min: f64, max: f64) -> f64 {
     // [min,max)
-    rand::random_range(min..max)
+    RNG.with(|rng| rng.borrow_mut().random_range(min..max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        seed(42);
+        let a = [random_f64(), random_f64_range(-1.0, 1.0)];
+
+        seed(42);
+        let b = [random_f64(), random_f64_range(-1.0, 1.0)];
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_with_seed() {
+        seed(42);
+        let expected = [random_f64(), random_f64()];
+
+        seed(7);
+        let inner = [random_f64(), random_f64()];
+
+        seed(42);
+        let a = random_f64();
+        let seeded = with_seed(7, || [random_f64(), random_f64()]);
+        let b = random_f64();
+
+        assert_eq!(seeded, inner);
+        assert_eq!([a, b], expected);
+    }
+
+    #[test]
+    fn test_seed_for() {
+        assert_eq!(seed_for(7, 3), seed_for(7, 3));
+        assert_ne!(seed_for(7, 3), seed_for(7, 4));
+        assert_ne!(seed_for(7, 3), seed_for(8, 3));
+        assert_ne!(seed_for(0, 0), seed_for(0, 1));
+    }
+
+    #[test]
+    fn test_permute() {
+        for len in [1, 2, 7, 16, 100] {
+            for seed in [0, 1, 12345, u32::MAX] {
+                let mut values: Vec<u32> = (0..len).map(|i| permute(i, len, seed)).collect();
+                values.sort();
+                assert_eq!(values, (0..len).collect::<Vec<_>>());
+            }
+        }
+
+        let a: Vec<u32> = (0..16).map(|i| permute(i, 16, 1)).collect();
+        let b: Vec<u32> = (0..16).map(|i| permute(i, 16, 2)).collect();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_range() {
+        for _ in 0..1000 {
+            let a = random_f64();
+            assert!((0.0..1.0).contains(&a));
+
+            let b = random_f64_range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&b));
+        }
+    }
 }
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+/// Counters of what a render traced, see `Camera::render_with_stats`
+///
+/// Every camera sample follows a path of rays until it escapes to the
+/// background, is absorbed, or is cut off at `max_depth`, which helps tune
+/// `max_depth` and `samples_per_pixel`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// Camera rays, one per pixel sample
+    pub samples: u64,
+    /// Every ray tested against the world, camera rays and bounces
+    pub rays: u64,
+    /// Rays that hit an object
+    pub hits: u64,
+    /// Rays that missed everything and took the background color
+    pub escapes: u64,
+    /// Paths cut off by reaching `max_depth` bounces
+    pub truncated: u64,
+    /// Wall-clock time of the render
+    pub elapsed: Duration,
+}
+
+impl RenderStats {
+    /// Average number of rays traced per camera sample
+    pub fn average_path_length(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.rays as f64 / self.samples as f64
+        }
+    }
+
+    /// Rays traced per second of wall-clock time
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            0.0
+        } else {
+            self.rays as f64 / seconds
+        }
+    }
+}
+
+impl std::ops::Add for RenderStats {
+    type Output = RenderStats;
+
+    fn add(self, rhs: RenderStats) -> RenderStats {
+        RenderStats {
+            samples: self.samples + rhs.samples,
+            rays: self.rays + rhs.rays,
+            hits: self.hits + rhs.hits,
+            escapes: self.escapes + rhs.escapes,
+            truncated: self.truncated + rhs.truncated,
+            elapsed: self.elapsed + rhs.elapsed,
+        }
+    }
+}
+
+impl std::fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "samples       {}", self.samples)?;
+        writeln!(f, "rays          {}", self.rays)?;
+        writeln!(f, "hits          {}", self.hits)?;
+        writeln!(f, "escapes       {}", self.escapes)?;
+        writeln!(f, "truncated     {}", self.truncated)?;
+        writeln!(f, "path length   {:.2}", self.average_path_length())?;
+        write!(f, "elapsed       {:?}", self.elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_path_length() {
+        assert_eq!(RenderStats::default().average_path_length(), 0.0);
+
+        let stats = RenderStats {
+            samples: 4,
+            rays: 10,
+            ..Default::default()
+        };
+        assert_eq!(stats.average_path_length(), 2.5);
+    }
+
+    #[test]
+    fn test_add() {
+        let a = RenderStats {
+            samples: 1,
+            rays: 2,
+            hits: 1,
+            escapes: 1,
+            truncated: 0,
+            elapsed: Duration::from_millis(5),
+        };
+        let b = RenderStats { truncated: 3, ..a };
+
+        let sum = a + b;
+        assert_eq!(sum.samples, 2);
+        assert_eq!(sum.rays, 4);
+        assert_eq!(sum.truncated, 3);
+        assert_eq!(sum.elapsed, Duration::from_millis(10));
+        assert_eq!(sum.rays_per_second(), 400.0);
+    }
+
+    #[test]
+    fn test_display() {
+        let stats = RenderStats {
+            samples: 2,
+            rays: 3,
+            ..Default::default()
+        };
+        let report = stats.to_string();
+        assert!(report.contains("rays          3"));
+        assert!(report.contains("path length   1.50"));
+    }
+}
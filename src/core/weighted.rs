@@ -0,0 +1,131 @@
+use crate::core::random_f64;
+
+/// Pick one of several values at random in proportion to their weights, e.g.
+/// the material of a randomly generated sphere
+///
+/// Weights are normalized so they need not sum to 1, values with a zero,
+/// negative or non finite weight are never chosen
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedChooser<T> {
+    values: Vec<T>,
+    /// Running sum of normalized weights, the last chosable entry is exactly 1
+    cumulative: Vec<f64>,
+    /// Index of the last value with a positive weight
+    last_chosable: usize,
+}
+
+impl<T> WeightedChooser<T> {
+    /// None when no value has a positive weight
+    pub fn new(items: Vec<(f64, T)>) -> Option<Self> {
+        let weight = |w: f64| if w.is_finite() && w > 0.0 { w } else { 0.0 };
+
+        let total: f64 = items.iter().map(|(w, _)| weight(*w)).sum();
+        if !(total.is_finite() && total > 0.0) {
+            return None;
+        }
+
+        let mut cumulative = Vec::with_capacity(items.len());
+        let mut values = Vec::with_capacity(items.len());
+        let mut sum = 0.0;
+        let mut last_chosable = 0;
+
+        for (index, (w, value)) in items.into_iter().enumerate() {
+            if weight(w) > 0.0 {
+                last_chosable = index;
+            }
+            sum += weight(w) / total;
+            cumulative.push(sum);
+            values.push(value);
+        }
+
+        // guard against rounding so every u in [0,1) lands on a value
+        for c in &mut cumulative[last_chosable..] {
+            *c = 1.0;
+        }
+
+        Some(WeightedChooser {
+            values,
+            cumulative,
+            last_chosable,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Normalized probability of choosing the value at `index`
+    pub fn probability(&self, index: usize) -> f64 {
+        let previous = if index == 0 {
+            0.0
+        } else {
+            self.cumulative[index - 1]
+        };
+        self.cumulative[index] - previous
+    }
+
+    pub fn choose(&self) -> &T {
+        self.choose_with(random_f64())
+    }
+
+    /// Value for a uniform number `u` in [0,1), with a single lookup
+    pub fn choose_with(&self, u: f64) -> &T {
+        let index = self
+            .cumulative
+            .partition_point(|c| *c <= u)
+            .min(self.last_chosable);
+        &self.values[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(WeightedChooser::<u8>::new(vec![]).is_none());
+        assert!(WeightedChooser::new(vec![(0.0, 'a'), (-1.0, 'b')]).is_none());
+        assert!(WeightedChooser::new(vec![(f64::NAN, 'a')]).is_none());
+
+        let chooser = WeightedChooser::new(vec![(2.0, 'a'), (6.0, 'b')]).unwrap();
+        assert_eq!(chooser.len(), 2);
+        assert_eq!(chooser.probability(0), 0.25);
+        assert_eq!(chooser.probability(1), 0.75);
+    }
+
+    #[test]
+    fn test_choose_with() {
+        let chooser =
+            WeightedChooser::new(vec![(1.0, 'a'), (0.0, 'b'), (1.0, 'c'), (0.0, 'd')]).unwrap();
+
+        assert_eq!(*chooser.choose_with(0.0), 'a');
+        assert_eq!(*chooser.choose_with(0.49), 'a');
+        assert_eq!(*chooser.choose_with(0.5), 'c');
+        assert_eq!(*chooser.choose_with(0.999_999), 'c');
+        assert_eq!(*chooser.choose_with(1.0), 'c');
+    }
+
+    #[test]
+    fn test_distribution() {
+        let chooser = WeightedChooser::new(vec![(0.4, 0), (0.3, 1), (0.3, 2)]).unwrap();
+
+        let samples = 10_000;
+        let mut counts = [0; 3];
+        for _ in 0..samples {
+            counts[*chooser.choose()] += 1;
+        }
+
+        for (count, expected) in counts.iter().zip([0.4, 0.3, 0.3]) {
+            // ~6 standard deviations of a binomial with n 10k
+            assert!(
+                (*count as f64 / samples as f64 - expected).abs() < 0.03,
+                "{counts:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::geo::Interval;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    x: Interval,
+    y: Interval,
+    z: Interval,
+}
+
+// flat boxes (e.g. an axis aligned triangle) are padded to this minimum
+// thickness so the slab test does not miss them due to float precision
+const MIN_SIZE: f64 = 0.0001;
+
+impl Aabb {
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Aabb {
+        Aabb {
+            x: pad(x),
+            y: pad(y),
+            z: pad(z),
+        }
+    }
+
+    /// Box with no extent, the identity for `union`
+    pub fn empty() -> Aabb {
+        Aabb {
+            x: Interval::empty(),
+            y: Interval::empty(),
+            z: Interval::empty(),
+        }
+    }
+
+    /// Box enclosing both points, in any order
+    pub fn from_points(a: Point3, b: Point3) -> Aabb {
+        let interval = |a: f64, b: f64| Interval::new(a.min(b), a.max(b));
+        Aabb::new(
+            interval(a.x(), b.x()),
+            interval(a.y(), b.y()),
+            interval(a.z(), b.z()),
+        )
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let interval =
+            |a: Interval, b: Interval| Interval::new(a.min().min(b.min()), a.max().max(b.max()));
+        Aabb {
+            x: interval(self.x, other.x),
+            y: interval(self.y, other.y),
+            z: interval(self.z, other.z),
+        }
+    }
+
+    pub fn axis(&self, n: u8) -> Interval {
+        match n {
+            0 => self.x,
+            1 => self.y,
+            2 => self.z,
+            _ => panic!("axis out of bounds"),
+        }
+    }
+
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min(), self.y.min(), self.z.min())
+    }
+
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max(), self.y.max(), self.z.max())
+    }
+
+    pub fn size(&self) -> Vec3 {
+        Vec3::new(self.x.size(), self.y.size(), self.z.size())
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::from(0.5 * (Vec3::from(self.min()) + Vec3::from(self.max())))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.size() < 0.0 || self.y.size() < 0.0 || self.z.size() < 0.0
+    }
+
+    /// Slab test, returns the parametric range of the ray inside the box
+    pub fn hit_interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Interval> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for n in 0..3 {
+            let axis = self.axis(n);
+            let inv_direction = 1.0 / ray.direction()[n];
+            let origin = ray.origin()[n];
+
+            let t0 = (axis.min() - origin) * inv_direction;
+            let t1 = (axis.max() - origin) * inv_direction;
+
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some(Interval::new(t_min, t_max))
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_interval(ray, t_min, t_max).is_some()
+    }
+}
+
+fn pad(interval: Interval) -> Interval {
+    if interval.size() >= MIN_SIZE {
+        return interval;
+    }
+
+    let delta = MIN_SIZE / 2.0;
+    Interval::new(interval.min() - delta, interval.max() + delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_points() {
+        let aabb = Aabb::from_points(Point3::new(1.0, -2.0, 3.0), Point3::new(-1.0, 2.0, -3.0));
+        assert_eq!(aabb.min(), Point3::new(-1.0, -2.0, -3.0));
+        assert_eq!(aabb.max(), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(aabb.centroid(), Point3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pad() {
+        let aabb = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0));
+        assert!(aabb.axis(2).size() >= MIN_SIZE);
+        assert!(aabb.axis(2).contains(0.0));
+    }
+
+    #[test]
+    fn test_union() {
+        let a = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Aabb::from_points(Point3::new(-1.0, 2.0, 0.5), Point3::new(0.5, 3.0, 0.75));
+        let c = a.union(&b);
+        assert_eq!(c.min(), Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(c.max(), Point3::new(1.0, 3.0, 1.0));
+
+        assert!(Aabb::empty().is_empty());
+        assert_eq!(Aabb::empty().union(&a), a);
+    }
+
+    #[test]
+    fn test_hit() {
+        let aabb = Aabb::from_points(Point3::new(-1.0, -1.0, -3.0), Point3::new(1.0, 1.0, -1.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            aabb.hit_interval(&ray, 0.0, f64::INFINITY),
+            Some(Interval::new(1.0, 3.0))
+        );
+        assert!(!aabb.hit(&ray, 0.0, 0.5));
+
+        let ray = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!aabb.hit(&ray, 0.0, f64::INFINITY));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!aabb.hit(&ray, 0.0, f64::INFINITY));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::HittableList;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Bounding volume hierarchy, a binary tree of bounding boxes so rays only
+/// test objects whose boxes they pass through, roughly log(n) per ray
+///
+/// https://raytracing.github.io/books/RayTracingTheNextWeek.html#boundingvolumehierarchies
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    /// None for a leaf holding a single object
+    right: Option<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+pub struct BvhBuilder {
+    parallel_threshold: usize,
+}
+
+impl Default for BvhBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BvhBuilder {
+    pub fn new() -> BvhBuilder {
+        BvhBuilder {
+            parallel_threshold: 4096,
+        }
+    }
+
+    /// Subtrees with at least this many objects are built on separate rayon
+    /// tasks, smaller ones serially since splitting them costs more than it
+    /// saves. `usize::MAX` builds entirely on the calling thread.
+    pub fn parallel_threshold(mut self, parallel_threshold: usize) -> BvhBuilder {
+        self.parallel_threshold = parallel_threshold;
+        self
+    }
+
+    pub fn build(&self, objects: Vec<Box<dyn Hittable>>) -> BvhNode {
+        if objects.is_empty() {
+            return BvhNode {
+                left: Box::new(HittableList::new()),
+                right: None,
+                bbox: Aabb::empty(),
+            };
+        }
+
+        self.split(objects)
+    }
+
+    fn split(&self, mut objects: Vec<Box<dyn Hittable>>) -> BvhNode {
+        if objects.len() == 1 {
+            let left = objects.pop().unwrap();
+            let bbox = left.bounding_box();
+            return BvhNode {
+                left,
+                right: None,
+                bbox,
+            };
+        }
+
+        // split at the median along the axis the object centers spread out the most
+        let centroids = objects.iter().fold(Aabb::empty(), |bbox, object| {
+            let centroid = object.bounding_box().centroid();
+            bbox.union(&Aabb::from_points(centroid, centroid))
+        });
+        let size = centroids.size();
+        let axis = if size.x > size.y && size.x > size.z {
+            0
+        } else if size.y > size.z {
+            1
+        } else {
+            2
+        };
+
+        // stable sort so serial and parallel builds produce the same tree
+        objects.sort_by(|a, b| {
+            let a = Vec3::from(a.bounding_box().centroid())[axis];
+            let b = Vec3::from(b.bounding_box().centroid())[axis];
+            a.total_cmp(&b)
+        });
+
+        let right_objects = objects.split_off(objects.len() / 2);
+        let left_objects = objects;
+
+        let (left, right) = if left_objects.len() + right_objects.len() >= self.parallel_threshold {
+            rayon::join(|| self.split(left_objects), || self.split(right_objects))
+        } else {
+            (self.split(left_objects), self.split(right_objects))
+        };
+
+        let bbox = left.bbox.union(&right.bbox);
+
+        BvhNode {
+            left: Box::new(left),
+            right: Some(Box::new(right)),
+            bbox,
+        }
+    }
+}
+
+impl BvhNode {
+    pub fn builder() -> BvhBuilder {
+        BvhBuilder::new()
+    }
+
+    pub fn new(objects: Vec<Box<dyn Hittable>>) -> Self {
+        BvhBuilder::new().build(objects)
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let left = self.left.hit(ray, t_min, t_max);
+
+        // only accept right hits closer than the left hit
+        let t_max = left.map_or(t_max, |hit| hit.t);
+        let right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(ray, t_min, t_max));
+
+        right.or(left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.left.set_materials(material)
+            + self
+                .right
+                .as_mut()
+                .map_or(0, |right| right.set_materials(material))
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.left.memory_bytes()
+            + self.right.as_ref().map_or(0, |right| right.memory_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::random_f64_range;
+    use crate::geo::material;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+    use crate::geo::Triangle;
+
+    fn primitives() -> (Vec<Sphere>, Vec<Triangle>) {
+        let spheres = (0..500)
+            .map(|_| {
+                Sphere::builder()
+                    .center(
+                        random_f64_range(-10.0, 10.0),
+                        random_f64_range(-10.0, 10.0),
+                        random_f64_range(-10.0, 10.0),
+                    )
+                    .radius(random_f64_range(0.05, 0.5))
+                    .material(material::Type::debug())
+                    .build()
+            })
+            .collect();
+
+        let triangles = (0..500)
+            .map(|_| {
+                let a = Vec3::random_range(-10.0, 10.0);
+                let b = a + Vec3::random_range(-1.0, 1.0);
+                let c = a + Vec3::random_range(-1.0, 1.0);
+                Triangle::new(
+                    Point3::from(a),
+                    Point3::from(b),
+                    Point3::from(c),
+                    material::Type::debug(),
+                )
+            })
+            .collect();
+
+        (spheres, triangles)
+    }
+
+    fn objects(spheres: &[Sphere], triangles: &[Triangle]) -> Vec<Box<dyn Hittable>> {
+        let spheres = spheres.iter().map(|s| Box::new(*s) as Box<dyn Hittable>);
+        let triangles = triangles.iter().map(|t| Box::new(*t) as Box<dyn Hittable>);
+        spheres.chain(triangles).collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let (spheres, triangles) = primitives();
+
+        let serial = BvhNode::builder()
+            .parallel_threshold(usize::MAX)
+            .build(objects(&spheres, &triangles));
+        let parallel = BvhNode::builder()
+            .parallel_threshold(2)
+            .build(objects(&spheres, &triangles));
+
+        let mut list = HittableList::new();
+        for sphere in &spheres {
+            list.add(*sphere);
+        }
+        for triangle in &triangles {
+            list.add(*triangle);
+        }
+
+        assert_eq!(serial.bounding_box(), parallel.bounding_box());
+        assert_eq!(serial.bounding_box(), list.bounding_box());
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let origin = Point3::from(Vec3::random_range(-15.0, 15.0));
+            let ray = Ray::new(origin, Vec3::random_range(-1.0, 1.0));
+
+            let expected = serial.hit(&ray, 0.001, f64::INFINITY);
+            assert_eq!(
+                parallel.hit(&ray, 0.001, f64::INFINITY),
+                expected,
+                "{ray:?}"
+            );
+
+            let linear = list.hit(&ray, 0.001, f64::INFINITY);
+            assert_eq!(expected.map(|h| h.t), linear.map(|h| h.t), "{ray:?}");
+
+            hits += expected.is_some() as usize;
+        }
+
+        // rays should actually exercise the tree
+        assert!(hits > 100, "{hits}");
+    }
+
+    #[test]
+    fn test_memory_bytes() {
+        let (spheres, triangles) = primitives();
+        let bvh = BvhNode::new(objects(&spheres, &triangles));
+
+        let primitives = spheres.len() * std::mem::size_of::<Sphere>()
+            + triangles.len() * std::mem::size_of::<Triangle>();
+
+        // n leaves and n - 1 inner nodes on top of the primitives
+        let nodes = (2 * 1000 - 1) * std::mem::size_of::<BvhNode>();
+        assert_eq!(bvh.memory_bytes(), primitives + nodes);
+    }
+
+    #[test]
+    fn test_empty_and_single() {
+        let empty = BvhNode::new(vec![]);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(empty.hit(&ray, 0.001, f64::INFINITY).is_none());
+
+        let sphere = Sphere::builder().center(0.0, 0.0, -5.0).radius(1.0).build();
+        let single = BvhNode::new(vec![Box::new(sphere)]);
+        assert_eq!(single.bounding_box(), sphere.bounding_box());
+        assert_eq!(single.hit(&ray, 0.001, f64::INFINITY).unwrap().t, 4.0);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::geo::degrees_to_radians;
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Interval;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Finite cone opening from `apex` along `axis`, e.g. a party hat (axis
+/// pointing down) or the beam of a spotlight, optionally closed by a flat
+/// disk at the base
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cone {
+    apex: Point3,
+    /// Unit direction from the apex towards the base
+    axis: Vec3,
+    /// Half opening angle in degrees
+    half_angle: f64,
+    height: f64,
+    cap: bool,
+    material: material::Type,
+    // squared cosine of the half angle, the cone equation is written in it
+    cos2: f64,
+}
+
+pub struct ConeBuilder {
+    apex: Option<Point3>,
+    axis: Option<Vec3>,
+    half_angle: Option<f64>,
+    height: Option<f64>,
+    cap: Option<bool>,
+    material: Option<material::Type>,
+}
+
+impl ConeBuilder {
+    pub fn build(&self) -> Cone {
+        let axis = self.axis.unwrap_or(Vec3::new(0.0, -1.0, 0.0));
+        let axis = if axis.length_squared() > 0.0 {
+            axis.unit()
+        } else {
+            Vec3::new(0.0, -1.0, 0.0)
+        };
+
+        // open wider than a plane or not at all is not a cone
+        let half_angle = self.half_angle.unwrap_or(45.0).clamp(0.0, 89.9);
+
+        Cone {
+            apex: self.apex.unwrap_or(Point3::new(0.0, 0.0, 0.0)),
+            axis,
+            half_angle,
+            height: self.height.unwrap_or(1.0).max(0.0),
+            cap: self.cap.unwrap_or(false),
+            material: self.material.unwrap_or(material::Type::empty()),
+            cos2: degrees_to_radians(half_angle).cos().powi(2),
+        }
+    }
+
+    pub fn apex(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.apex = Some(Point3::new(x, y, z));
+        self
+    }
+
+    /// Direction the cone opens towards, normalized, defaults to -y
+    pub fn axis(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.axis = Some(Vec3::new(x, y, z));
+        self
+    }
+
+    /// Angle between the axis and the surface in degrees, defaults to 45
+    pub fn half_angle(mut self, half_angle: f64) -> Self {
+        self.half_angle = Some(half_angle);
+        self
+    }
+
+    /// Distance from the apex to the base along the axis, defaults to 1
+    pub fn height(mut self, height: f64) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    /// Close the base with a disk, open by default
+    pub fn cap(mut self, cap: bool) -> Self {
+        self.cap = Some(cap);
+        self
+    }
+
+    pub fn material(mut self, material: material::Type) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Cone {
+    pub fn builder() -> ConeBuilder {
+        ConeBuilder {
+            apex: None,
+            axis: None,
+            half_angle: None,
+            height: None,
+            cap: None,
+            material: None,
+        }
+    }
+
+    pub fn apex(&self) -> &Point3 {
+        &self.apex
+    }
+
+    pub fn axis(&self) -> Vec3 {
+        self.axis
+    }
+
+    pub fn half_angle(&self) -> f64 {
+        self.half_angle
+    }
+
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn cap(&self) -> bool {
+        self.cap
+    }
+
+    pub fn material(&self) -> material::Type {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: material::Type) {
+        self.material = material;
+    }
+
+    /// Radius of the base disk
+    pub fn base_radius(&self) -> f64 {
+        self.height * degrees_to_radians(self.half_angle).tan()
+    }
+
+    fn base_center(&self) -> Vec3 {
+        Vec3::from(self.apex) + self.height * self.axis
+    }
+
+    // closest t of the lateral surface within the height band, solves
+    // ((p - apex) . axis)^2 = cos^2 * |p - apex|^2 for p on the ray
+    fn hit_lateral(&self, ray: &Ray, t_interval: &Interval) -> Option<f64> {
+        let co = Vec3::from(ray.origin()) - Vec3::from(self.apex);
+        let direction = ray.direction();
+
+        let dv = direction.dot(&self.axis);
+        let cv = co.dot(&self.axis);
+
+        let a = dv * dv - self.cos2 * direction.length_squared();
+        let h = dv * cv - self.cos2 * direction.dot(&co);
+        let c = cv * cv - self.cos2 * co.length_squared();
+
+        let roots = if a.abs() < 1e-12 {
+            // ray parallel to the surface crosses it at most once
+            if h == 0.0 {
+                return None;
+            }
+            [-c / (2.0 * h), f64::NAN]
+        } else {
+            let discriminant = h * h - a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let sqrtd = discriminant.sqrt();
+            let (t0, t1) = ((-h - sqrtd) / a, (-h + sqrtd) / a);
+            [t0.min(t1), t0.max(t1)]
+        };
+
+        // the equation also describes the mirrored cone behind the apex
+        roots.into_iter().find(|&t| {
+            if !t_interval.surrounds(t) {
+                return false;
+            }
+            let m = (Vec3::from(ray.at(t)) - Vec3::from(self.apex)).dot(&self.axis);
+            (0.0..=self.height).contains(&m)
+        })
+    }
+
+    fn hit_cap(&self, ray: &Ray, t_interval: &Interval) -> Option<f64> {
+        let dv = ray.direction().dot(&self.axis);
+        if dv == 0.0 {
+            return None;
+        }
+
+        let t = (self.base_center() - Vec3::from(ray.origin())).dot(&self.axis) / dv;
+        if !t_interval.surrounds(t) {
+            return None;
+        }
+
+        let radius = self.base_radius();
+        let offset = Vec3::from(ray.at(t)) - self.base_center();
+        (offset.length_squared() <= radius * radius).then_some(t)
+    }
+
+    // outward lateral normal, the gradient of the cone equation
+    fn lateral_normal(&self, p: &Point3) -> Vec3 {
+        let cp = Vec3::from(p) - Vec3::from(self.apex);
+        let normal = self.cos2 * cp - cp.dot(&self.axis) * self.axis;
+
+        // the apex has no tangent plane, point away from the cone
+        if normal.length_squared() < 1e-24 {
+            -self.axis
+        } else {
+            normal.unit()
+        }
+    }
+}
+
+impl hittable::Hittable for Cone {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let mut t_interval = Interval::new(t_min, t_max);
+
+        let lateral = self.hit_lateral(ray, &t_interval);
+        if let Some(t) = lateral {
+            t_interval = Interval::new(t_min, t);
+        }
+
+        let cap = if self.cap {
+            self.hit_cap(ray, &t_interval)
+        } else {
+            None
+        };
+
+        let (t, normal) = match (cap, lateral) {
+            (Some(t), _) => (t, self.axis),
+            (None, Some(t)) => (t, self.lateral_normal(&ray.at(t))),
+            (None, None) => return None,
+        };
+
+        let mut hit_record = hittable::HitRecord {
+            t,
+            p: ray.at(t),
+            normal,
+            front_face: false,
+            material: self.material,
+        };
+
+        hit_record.set_face_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // base disk extends radius * sqrt(1 - axis_n^2) along each axis
+        let radius = self.base_radius();
+        let center = self.base_center();
+        let extent = Vec3::new(
+            radius * (1.0 - self.axis.x * self.axis.x).max(0.0).sqrt(),
+            radius * (1.0 - self.axis.y * self.axis.y).max(0.0).sqrt(),
+            radius * (1.0 - self.axis.z * self.axis.z).max(0.0).sqrt(),
+        );
+
+        let apex = Vec3::from(self.apex);
+        let (low, high) = (center - extent, center + extent);
+        let min = Vec3::new(apex.x.min(low.x), apex.y.min(low.y), apex.z.min(low.z));
+        let max = Vec3::new(apex.x.max(high.x), apex.y.max(high.y), apex.z.max(high.z));
+
+        Aabb::from_points(Point3::from(min), Point3::from(max))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material(material);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Hittable;
+    use crate::test::assert;
+
+    // party hat, apex at y = 1 opening down to a unit radius base at y = 0
+    fn hat(cap: bool) -> Cone {
+        Cone::builder()
+            .apex(0.0, 1.0, 0.0)
+            .axis(0.0, -1.0, 0.0)
+            .half_angle(45.0)
+            .height(1.0)
+            .cap(cap)
+            .build()
+    }
+
+    #[test]
+    fn test_cone_builder() {
+        let cone = Cone::builder()
+            .axis(0.0, 0.0, 2.0)
+            .half_angle(120.0)
+            .height(-1.0)
+            .build();
+        assert_eq!(cone.axis(), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(cone.half_angle(), 89.9);
+        assert_eq!(cone.height(), 0.0);
+        assert!(!cone.cap());
+        assert::float(hat(true).base_radius(), 1.0, 12);
+    }
+
+    #[test]
+    fn test_cone_hit_lateral() {
+        let cone = hat(true);
+
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = cone.hit(&ray, 0.001, 100.0).unwrap();
+        assert::float(record.t, 4.5, 12);
+        assert::float(record.p.x(), -0.5, 12);
+        assert!(record.front_face);
+
+        // perpendicular to the 45 degree slope, not radial
+        let expected = Vec3::new(-1.0, 1.0, 0.0).unit();
+        assert::float(record.normal.x, expected.x, 12);
+        assert::float(record.normal.y, expected.y, 12);
+        assert::float(record.normal.z, 0.0, 12);
+
+        // leaves through the far side
+        let record = cone.hit(&ray, 4.6, 100.0).unwrap();
+        assert::float(record.t, 5.5, 12);
+        assert!(!record.front_face);
+
+        // the mirrored cone above the apex and anything below the base is empty
+        let ray = Ray::new(Point3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cone.hit(&ray, 0.001, 100.0).is_none());
+        let ray = Ray::new(Point3::new(-5.0, -0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cone.hit(&ray, 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_cone_hit_cap() {
+        let ray = Ray::new(Point3::new(0.2, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let record = hat(true).hit(&ray, 0.001, 100.0).unwrap();
+        assert::float(record.t, 5.0, 12);
+        assert_eq!(record.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!(record.front_face);
+
+        // open cone is entered through the base and hit from inside
+        let record = hat(false).hit(&ray, 0.001, 100.0).unwrap();
+        assert::float(record.t, 5.8, 12);
+        assert!(!record.front_face);
+
+        // outside the base radius
+        let ray = Ray::new(Point3::new(1.2, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(hat(true).hit(&ray, 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_cone_hit_apex() {
+        let cone = hat(true);
+
+        // straight down the axis
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let record = cone.hit(&ray, 0.001, 100.0).unwrap();
+        assert::float(record.t, 4.0, 12);
+        assert_eq!(record.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(record.front_face);
+
+        // then out through the cap
+        let record = cone.hit(&ray, 4.001, 100.0).unwrap();
+        assert::float(record.t, 5.0, 12);
+        assert!(!record.front_face);
+
+        // at an angle steeper than the surface, through the apex into the cone
+        let ray = Ray::new(Point3::new(-0.5, 2.0, 0.0), Vec3::new(0.5, -1.0, 0.0));
+        let record = cone.hit(&ray, 0.001, 100.0).unwrap();
+        assert::float(record.t, 1.0, 6);
+        assert!(record.normal.x.is_finite());
+        assert::float(record.normal.length(), 1.0, 12);
+
+        let record = cone.hit(&ray, 1.001, 100.0).unwrap();
+        assert::float(record.t, 2.0, 12);
+        assert_eq!(record.normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_cone_bounding_box() {
+        let bbox = hat(true).bounding_box();
+        assert::float(bbox.min().x(), -1.0, 12);
+        assert::float(bbox.max().x(), 1.0, 12);
+        assert::float(bbox.min().y(), 0.0, 12);
+        assert::float(bbox.max().y(), 1.0, 12);
+        assert::float(bbox.min().z(), -1.0, 12);
+        assert::float(bbox.max().z(), 1.0, 12);
+    }
+}
@@ -1,14 +1,14 @@
 use crate::geo::Vec3;
 
 pub fn degrees_to_radians(degrees: f64) -> f64 {
-    return degrees * std::f64::consts::PI / 180.0;
+    degrees * std::f64::consts::PI / 180.0
 }
 
 pub fn random_unit_normal_direction(normal: &Vec3) -> Vec3 {
     let unit = random_unit();
 
     // in same general direction as normal (e.g. for a sphere, same hemisphere)
-    if unit.dot(&normal) > 0.0 {
+    if unit.dot(normal) > 0.0 {
         unit
     } else {
         // otherwise, flip it so it is
@@ -16,18 +16,136 @@ pub fn random_unit_normal_direction(normal: &Vec3) -> Vec3 {
     }
 }
 
+/// Uniformly distributed unit vector in the hemisphere around `normal`
+pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
+    random_unit_normal_direction(normal)
+}
+
+/// Uniformly distributed unit vector, a point on the unit sphere
 pub fn random_unit() -> Vec3 {
     random_unit_with_transform(|p| p)
 }
 
+/// Unit vector in the xy plane, a point on the unit circle
 pub fn random_unit_disk() -> Vec3 {
-    random_unit_with_transform(|mut p| {
-        p.z = 0.0;
-        p
-    })
+    random_unit_with_transform(flatten)
+}
+
+/// Uniformly distributed point inside the unit sphere
+pub fn random_in_unit_sphere() -> Vec3 {
+    random_in_unit_with_transform(|p| p)
+}
+
+/// Uniformly distributed point inside the unit disk in the xy plane, unlike
+/// `random_unit_disk` which only returns points on its edge
+pub fn random_in_unit_disk() -> Vec3 {
+    random_in_unit_with_transform(flatten)
+}
+
+/// Map a point in the unit square [0, 1)^2 onto the unit disk in the xy plane,
+/// keeping stratified square samples stratified on the disk
+///
+/// https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations#ConcentricSampleDisk
+pub fn square_to_disk(u: f64, v: f64) -> Vec3 {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+
+    if a == 0.0 && b == 0.0 {
+        return Vec3::ZERO;
+    }
+
+    let quarter_pi = std::f64::consts::FRAC_PI_4;
+    let (radius, theta) = if a.abs() > b.abs() {
+        (a, quarter_pi * (b / a))
+    } else {
+        (b, 2.0 * quarter_pi - quarter_pi * (a / b))
+    };
+
+    Vec3::new(radius * theta.cos(), radius * theta.sin(), 0.0)
+}
+
+/// Uniformly distributed point inside a regular polygon with `blades` sides
+/// inscribed in the unit circle in the xy plane, e.g. the opening of a camera
+/// aperture made of that many blades
+pub fn random_in_polygon(blades: u32) -> Vec3 {
+    square_to_polygon(crate::core::random_f64(), crate::core::random_f64(), blades)
+}
+
+/// Unit vector scattered from `incoming` by the Henyey-Greenstein phase
+/// function, the average cosine to `incoming` is the anisotropy `g` in
+/// (-1, 1): positive scatters forward, negative back and 0 uniformly
+///
+/// https://pbr-book.org/3ed-2018/Light_Transport_II_Volume_Rendering/Sampling_Volume_Scattering#SamplingPhaseFunctions
+pub fn random_hg_direction(g: f64, incoming: &Vec3) -> Vec3 {
+    let xi = crate::core::random_f64();
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * xi
+    } else {
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    };
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * crate::core::random_f64();
+
+    let w = incoming.unit();
+    let (u, v) = onb(&w);
+
+    sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w
+}
+
+/// Unit vectors `u` and `v` completing the unit vector `w` to a right handed
+/// orthonormal basis, e.g. to turn directions sampled around +z into
+/// directions around `w`
+pub fn onb(w: &Vec3) -> (Vec3, Vec3) {
+    // any axis not too close to w gives a well conditioned cross product
+    let helper = if w.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let u = w.cross(&helper).unit();
+    let v = w.cross(&u);
+
+    (u, v)
+}
+
+/// Map a point in the unit square [0, 1)^2 into a regular polygon with
+/// `blades` (at least 3) sides inscribed in the unit circle, one vertex at +y
+pub fn square_to_polygon(u: f64, v: f64, blades: u32) -> Vec3 {
+    let blades = blades.max(3);
+
+    // u picks one of the triangles fanning out from the center, what remains
+    // of it is reused within the triangle
+    let scaled = u * blades as f64;
+    let triangle = (scaled.floor() as u32).min(blades - 1);
+    let u = scaled - triangle as f64;
+
+    let angle = |n: u32| {
+        std::f64::consts::FRAC_PI_2 + 2.0 * std::f64::consts::PI * n as f64 / blades as f64
+    };
+    let (a, b) = (angle(triangle), angle(triangle + 1));
+    let a = Vec3::new(a.cos(), a.sin(), 0.0);
+    let b = Vec3::new(b.cos(), b.sin(), 0.0);
+
+    // uniform in the triangle (center, a, b), sqrt keeps area density even
+    let r = u.sqrt();
+    r * ((1.0 - v) * a + v * b)
+}
+
+fn flatten(mut p: Vec3) -> Vec3 {
+    p.z = 0.0;
+    p
 }
 
 fn random_unit_with_transform<T>(transform: T) -> Vec3
+where
+    T: Fn(Vec3) -> Vec3,
+{
+    // inside points are never zero length so normalizing is always safe
+    random_in_unit_with_transform(transform).unit()
+}
+
+fn random_in_unit_with_transform<T>(transform: T) -> Vec3
 where
     T: Fn(Vec3) -> Vec3,
 {
@@ -39,14 +157,144 @@ where
 
         let lensq = p.length_squared();
 
-        if lensq <= 1.0 {
-            let sqrtlensq = lensq.sqrt();
+        // avoid potential division by zero for small values when normalizing
+        // e.g. 1e-160
+        if lensq <= 1.0 && lensq.sqrt() > 0.0 {
+            return p;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    #[test]
+    fn test_onb() {
+        for w in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(1.0, 2.0, -3.0).unit(),
+        ] {
+            let (u, v) = onb(&w);
+            assert::float(u.length(), 1.0, 12);
+            assert::float(v.length(), 1.0, 12);
+            assert::float(u.dot(&v), 0.0, 12);
+            assert::float(u.dot(&w), 0.0, 12);
+            assert::float(v.dot(&w), 0.0, 12);
+
+            // right handed, u x v = w
+            assert::float(u.cross(&v).dot(&w), 1.0, 12);
+        }
+    }
+
+    #[test]
+    fn test_unit_length() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        for _ in 0..1000 {
+            assert::float(random_unit().length(), 1.0, 9);
+
+            let disk = random_unit_disk();
+            assert::float(disk.length(), 1.0, 9);
+            assert_eq!(disk.z, 0.0);
 
-            // avoid potential division by zero for small values
-            // e.g. 1e-160
-            if sqrtlensq > 0.0 {
-                return p / sqrtlensq;
+            let hemisphere = random_on_hemisphere(&normal);
+            assert::float(hemisphere.length(), 1.0, 9);
+            assert!(hemisphere.dot(&normal) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_random_hg_direction() {
+        let incoming = Vec3::new(0.0, 0.0, -2.0);
+        let n = 20_000;
+
+        for g in [0.0, 0.6, -0.4] {
+            let mut mean = 0.0;
+            for _ in 0..n {
+                let direction = random_hg_direction(g, &incoming);
+                assert::float(direction.length(), 1.0, 9);
+                mean += direction.dot(&incoming.unit()) / n as f64;
             }
+
+            // the mean cosine of Henyey-Greenstein is g, uniform at 0
+            assert!((mean - g).abs() < 0.02, "{g}: {mean}");
         }
     }
+
+    #[test]
+    fn test_square_to_disk() {
+        assert_eq!(square_to_disk(0.5, 0.5), Vec3::new(0.0, 0.0, 0.0));
+
+        let edge = square_to_disk(1.0, 0.5);
+        assert::float(edge.x, 1.0, 9);
+        assert::float(edge.y, 0.0, 9);
+
+        for _ in 0..1000 {
+            let p = square_to_disk(crate::core::random_f64(), crate::core::random_f64());
+            assert!(p.length() <= 1.0 + 1e-12);
+            assert_eq!(p.z, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_random_in_polygon() {
+        for blades in [3, 5, 6, 8] {
+            let step = 2.0 * std::f64::consts::PI / blades as f64;
+            // distance from center to each edge
+            let apothem = (step / 2.0).cos();
+
+            let mut outer = 0;
+            for _ in 0..2000 {
+                let p = random_in_polygon(blades);
+                assert_eq!(p.z, 0.0);
+
+                // inside every edge's half plane, edge normals point between vertices
+                for n in 0..blades {
+                    let angle = std::f64::consts::FRAC_PI_2 + step * (n as f64 + 0.5);
+                    let normal = Vec3::new(angle.cos(), angle.sin(), 0.0);
+                    assert!(p.dot(&normal) <= apothem + 1e-9, "{blades} {p:?}");
+                }
+
+                outer += (p.length() > apothem * 0.9) as usize;
+            }
+
+            // fills the whole polygon out to its edges
+            assert!(outer > 100, "{blades} {outer}");
+        }
+    }
+
+    #[test]
+    fn test_square_to_polygon_corners() {
+        let top = square_to_polygon(0.999_999_999, 1.0, 4);
+        assert::float(top.x, 0.0, 6);
+        assert::float(top.y, 1.0, 6);
+
+        assert_eq!(square_to_polygon(0.0, 0.5, 6), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_in_unit() {
+        let mut sphere_inside = 0;
+        let mut disk_inside = 0;
+
+        for _ in 0..1000 {
+            let p = random_in_unit_sphere();
+            assert!(p.length() <= 1.0);
+            sphere_inside += (p.length() < 0.9) as usize;
+
+            let p = random_in_unit_disk();
+            assert!(p.length() <= 1.0);
+            assert_eq!(p.z, 0.0);
+            disk_inside += (p.length() < 0.9) as usize;
+        }
+
+        // uniform over the volume / area, not just the surface / edge
+        // expect 0.9^3 = 73% and 0.9^2 = 81% of samples
+        assert!((600..850).contains(&sphere_inside), "{sphere_inside}");
+        assert!((700..900).contains(&disk_inside), "{disk_inside}");
+    }
 }
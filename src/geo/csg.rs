@@ -0,0 +1,246 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Ray;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOp {
+    /// Inside either operand
+    Union,
+    /// Inside both operands
+    Intersection,
+    /// Inside the left operand but not the right, the right is carved away
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, left: bool, right: bool) -> bool {
+        match self {
+            CsgOp::Union => left || right,
+            CsgOp::Intersection => left && right,
+            CsgOp::Difference => left && !right,
+        }
+    }
+}
+
+/// Constructive solid geometry, a boolean combination of two closed solids
+///
+/// Both operands report every span of the ray inside them (see
+/// `Hittable::spans`), the spans are merged according to the operation and
+/// the nearest resulting surface is the hit. Csg is itself a solid so
+/// combinations can be nested.
+///
+/// https://en.wikipedia.org/wiki/Constructive_solid_geometry
+pub struct Csg {
+    op: CsgOp,
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Csg { op, left, right }
+    }
+
+    pub fn union<L, R>(left: L, right: R) -> Self
+    where
+        L: Hittable + 'static,
+        R: Hittable + 'static,
+    {
+        Csg::new(CsgOp::Union, Box::new(left), Box::new(right))
+    }
+
+    pub fn intersection<L, R>(left: L, right: R) -> Self
+    where
+        L: Hittable + 'static,
+        R: Hittable + 'static,
+    {
+        Csg::new(CsgOp::Intersection, Box::new(left), Box::new(right))
+    }
+
+    pub fn difference<L, R>(left: L, right: R) -> Self
+    where
+        L: Hittable + 'static,
+        R: Hittable + 'static,
+    {
+        Csg::new(CsgOp::Difference, Box::new(left), Box::new(right))
+    }
+
+    pub fn op(&self) -> CsgOp {
+        self.op
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        if !self.bounding_box().hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        self.spans(ray)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|hit| t_min < hit.t && hit.t < t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match self.op {
+            CsgOp::Union => self.left.bounding_box().union(&self.right.bounding_box()),
+            // never larger than the left operand, conservative for intersection
+            CsgOp::Intersection | CsgOp::Difference => self.left.bounding_box(),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.left.set_materials(material) + self.right.set_materials(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.left.memory_bytes() + self.right.memory_bytes()
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<hittable::Span> {
+        // sweep every operand boundary in order, tracking which operands the
+        // ray is inside of, and emit a span wherever the combination is inside
+        let mut events = vec![];
+        for (operand, spans) in [(0, self.left.spans(ray)), (1, self.right.spans(ray))] {
+            for span in spans {
+                events.push((span.enter, operand, true));
+                events.push((span.exit, operand, false));
+            }
+        }
+        events.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+        let mut inside = [false, false];
+        let mut enter = None;
+        let mut spans = vec![];
+
+        for (mut hit, operand, entering) in events {
+            let was_inside = self.op.inside(inside[0], inside[1]);
+            inside[operand] = entering;
+            let is_inside = self.op.inside(inside[0], inside[1]);
+
+            // normals already face against the ray, only which side of the
+            // combined solid the ray is on changes, e.g. leaving a carved
+            // away operand is entering the result
+            if !was_inside && is_inside {
+                hit.front_face = true;
+                enter = Some(hit);
+            } else if was_inside && !is_inside {
+                hit.front_face = false;
+                if let Some(enter) = enter.take() {
+                    spans.push(hittable::Span { enter, exit: hit });
+                }
+            }
+        }
+
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::material;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+    use crate::geo::Vec3;
+    use crate::test::assert;
+
+    fn sphere(z: f64, radius: f64) -> Sphere {
+        Sphere::builder()
+            .center(0.0, 0.0, z)
+            .radius(radius)
+            .material(material::Type::debug())
+            .build()
+    }
+
+    fn ray_down_z(x: f64) -> Ray {
+        Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0))
+    }
+
+    #[test]
+    fn test_difference() {
+        // bite out of the front (+z side) of a unit sphere
+        let carved = Csg::difference(sphere(0.0, 1.0), sphere(1.0, 0.5));
+
+        // through the bite the ray passes the removed cap and hits the cavity
+        // wall, the back of the small sphere at z = 0.5
+        let hit = carved.hit(&ray_down_z(0.0), 0.001, f64::INFINITY).unwrap();
+        assert::float(hit.t, 4.5, 9);
+        assert!(hit.front_face);
+
+        // cavity normal points out of the carved solid, into the removed sphere
+        assert::float(hit.normal.z, 1.0, 9);
+
+        // away from the bite it is the plain unit sphere
+        let hit = carved.hit(&ray_down_z(0.8), 0.001, f64::INFINITY).unwrap();
+        let plain = sphere(0.0, 1.0)
+            .hit(&ray_down_z(0.8), 0.001, f64::INFINITY)
+            .unwrap();
+        assert_eq!(hit, plain);
+
+        // the far side is unchanged, leaving through the back of the unit sphere
+        let spans = carved.spans(&ray_down_z(0.0));
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].exit.t, 6.0, 9);
+        assert!(!spans[0].exit.front_face);
+    }
+
+    #[test]
+    fn test_difference_hollow() {
+        // carving a smaller concentric sphere leaves a shell with two spans
+        let shell = Csg::difference(sphere(0.0, 1.0), sphere(0.0, 0.5));
+        let spans = shell.spans(&ray_down_z(0.0));
+        let ts: Vec<_> = spans.iter().map(|s| (s.enter.t, s.exit.t)).collect();
+        assert_eq!(ts, [(4.0, 4.5), (5.5, 6.0)]);
+
+        // from inside the cavity the next surface is the inner wall
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = shell.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert::float(hit.t, 0.5, 9);
+        assert!(hit.front_face);
+        assert::float(hit.normal.z, 1.0, 9);
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let a = sphere(0.0, 1.0);
+        let b = sphere(1.0, 1.0);
+
+        let union = Csg::union(a, b);
+        let spans = union.spans(&ray_down_z(0.0));
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].enter.t, 3.0, 9);
+        assert::float(spans[0].exit.t, 6.0, 9);
+
+        let intersection = Csg::intersection(a, b);
+        let spans = intersection.spans(&ray_down_z(0.0));
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].enter.t, 4.0, 9);
+        assert::float(spans[0].exit.t, 5.0, 9);
+
+        // outside the overlap the intersection is empty
+        assert!(intersection
+            .hit(&ray_down_z(0.95), 0.001, f64::INFINITY)
+            .is_none());
+        assert!(union.hit(&ray_down_z(0.95), 0.001, f64::INFINITY).is_some());
+    }
+
+    #[test]
+    fn test_nested() {
+        // ((a - b) - c) carves two bites, nested csg uses the spans of the inner one
+        let inner = Csg::difference(sphere(0.0, 1.0), sphere(1.0, 0.5));
+        let outer = Csg::difference(inner, sphere(-1.0, 0.5));
+
+        let spans = outer.spans(&ray_down_z(0.0));
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].enter.t, 4.5, 9);
+        assert::float(spans[0].exit.t, 5.5, 9);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Interval;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Sphere stretched along the axes, semi-axis radii (a, b, c) along x, y, z
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Ellipsoid {
+    center: Point3,
+    radii: Vec3,
+    material: material::Type,
+}
+
+pub struct EllipsoidBuilder {
+    center: Option<Point3>,
+    radii: Option<Vec3>,
+    material: Option<material::Type>,
+}
+
+impl EllipsoidBuilder {
+    pub fn build(&self) -> Ellipsoid {
+        let radii = self.radii.unwrap_or_default();
+
+        Ellipsoid {
+            center: self.center.unwrap_or(Point3::new(0.0, 0.0, 0.0)),
+            radii: Vec3::new(radii.x.max(0.0), radii.y.max(0.0), radii.z.max(0.0)),
+            material: self.material.unwrap_or(material::Type::empty()),
+        }
+    }
+
+    pub fn center(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.center = Some(Point3::new(x, y, z));
+        self
+    }
+
+    pub fn radii(mut self, a: f64, b: f64, c: f64) -> Self {
+        self.radii = Some(Vec3::new(a, b, c));
+        self
+    }
+
+    pub fn material(mut self, material: material::Type) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Ellipsoid {
+    pub fn builder() -> EllipsoidBuilder {
+        EllipsoidBuilder {
+            center: None,
+            radii: None,
+            material: None,
+        }
+    }
+
+    pub fn center(&self) -> &Point3 {
+        &self.center
+    }
+
+    pub fn radii(&self) -> Vec3 {
+        self.radii
+    }
+
+    pub fn material(&self) -> material::Type {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: material::Type) {
+        self.material = material;
+    }
+
+    // divide each component by the matching radius, maps the ellipsoid onto
+    // the unit sphere
+    fn unit_space(&self, v: Vec3) -> Vec3 {
+        Vec3::new(v.x / self.radii.x, v.y / self.radii.y, v.z / self.radii.z)
+    }
+}
+
+impl hittable::Hittable for Ellipsoid {
+    // intersect the unit sphere in a space scaled by 1 / radii, the direction
+    // is scaled but not normalized so t is the same in both spaces
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        if self.radii.x == 0.0 || self.radii.y == 0.0 || self.radii.z == 0.0 {
+            return None;
+        }
+
+        let t_interval = Interval::new(t_min, t_max);
+
+        let oc = self.unit_space(Vec3::from(self.center) - Vec3::from(ray.origin()));
+        let direction = self.unit_space(*ray.direction());
+
+        let a = direction.length_squared();
+        let h = direction.dot(&oc);
+        let c = oc.length_squared() - 1.0;
+        let discriminant = h * h - a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+
+        let mut root = (h - sqrtd) / a;
+
+        if !t_interval.surrounds(root) {
+            root = (h + sqrtd) / a;
+            if !t_interval.surrounds(root) {
+                return None;
+            }
+        }
+
+        // gradient of the implicit surface, (p - center) / r^2 per axis
+        let p = ray.at(root);
+        let normal = self
+            .unit_space(self.unit_space(Vec3::from(p) - Vec3::from(self.center)))
+            .unit();
+
+        let mut hit_record = hittable::HitRecord {
+            t: root,
+            p,
+            normal,
+            front_face: false,
+            material: self.material,
+        };
+
+        hit_record.set_face_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let center = Vec3::from(self.center);
+        Aabb::from_points(
+            Point3::from(center - self.radii),
+            Point3::from(center + self.radii),
+        )
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material(material);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Hittable;
+    use crate::geo::Sphere;
+    use crate::test::assert;
+
+    fn stretched() -> Ellipsoid {
+        Ellipsoid::builder().radii(2.0, 1.0, 1.0).build()
+    }
+
+    #[test]
+    fn test_ellipsoid_default() {
+        let ellipsoid = Ellipsoid::builder().radii(-1.0, 2.0, 3.0).build();
+        assert_eq!(ellipsoid.radii(), Vec3::new(0.0, 2.0, 3.0));
+        assert_eq!(ellipsoid.material(), material::Type::empty());
+
+        // degenerate ellipsoid is never hit
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(ellipsoid.hit(&ray, 0.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_ellipsoid_hit_axes() {
+        let ellipsoid = stretched();
+
+        // long axis
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = ellipsoid.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(record.t, 3.0);
+        assert_eq!(record.p, Point3::new(-2.0, 0.0, 0.0));
+        assert_eq!(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!(record.front_face);
+
+        // short axis, unnormalized direction
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        let record = ellipsoid.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.p, Point3::new(0.0, 0.0, 1.0));
+        assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        // misses where a unit sphere would not
+        let ray = Ray::new(Point3::new(0.0, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(ellipsoid.hit(&ray, 0.0, 100.0).is_none());
+
+        // hits where a unit sphere would not
+        let ray = Ray::new(Point3::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(ellipsoid.hit(&ray, 0.0, 100.0).is_some());
+    }
+
+    #[test]
+    fn test_ellipsoid_normal() {
+        let ellipsoid = Ellipsoid::builder()
+            .center(1.0, 2.0, 3.0)
+            .radii(2.0, 1.0, 1.0)
+            .build();
+
+        // straight down onto the top at x = 1 from the center, y = sqrt(3) / 2
+        let ray = Ray::new(Point3::new(2.0, 10.0, 3.0), Vec3::new(0.0, -1.0, 0.0));
+        let record = ellipsoid.hit(&ray, 0.0, 100.0).unwrap();
+        let y = 3.0_f64.sqrt() / 2.0;
+        assert::float(record.p.y(), 2.0 + y, 12);
+
+        // (p - center) / r^2 = (1/4, sqrt(3)/2, 0), not the radial direction
+        let expected = Vec3::new(0.25, y, 0.0).unit();
+        assert::float(record.normal.x, expected.x, 12);
+        assert::float(record.normal.y, expected.y, 12);
+        assert_eq!(record.normal.z, 0.0);
+        assert::float(record.normal.length(), 1.0, 12);
+
+        let radial = (Vec3::from(record.p) - Vec3::from(*ellipsoid.center())).unit();
+        assert!(record.normal.dot(&radial) < 0.99);
+
+        // from inside the normal faces the ray
+        let ray = Ray::new(Point3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 0.0, 0.0));
+        let record = ellipsoid.hit(&ray, 0.001, 100.0).unwrap();
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_ellipsoid_matches_sphere() {
+        let ellipsoid = Ellipsoid::builder()
+            .center(0.0, 0.0, -1.0)
+            .radii(0.5, 0.5, 0.5)
+            .build();
+        let sphere = Sphere::builder().center(0.0, 0.0, -1.0).radius(0.5).build();
+
+        for direction in [
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.2, -0.1, -1.0),
+            Vec3::new(0.3, 0.3, -1.0),
+        ] {
+            let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), direction);
+            let a = ellipsoid.hit(&ray, 0.0, 100.0);
+            let b = sphere.hit(&ray, 0.0, 100.0);
+            assert_eq!(a.is_some(), b.is_some());
+            if let (Some(a), Some(b)) = (a, b) {
+                assert::float(a.t, b.t, 12);
+                assert::float(a.normal.dot(&b.normal), 1.0, 12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ellipsoid_bounding_box() {
+        let ellipsoid = Ellipsoid::builder()
+            .center(1.0, 2.0, 3.0)
+            .radii(2.0, 1.0, 0.5)
+            .build();
+        assert_eq!(
+            ellipsoid.bounding_box(),
+            Aabb::from_points(Point3::new(-1.0, 1.0, 2.5), Point3::new(3.0, 3.0, 3.5))
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Uniform grid accelerator, an alternative to a BVH for scenes made of
+/// roughly uniformly sized and distributed objects (e.g. a field of spheres)
+///
+/// Object bounding boxes are bucketed into equally sized cells, rays then
+/// march through the cells they cross in order (3D DDA) and stop at the
+/// first cell that contains a hit
+///
+/// https://www.cse.yorku.ca/~amana/research/grid.pdf
+pub struct UniformGrid {
+    objects: Vec<Box<dyn Hittable>>,
+    bbox: Aabb,
+    resolution: [usize; 3],
+    cell_size: Vec3,
+    /// Object indices overlapping each cell, x varies fastest
+    cells: Vec<Vec<usize>>,
+}
+
+// target average number of objects per cell
+const DENSITY: f64 = 2.0;
+
+// upper bound on cells per axis to keep memory in check for huge scenes
+const MAX_RESOLUTION: usize = 128;
+
+impl UniformGrid {
+    pub fn new(objects: Vec<Box<dyn Hittable>>) -> Self {
+        let bbox = objects.iter().fold(Aabb::empty(), |bbox, object| {
+            bbox.union(&object.bounding_box())
+        });
+
+        let size = bbox.size();
+
+        // pick cells per axis proportional to the extent of each axis such that
+        // the total cell count is roughly DENSITY cells per object
+        let volume = size.x * size.y * size.z;
+        let cells_per_unit = (DENSITY * objects.len() as f64 / volume).cbrt();
+        let resolution = [0, 1, 2].map(|n| {
+            let cells = (size[n] * cells_per_unit).round();
+            if cells.is_finite() {
+                (cells as usize).clamp(1, MAX_RESOLUTION)
+            } else {
+                1
+            }
+        });
+
+        let cell_size = Vec3::new(
+            size.x / resolution[0] as f64,
+            size.y / resolution[1] as f64,
+            size.z / resolution[2] as f64,
+        );
+
+        let mut grid = UniformGrid {
+            objects: vec![],
+            bbox,
+            resolution,
+            cell_size,
+            cells: vec![vec![]; resolution[0] * resolution[1] * resolution[2]],
+        };
+
+        for (index, object) in objects.iter().enumerate() {
+            let object_bbox = object.bounding_box();
+            let min = grid.cell_coords(&Vec3::from(object_bbox.min()));
+            let max = grid.cell_coords(&Vec3::from(object_bbox.max()));
+
+            for z in min[2]..=max[2] {
+                for y in min[1]..=max[1] {
+                    for x in min[0]..=max[0] {
+                        let cell = grid.cell_index([x, y, z]);
+                        grid.cells[cell].push(index);
+                    }
+                }
+            }
+        }
+
+        grid.objects = objects;
+        grid
+    }
+
+    pub fn objects(&self) -> &Vec<Box<dyn Hittable>> {
+        &self.objects
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    // cell containing point, clamped to the grid
+    fn cell_coords(&self, p: &Vec3) -> [usize; 3] {
+        let min = Vec3::from(self.bbox.min());
+        [0, 1, 2].map(|n| {
+            let cell = ((p[n] - min[n]) / self.cell_size[n]).floor();
+            if cell.is_finite() && cell > 0.0 {
+                (cell as usize).min(self.resolution[n as usize] - 1)
+            } else {
+                0
+            }
+        })
+    }
+
+    fn cell_index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.resolution[1] + y) * self.resolution[0] + x
+    }
+}
+
+impl Hittable for UniformGrid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let t_range = self.bbox.hit_interval(ray, t_min, t_max)?;
+
+        let min = Vec3::from(self.bbox.min());
+        let direction = ray.direction();
+        let entry = Vec3::from(ray.at(t_range.min()));
+        let mut cell = self.cell_coords(&entry).map(|c| c as isize);
+
+        // per axis: direction to step, t between cell boundaries, t of next boundary
+        let mut step = [0_isize; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+        let mut t_next = [f64::INFINITY; 3];
+
+        for n in 0..3_u8 {
+            let axis = n as usize;
+            if direction[n] > 0.0 {
+                step[axis] = 1;
+                t_delta[axis] = self.cell_size[n] / direction[n];
+                let boundary = min[n] + (cell[axis] + 1) as f64 * self.cell_size[n];
+                t_next[axis] = (boundary - ray.origin()[n]) / direction[n];
+            } else if direction[n] < 0.0 {
+                step[axis] = -1;
+                t_delta[axis] = -self.cell_size[n] / direction[n];
+                let boundary = min[n] + cell[axis] as f64 * self.cell_size[n];
+                t_next[axis] = (boundary - ray.origin()[n]) / direction[n];
+            }
+        }
+
+        let mut closest_so_far = t_range.max();
+        let mut hit_record = None;
+
+        loop {
+            let index = self.cell_index(cell.map(|c| c as usize));
+
+            for &object in &self.cells[index] {
+                if let Some(hit) = self.objects[object].hit(ray, t_min, closest_so_far) {
+                    closest_so_far = hit.t;
+                    hit_record = Some(hit);
+                }
+            }
+
+            // step along the axis whose cell boundary is crossed first
+            let axis = if t_next[0] < t_next[1] && t_next[0] < t_next[2] {
+                0
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+
+            // any hit closer than the exit of this cell cannot be beaten by later cells
+            let cell_exit = t_next[axis];
+            if closest_so_far <= cell_exit || cell_exit > t_range.max() {
+                break;
+            }
+
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.resolution[axis] as isize {
+                break;
+            }
+
+            t_next[axis] += t_delta[axis];
+        }
+
+        hit_record
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.objects
+            .iter_mut()
+            .map(|object| object.set_materials(material))
+            .sum()
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.objects.capacity() * std::mem::size_of::<Box<dyn Hittable>>()
+            + self.objects.iter().map(|o| o.memory_bytes()).sum::<usize>()
+            + self.cells.capacity() * std::mem::size_of::<Vec<usize>>()
+            + self
+                .cells
+                .iter()
+                .map(|c| c.capacity() * std::mem::size_of::<usize>())
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::random_f64;
+    use crate::core::random_f64_range;
+    use crate::geo::material;
+    use crate::geo::HittableList;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+
+    fn sphere_field(ground: bool) -> Vec<Sphere> {
+        let mut spheres = vec![];
+
+        if ground {
+            spheres.push(
+                Sphere::builder()
+                    .center(0.0, -1000.0, 0.0)
+                    .radius(1000.0)
+                    .material(material::Type::debug())
+                    .build(),
+            );
+        }
+
+        for x in -11..11 {
+            for z in -11..11 {
+                let radius = random_f64_range(0.1, 0.3);
+                spheres.push(
+                    Sphere::builder()
+                        .center(
+                            x as f64 + 0.9 * random_f64(),
+                            radius,
+                            z as f64 + 0.9 * random_f64(),
+                        )
+                        .radius(radius)
+                        .material(material::Type::debug())
+                        .build(),
+                );
+            }
+        }
+
+        spheres
+    }
+
+    #[test]
+    fn test_resolution() {
+        let grid = UniformGrid::new(vec![]);
+        assert_eq!(grid.resolution(), [1, 1, 1]);
+
+        let spheres = sphere_field(true);
+        let objects = spheres
+            .iter()
+            .map(|s| Box::new(*s) as Box<dyn Hittable>)
+            .collect();
+        let grid = UniformGrid::new(objects);
+        assert!(grid
+            .resolution()
+            .iter()
+            .all(|r| (1..=MAX_RESOLUTION).contains(r)));
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        assert_matches_linear_scan(sphere_field(true));
+    }
+
+    #[test]
+    fn test_matches_linear_scan_without_ground() {
+        // without the huge ground sphere the cells are small and rays cross many of them
+        let spheres = sphere_field(false);
+        let objects = spheres
+            .iter()
+            .map(|s| Box::new(*s) as Box<dyn Hittable>)
+            .collect();
+        assert!(UniformGrid::new(objects).resolution()[0] > 4);
+
+        assert_matches_linear_scan(spheres);
+    }
+
+    fn assert_matches_linear_scan(spheres: Vec<Sphere>) {
+        let mut list = HittableList::new();
+        for sphere in &spheres {
+            list.add(*sphere);
+        }
+
+        let objects = spheres
+            .iter()
+            .map(|s| Box::new(*s) as Box<dyn Hittable>)
+            .collect();
+        let grid = UniformGrid::new(objects);
+
+        assert_eq!(grid.bounding_box(), list.bounding_box());
+
+        for _ in 0..2000 {
+            let origin = Point3::new(
+                random_f64_range(-15.0, 15.0),
+                random_f64_range(0.1, 4.0),
+                random_f64_range(-15.0, 15.0),
+            );
+            let direction = Vec3::random_range(-1.0, 1.0);
+            let ray = Ray::new(origin, direction);
+
+            let expected = list.hit(&ray, 0.001, f64::INFINITY);
+            let actual = grid.hit(&ray, 0.001, f64::INFINITY);
+
+            assert_eq!(actual.map(|h| h.t), expected.map(|h| h.t), "{ray:?}");
+            assert_eq!(actual.map(|h| h.p), expected.map(|h| h.p), "{ray:?}");
+        }
+    }
+
+    #[test]
+    fn test_axis_aligned_rays() {
+        let sphere = Sphere::builder().center(0.0, 0.0, -5.0).radius(1.0).build();
+        let grid = UniformGrid::new(vec![Box::new(sphere)]);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = grid.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 4.0);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(grid.hit(&ray, 0.001, f64::INFINITY).is_none());
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(grid.hit(&ray, 0.001, 3.0).is_none());
+    }
+}
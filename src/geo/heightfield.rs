@@ -0,0 +1,544 @@
+use std::io::BufRead;
+use std::io::Read;
+
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::BvhNode;
+use crate::geo::Hittable;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Triangle;
+use crate::geo::Vec3;
+
+/// Terrain from a grid of elevations over the XZ plane, e.g. a grayscale
+/// height map
+///
+/// Every cell between four neighboring samples is split into two triangles,
+/// shaded with normals interpolated from per vertex normals so the surface
+/// looks smooth rather than faceted. The triangles are kept in a BVH.
+pub struct HeightField {
+    bvh: BvhNode,
+    width: usize,
+    depth: usize,
+}
+
+/// Builder settings `HeightFieldBuilder::try_build` rejects
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeightFieldError {
+    /// (width, depth, heights) where the number of heights is not width * depth
+    HeightCount(usize, usize, usize),
+}
+
+impl std::fmt::Display for HeightFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HeightFieldError::HeightCount(width, depth, count) => {
+                write!(f, "expected {width} x {depth} heights, got {count}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeightFieldError {}
+
+pub struct HeightFieldBuilder {
+    /// (width, depth, heights) with heights row-major, x varies fastest
+    heights: Option<(usize, usize, Vec<f64>)>,
+    origin: Option<Point3>,
+    spacing: Option<f64>,
+    vertical_scale: Option<f64>,
+    material: Option<material::Type>,
+}
+
+impl HeightFieldBuilder {
+    /// Build the field, or an empty one with a warning when the heights do
+    /// not fill the grid, see `try_build`
+    pub fn build(&self) -> HeightField {
+        self.try_build().unwrap_or_else(|error| {
+            eprintln!("warning: {error}, building an empty height field");
+            HeightField {
+                bvh: BvhNode::new(vec![]),
+                width: 0,
+                depth: 0,
+            }
+        })
+    }
+
+    pub fn try_build(&self) -> Result<HeightField, HeightFieldError> {
+        let (width, depth, heights) = self.heights.clone().unwrap_or((0, 0, vec![]));
+        if width.checked_mul(depth) != Some(heights.len()) {
+            return Err(HeightFieldError::HeightCount(width, depth, heights.len()));
+        }
+
+        let origin = Vec3::from(self.origin.unwrap_or(Point3::new(0.0, 0.0, 0.0)));
+        let spacing = self.spacing.unwrap_or(1.0);
+        let scale = self.vertical_scale.unwrap_or(1.0);
+        let material = self.material.unwrap_or(material::Type::empty());
+
+        let height = |x: usize, z: usize| heights[z * width + x] * scale;
+
+        let vertex = |x: usize, z: usize| {
+            Point3::from(origin + Vec3::new(x as f64 * spacing, height(x, z), z as f64 * spacing))
+        };
+
+        // central differences of the neighboring heights, one sided at the edges
+        let normal = |x: usize, z: usize| {
+            let (x0, x1) = (x.saturating_sub(1), (x + 1).min(width - 1));
+            let (z0, z1) = (z.saturating_sub(1), (z + 1).min(depth - 1));
+            let dx = (height(x1, z) - height(x0, z)) / ((x1 - x0) as f64 * spacing);
+            let dz = (height(x, z1) - height(x, z0)) / ((z1 - z0) as f64 * spacing);
+            Vec3::new(-dx, 1.0, -dz).unit()
+        };
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        // wound so the geometric normals face +y
+        for z in 0..depth.saturating_sub(1) {
+            for x in 0..width.saturating_sub(1) {
+                for [a, b, c] in [
+                    [(x, z), (x, z + 1), (x + 1, z)],
+                    [(x + 1, z), (x, z + 1), (x + 1, z + 1)],
+                ] {
+                    objects.push(Box::new(SmoothTriangle {
+                        triangle: Triangle::new(
+                            vertex(a.0, a.1),
+                            vertex(b.0, b.1),
+                            vertex(c.0, c.1),
+                            material,
+                        ),
+                        normals: [normal(a.0, a.1), normal(b.0, b.1), normal(c.0, c.1)],
+                    }));
+                }
+            }
+        }
+
+        Ok(HeightField {
+            bvh: BvhNode::new(objects),
+            width,
+            depth,
+        })
+    }
+
+    /// Elevations of a `width` x `depth` grid, row-major with x varying
+    /// fastest, e.g. from `read_pgm`
+    pub fn heights(mut self, width: usize, depth: usize, heights: Vec<f64>) -> Self {
+        self.heights = Some((width, depth, heights));
+        self
+    }
+
+    /// Elevations from a grayscale PGM image, black 0 and white 1
+    pub fn pgm(self, filepath: &str) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::open(filepath)?;
+        let (width, depth, heights) = read_pgm(&mut std::io::BufReader::new(file))?;
+        Ok(self.heights(width, depth, heights))
+    }
+
+    /// Position of the first sample, the grid extends along +x and +z
+    pub fn origin(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.origin = Some(Point3::new(x, y, z));
+        self
+    }
+
+    /// Distance between neighboring samples along x and z
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.spacing = Some(spacing);
+        self
+    }
+
+    /// Multiplier turning heights into y offsets from the origin
+    pub fn vertical_scale(mut self, vertical_scale: f64) -> Self {
+        self.vertical_scale = Some(vertical_scale);
+        self
+    }
+
+    pub fn material(mut self, material: material::Type) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl HeightField {
+    pub fn builder() -> HeightFieldBuilder {
+        HeightFieldBuilder {
+            heights: None,
+            origin: None,
+            spacing: None,
+            vertical_scale: None,
+            material: None,
+        }
+    }
+
+    /// Samples along x
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Samples along z
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl Hittable for HeightField {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.bvh.set_materials(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<BvhNode>() + self.bvh.memory_bytes()
+    }
+}
+
+// triangle shaded with the normal interpolated from its vertex normals
+struct SmoothTriangle {
+    triangle: Triangle,
+    normals: [Vec3; 3],
+}
+
+impl Hittable for SmoothTriangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let (t, u, v) = self.triangle.intersect(ray, t_min, t_max)?;
+
+        let [a, b, c] = self.normals;
+        let normal = ((1.0 - u - v) * a + u * b + v * c).unit();
+
+        // the side is decided by the actual surface, not the shading normal
+        let front_face = ray.direction().dot(&self.triangle.normal()) < 0.0;
+
+        Some(hittable::HitRecord {
+            t,
+            p: ray.at(t),
+            normal: if front_face { normal } else { -normal },
+            front_face,
+            material: self.triangle.material(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangle.bounding_box()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.triangle.set_materials(material)
+    }
+}
+
+/// Read a binary (P5) or plain (P2) grayscale PGM image as (width, height,
+/// values) with values row-major and scaled to [0, 1] by the image's maxval
+///
+/// https://netpbm.sourceforge.net/doc/pgm.html
+pub fn read_pgm<R: BufRead>(reader: &mut R) -> Result<(usize, usize, Vec<f64>), std::io::Error> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    let magic = header_token(reader)?;
+    if magic != "P2" && magic != "P5" {
+        return Err(invalid(format!("unsupported pgm format: {magic:?}")));
+    }
+
+    let mut number = || -> Result<usize, std::io::Error> {
+        let token = header_token(reader)?;
+        token
+            .parse()
+            .map_err(|_| invalid(format!("bad pgm header value: {token:?}")))
+    };
+
+    let width = number()?;
+    let height = number()?;
+    let maxval = number()?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(invalid(format!("bad pgm maxval: {maxval}")));
+    }
+
+    // sizes come from the file, sized only as far as data is actually read
+    let too_large = || invalid(format!("pgm size {width}x{height} is too large"));
+    let samples = width.checked_mul(height).ok_or_else(too_large)?;
+    let mut values = vec![];
+
+    if magic == "P2" {
+        for _ in 0..samples {
+            values.push(number()?.min(maxval));
+        }
+    } else {
+        // a single whitespace byte after maxval was consumed with it,
+        // samples are one byte or two big endian bytes depending on maxval
+        let bytes = if maxval < 256 { 1 } else { 2 };
+        let length = samples.checked_mul(bytes).ok_or_else(too_large)?;
+        let mut data = vec![];
+        reader.take(length as u64).read_to_end(&mut data)?;
+        if data.len() < length {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("pgm ends after {} of {length} bytes", data.len()),
+            ));
+        }
+
+        values.extend(data.chunks_exact(bytes).map(|sample| {
+            let value = sample
+                .iter()
+                .fold(0, |value, byte| value << 8 | *byte as usize);
+            value.min(maxval)
+        }));
+    }
+
+    let heights = values
+        .into_iter()
+        .map(|value| value as f64 / maxval as f64)
+        .collect();
+
+    Ok((width, height, heights))
+}
+
+// next whitespace separated header token, skipping `#` comments to the end
+// of their line, consumes the single whitespace byte following the token
+fn header_token<R: BufRead>(reader: &mut R) -> Result<String, std::io::Error> {
+    let mut token = String::new();
+    let mut byte = [0_u8; 1];
+
+    loop {
+        reader.read_exact(&mut byte)?;
+        let c = byte[0] as char;
+
+        if c == '#' && token.is_empty() {
+            let mut comment = vec![];
+            reader.read_until(b'\n', &mut comment)?;
+        } else if c.is_ascii_whitespace() {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+        } else {
+            token.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::random_f64_range;
+    use crate::geo::HittableList;
+    use crate::test::assert;
+
+    fn down(x: f64, z: f64) -> Ray {
+        Ray::new(Point3::new(x, 10.0, z), Vec3::new(0.0, -1.0, 0.0))
+    }
+
+    // heights from a function of the sample coordinates
+    fn field(width: usize, depth: usize, f: impl Fn(f64, f64) -> f64) -> HeightFieldBuilder {
+        let heights = (0..width * depth)
+            .map(|n| f((n % width) as f64, (n / width) as f64))
+            .collect();
+        HeightField::builder().heights(width, depth, heights)
+    }
+
+    #[test]
+    fn test_read_pgm() {
+        let plain = b"P2\n# made by hand\n3 2\n4\n0 1 2\n3 4 9\n";
+        let (width, height, heights) = read_pgm(&mut &plain[..]).unwrap();
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(heights, [0.0, 0.25, 0.5, 0.75, 1.0, 1.0]);
+
+        let mut binary = b"P5 2 2 255\n".to_vec();
+        binary.extend([0, 51, 255, 10]);
+        let (_, _, heights) = read_pgm(&mut &binary[..]).unwrap();
+        assert_eq!(heights, [0.0, 0.2, 1.0, 10.0 / 255.0]);
+
+        // 16-bit samples are big endian
+        let mut wide = b"P5\n2 1\n65535\n".to_vec();
+        wide.extend([0xff, 0xff, 0x80, 0x00]);
+        let (_, _, heights) = read_pgm(&mut &wide[..]).unwrap();
+        assert_eq!(heights, [1.0, 32768.0 / 65535.0]);
+
+        assert!(read_pgm(&mut &b"P6 1 1 255\n\0\0\0"[..]).is_err());
+        assert!(read_pgm(&mut &b"P5 2 2 255\n\0"[..]).is_err());
+        assert!(read_pgm(&mut &b"P2 1 1 0\n0"[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_pgm_hostile_size() {
+        // sizes that overflow are rejected, huge ones fail on the missing data
+        // instead of allocating for them up front
+        let overflow = format!("P5 {} 3 255\n\0", usize::MAX / 2);
+        let error = read_pgm(&mut overflow.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let wide = format!("P5 {} 2 65535\n\0", usize::MAX / 3);
+        let error = read_pgm(&mut wide.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let huge = b"P5 1000000 1000000 255\n\0\0";
+        let error = read_pgm(&mut &huge[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let huge = b"P2 1000000 1000000 255\n0 0";
+        assert!(read_pgm(&mut &huge[..]).is_err());
+    }
+
+    #[test]
+    fn test_height_count_mismatch() {
+        let builder = HeightField::builder().heights(3, 2, vec![0.0; 5]);
+        assert_eq!(
+            builder.try_build().map(|_| ()),
+            Err(HeightFieldError::HeightCount(3, 2, 5))
+        );
+
+        let terrain = builder.build();
+        assert_eq!((terrain.width(), terrain.depth()), (0, 0));
+        assert!(terrain.bounding_box().is_empty());
+
+        let overflow = HeightField::builder().heights(usize::MAX, 2, vec![]);
+        assert!(overflow.try_build().is_err());
+    }
+
+    #[test]
+    fn test_flat() {
+        let terrain = field(4, 3, |_, _| 0.5)
+            .origin(1.0, 2.0, 3.0)
+            .spacing(0.5)
+            .vertical_scale(2.0)
+            .build();
+
+        assert_eq!((terrain.width(), terrain.depth()), (4, 3));
+
+        let hit = terrain.hit(&down(2.1, 3.6), 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hit.p.y(), 3.0);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        // grid covers x in [1, 2.5] and z in [3, 4]
+        assert!(terrain.hit(&down(2.6, 3.5), 0.001, f64::INFINITY).is_none());
+        assert!(terrain.hit(&down(2.0, 4.1), 0.001, f64::INFINITY).is_none());
+
+        // from below the surface is a back face
+        let up = Ray::new(Point3::new(2.1, 0.0, 3.6), Vec3::new(0.0, 1.0, 0.0));
+        let hit = terrain.hit(&up, 0.001, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_empty() {
+        let terrain = HeightField::builder().build();
+        assert!(terrain.bounding_box().is_empty());
+        assert!(terrain.hit(&down(0.0, 0.0), 0.001, f64::INFINITY).is_none());
+
+        let line = field(5, 1, |_, _| 0.0).build();
+        assert!(line.hit(&down(1.1, 0.0), 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_slope_normals() {
+        // a plane rising along x, smooth normals equal the plane's normal
+        let terrain = field(5, 5, |x, _| 0.5 * x).build();
+        let expected = Vec3::new(-0.5, 1.0, 0.0).unit();
+
+        for (x, z) in [(0.2, 0.3), (1.5, 2.5), (3.9, 3.1)] {
+            let hit = terrain.hit(&down(x, z), 0.001, f64::INFINITY).unwrap();
+            assert::float(hit.p.y(), 0.5 * x, 9);
+            assert::float(hit.normal.x, expected.x, 9);
+            assert::float(hit.normal.y, expected.y, 9);
+            assert::float(hit.normal.z, expected.z, 9);
+        }
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        let bump = |x: f64, z: f64| (-((x - 4.0).powi(2) + (z - 4.0).powi(2)) / 8.0).exp();
+        let terrain = field(9, 9, bump).vertical_scale(2.0).build();
+
+        // at (just beside) a sample the normal comes from its neighbors' heights
+        let hit = terrain
+            .hit(&down(3.0 + 1e-9, 4.0 + 1e-9), 0.001, f64::INFINITY)
+            .unwrap();
+        let dx = 2.0 * (bump(4.0, 4.0) - bump(2.0, 4.0)) / 2.0;
+        let expected = Vec3::new(-dx, 1.0, 0.0).unit();
+        assert::float(hit.normal.x, expected.x, 6);
+        assert::float(hit.normal.y, expected.y, 6);
+        assert::float(hit.normal.z, expected.z, 6);
+
+        // continuous across the diagonal shared by a cell's two triangles
+        // although the flat triangles on either side face different ways
+        let a = terrain
+            .hit(&down(2.5 - 1e-6, 5.5), 0.001, f64::INFINITY)
+            .unwrap();
+        let b = terrain
+            .hit(&down(2.5 + 1e-6, 5.5), 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((a.normal - b.normal).length() < 1e-5);
+
+        // and the top of the bump faces straight up
+        let hit = terrain
+            .hit(&down(4.0 + 1e-9, 4.0 + 1e-9), 0.001, f64::INFINITY)
+            .unwrap();
+        assert::float(hit.normal.y, 1.0, 6);
+    }
+
+    #[test]
+    fn test_matches_triangles() {
+        let f = |x: f64, z: f64| (x * 0.7).sin() + (z * 1.3).cos();
+        let terrain = field(8, 6, f).origin(-2.0, 0.0, -1.0).spacing(0.5).build();
+
+        // the same cells as flat triangles in a list
+        let vertex = |x: usize, z: usize| {
+            Point3::new(
+                -2.0 + 0.5 * x as f64,
+                f(x as f64, z as f64),
+                -1.0 + 0.5 * z as f64,
+            )
+        };
+        let mut list = HittableList::new();
+        for z in 0..5 {
+            for x in 0..7 {
+                let material = material::Type::empty();
+                let (a, b, c, d) = (
+                    vertex(x, z),
+                    vertex(x, z + 1),
+                    vertex(x + 1, z),
+                    vertex(x + 1, z + 1),
+                );
+                list.add(Triangle::new(a, b, c, material));
+                list.add(Triangle::new(c, b, d, material));
+            }
+        }
+        assert_eq!(terrain.bounding_box(), list.bounding_box());
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = Point3::new(
+                random_f64_range(-2.0, 1.5),
+                random_f64_range(2.5, 4.0),
+                random_f64_range(-1.0, 1.5),
+            );
+            let direction = Vec3::new(
+                random_f64_range(-0.5, 0.5),
+                -1.0,
+                random_f64_range(-0.5, 0.5),
+            );
+            let ray = Ray::new(origin, direction);
+
+            let expected = list.hit(&ray, 0.001, f64::INFINITY).map(|h| h.t);
+            let actual = terrain.hit(&ray, 0.001, f64::INFINITY).map(|h| h.t);
+            assert_eq!(actual, expected, "{ray:?}");
+
+            hits += actual.is_some() as usize;
+        }
+
+        assert!(hits > 250, "{hits}");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::geo::material;
+use crate::geo::Aabb;
 use crate::geo::Interval;
 use crate::geo::Point3;
 use crate::geo::Ray;
@@ -25,51 +26,422 @@ impl HitRecord {
     }
 }
 
+/// Stretch of a ray inside a closed solid, from the surface it enters
+/// through to the surface it leaves through, e.g. for CSG
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn bounding_box(&self) -> Aabb;
     fn as_any(&self) -> &dyn std::any::Any;
+
+    /// Replace the material of every surface in this object, nested objects
+    /// included, e.g. to render the same geometry once per material.
+    /// Returns the number of surfaces changed.
+    ///
+    /// The default changes nothing, for objects without a surface material
+    /// of their own such as a `ConstantMedium`.
+    fn set_materials(&mut self, _material: material::Type) -> usize {
+        0
+    }
+
+    /// Approximate bytes used by this object including what it owns on the
+    /// heap, e.g. to compare scene layouts. Containers should add their
+    /// allocations and their children.
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+
+    /// Every span of the whole (unbounded) ray inside this object, ordered
+    /// by t. Only meaningful for closed surfaces.
+    ///
+    /// The default walks successive hits along the ray and pairs each
+    /// entering hit with the next exiting one, shapes that can solve for all
+    /// their intersections at once should override it.
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let mut spans = vec![];
+        let mut enter: Option<HitRecord> = None;
+        let mut t_min = f64::NEG_INFINITY;
+
+        // bounded so grazing hits that make no progress cannot loop forever
+        for _ in 0..64 {
+            let Some(hit) = self.hit(ray, t_min, f64::INFINITY) else {
+                break;
+            };
+
+            match (enter, hit.front_face) {
+                (None, true) => enter = Some(hit),
+                (Some(entered), false) => {
+                    spans.push(Span {
+                        enter: entered,
+                        exit: hit,
+                    });
+                    enter = None;
+                }
+                // unpaired surfaces, e.g. an open or self-overlapping mesh
+                _ => {}
+            }
+
+            t_min = hit.t + 1e-9 * hit.t.abs().max(1.0);
+        }
+
+        spans
+    }
 }
 
 pub struct HittableList {
     objects: Vec<Box<dyn Hittable>>,
+    // bounding box of each object, kept alongside so culling does not
+    // recompute them for every ray
+    boxes: Vec<Aabb>,
+    bbox: Aabb,
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HittableList {
     pub fn new() -> Self {
-        HittableList { objects: vec![] }
+        HittableList {
+            objects: vec![],
+            boxes: vec![],
+            bbox: Aabb::empty(),
+        }
+    }
+
+    /// Empty list with room for `capacity` objects, e.g. for large generated scenes
+    pub fn with_capacity(capacity: usize) -> Self {
+        HittableList {
+            objects: Vec::with_capacity(capacity),
+            boxes: Vec::with_capacity(capacity),
+            bbox: Aabb::empty(),
+        }
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.boxes.clear();
+        self.bbox = Aabb::empty();
     }
 
     pub fn add<H: Hittable + 'static>(&mut self, object: H) {
+        let bbox = object.bounding_box();
+        self.bbox = self.bbox.union(&bbox);
+        self.boxes.push(bbox);
         self.objects.push(Box::new(object));
     }
 
     pub fn objects(&self) -> &Vec<Box<dyn Hittable>> {
         &self.objects
     }
-}
 
-impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Box<dyn Hittable>> {
+        self.objects.iter()
+    }
+
+    /// Give every object, nested lists included, the same material, e.g. for
+    /// a material study of one scene. Returns the number of surfaces
+    /// changed, see `Hittable::set_materials`.
+    pub fn set_material_all(&mut self, material: material::Type) -> usize {
+        self.objects
+            .iter_mut()
+            .map(|object| object.set_materials(material))
+            .sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Same as `hit` but also returns the position of the object hit in the
+    /// list, i.e. the order it was added, e.g. to select it in a viewer
+    pub fn hit_with_index(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(usize, HitRecord)> {
         let t_interval = Interval::new(t_min, t_max);
 
         let mut closest_so_far = t_interval.max();
-        let mut hit_record: Option<HitRecord> = None;
+        let mut hit_record: Option<(usize, HitRecord)> = None;
+
+        for (index, (object, bbox)) in self.objects.iter().zip(&self.boxes).enumerate() {
+            // the slab test is cheaper than most full intersections (e.g. a
+            // triangle or a nested list) and rejects objects behind the
+            // closest hit so far as well as ones off to the side
+            if !bbox.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
 
-        for object in &self.objects {
             if let Some(hit) = object.hit(ray, t_min, closest_so_far) {
                 closest_so_far = hit.t;
-                hit_record = Some(hit);
+                hit_record = Some((index, hit));
             }
         }
 
         hit_record
     }
+}
+
+impl Extend<Box<dyn Hittable>> for HittableList {
+    fn extend<I: IntoIterator<Item = Box<dyn Hittable>>>(&mut self, iter: I) {
+        for object in iter {
+            let bbox = object.bounding_box();
+            self.bbox = self.bbox.union(&bbox);
+            self.boxes.push(bbox);
+            self.objects.push(object);
+        }
+    }
+}
+
+impl FromIterator<Box<dyn Hittable>> for HittableList {
+    fn from_iter<I: IntoIterator<Item = Box<dyn Hittable>>>(iter: I) -> Self {
+        let mut list = HittableList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl IntoIterator for HittableList {
+    type Item = Box<dyn Hittable>;
+    type IntoIter = std::vec::IntoIter<Box<dyn Hittable>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a HittableList {
+    type Item = &'a Box<dyn Hittable>;
+    type IntoIter = std::slice::Iter<'a, Box<dyn Hittable>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.iter()
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_with_index(ray, t_min, t_max).map(|(_, hit)| hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material_all(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.objects.capacity() * std::mem::size_of::<Box<dyn Hittable>>()
+            + self.boxes.capacity() * std::mem::size_of::<Aabb>()
+            + self.objects.iter().map(|o| o.memory_bytes()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench;
+    use crate::core::random_f64_range;
+    use crate::geo::Sphere;
+    use crate::geo::Triangle;
+
+    #[test]
+    fn test_collect() {
+        let spheres =
+            [-1.0, 0.0, 1.0].map(|x| Sphere::builder().center(x, 0.0, -1.0).radius(0.25).build());
+
+        let list: HittableList = spheres
+            .iter()
+            .map(|s| Box::new(*s) as Box<dyn Hittable>)
+            .collect();
+        assert_eq!(list.len(), 3);
+        assert!(!list.is_empty());
+
+        let mut added = HittableList::with_capacity(3);
+        assert!(added.is_empty());
+        for sphere in spheres {
+            added.add(sphere);
+        }
+        assert_eq!(list.bounding_box(), added.bounding_box());
+
+        // borrowed iteration in insertion order
+        let centers: Vec<f64> = (&list)
+            .into_iter()
+            .map(|o| o.as_any().downcast_ref::<Sphere>().unwrap().center().x())
+            .collect();
+        assert_eq!(centers, [-1.0, 0.0, 1.0]);
+        assert_eq!(list.iter().count(), 3);
+
+        // extend keeps the bounding box up to date
+        let mut extended: HittableList = list.into_iter().take(1).collect();
+        extended.extend(vec![Box::new(spheres[2]) as Box<dyn Hittable>]);
+        assert_eq!(extended.len(), 2);
+        assert_eq!(extended.bounding_box(), added.bounding_box());
+    }
+
+    #[test]
+    fn test_culling_matches_unculled() {
+        let (_, world) = bench::mesh_scene();
+
+        // every object tested, closest hit wins
+        let unculled = |ray: &Ray| {
+            let mut closest: Option<HitRecord> = None;
+            for object in &world {
+                let t_max = closest.map_or(f64::INFINITY, |h| h.t);
+                if let Some(hit) = object.hit(ray, 0.001, t_max) {
+                    closest = Some(hit);
+                }
+            }
+            closest
+        };
+
+        let mut hits = 0;
+        for n in 0..2000 {
+            let origin = Point3::new(
+                random_f64_range(-4.0, 4.0),
+                random_f64_range(0.1, 4.0),
+                random_f64_range(-4.0, 4.0),
+            );
+
+            // half the rays aimed at the mesh so most of them hit a triangle
+            let direction = if n % 2 == 0 {
+                Vec3::new(0.0, 1.0, 0.0) - Vec3::from(origin) + Vec3::random_range(-0.5, 0.5)
+            } else {
+                Vec3::random_range(-1.0, 1.0)
+            };
+            let ray = Ray::new(origin, direction);
+
+            let expected = unculled(&ray);
+            let actual = world.hit(&ray, 0.001, f64::INFINITY);
+            assert_eq!(actual, expected, "{ray:?}");
+
+            hits += actual.is_some() as usize;
+        }
+
+        assert!(hits > 1000, "{hits}");
+    }
+
+    #[test]
+    fn test_default_spans() {
+        // list does not override spans, so it walks its hits
+        let mut list = HittableList::new();
+        for z in [0.0, -3.0] {
+            list.add(Sphere::builder().center(0.0, 0.0, z).radius(1.0).build());
+        }
+
+        // origin inside the first sphere, its entry lies behind the ray
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let spans = list.spans(&ray);
+        let ts: Vec<_> = spans.iter().map(|s| (s.enter.t, s.exit.t)).collect();
+        assert_eq!(ts, [(-1.0, 1.0), (2.0, 4.0)]);
+        assert!(spans
+            .iter()
+            .all(|s| s.enter.front_face && !s.exit.front_face));
+
+        assert!(HittableList::new().spans(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_memory_bytes() {
+        let sphere = Sphere::builder().build();
+        assert_eq!(sphere.memory_bytes(), std::mem::size_of::<Sphere>());
+
+        let mut list = HittableList::new();
+        for n in 0..1000 {
+            let x = n as f64;
+            list.add(Triangle::new(
+                Point3::new(x, 0.0, 0.0),
+                Point3::new(x + 1.0, 0.0, 0.0),
+                Point3::new(x, 1.0, 0.0),
+                material::Type::default(),
+            ));
+        }
+
+        // triangles and their cached bounding boxes dominate, pointers and
+        // spare vec capacity add a little
+        let triangles = 1000 * (std::mem::size_of::<Triangle>() + std::mem::size_of::<Aabb>());
+        let bytes = list.memory_bytes();
+        assert!(bytes >= triangles, "{bytes} >= {triangles}");
+        assert!(bytes < triangles * 5 / 4, "{bytes} < {triangles} * 1.25");
+
+        let empty = HittableList::new();
+        assert_eq!(empty.memory_bytes(), std::mem::size_of::<HittableList>());
+    }
+
+    #[test]
+    fn test_set_material_all() {
+        let triangle = |z: f64| {
+            Triangle::new(
+                Point3::new(-1.0, -1.0, z),
+                Point3::new(1.0, -1.0, z),
+                Point3::new(0.0, 1.0, z),
+                material::Type::debug(),
+            )
+        };
+
+        // one of each container around the surfaces, and a volume without
+        // a surface material of its own
+        let mut nested = HittableList::new();
+        nested.add(triangle(-2.0));
+        nested.add(crate::geo::Rotate::new(
+            Box::new(crate::geo::TriangleMesh::new(
+                triangle(-3.0).vertices().to_vec(),
+                vec![[0, 1, 2]],
+                material::Type::debug(),
+            )),
+            Vec3::new(0.0, 0.0, 1.0),
+            0.0,
+        ));
+
+        let mut world = HittableList::new();
+        world.add(crate::geo::BvhNode::new(vec![
+            Box::new(
+                Sphere::builder()
+                    .center(0.0, 0.0, -1.0)
+                    .radius(0.25)
+                    .build(),
+            ),
+            Box::new(
+                Sphere::builder()
+                    .center(5.0, 0.0, -1.0)
+                    .radius(0.25)
+                    .build(),
+            ),
+        ]));
+        world.add(nested);
+        world.add(crate::geo::ConstantMedium::new(
+            Box::new(Sphere::builder().center(0.0, 5.0, 0.0).build()),
+            1.0,
+            material::Type::from(crate::geo::IsotropicParams::default()),
+        ));
+
+        let metal = material::Type::from(crate::geo::MetalParams::default());
+        assert_eq!(world.set_material_all(metal), 4);
+
+        // every surface along the z axis hits with the new material
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut t_min = 0.001;
+        let mut hits = 0;
+        while let Some(hit) = world.hit(&ray, t_min, f64::INFINITY) {
+            assert_eq!(hit.material, metal);
+            t_min = hit.t + 0.001;
+            hits += 1;
+        }
+        assert_eq!(hits, 4);
+    }
 }
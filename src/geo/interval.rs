@@ -54,6 +54,12 @@ impl Interval {
     }
 }
 
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.min, self.max)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,6 +71,22 @@ mod tests {
         assert_eq!(i.max(), 6.0);
     }
 
+    #[test]
+    fn test_display() {
+        let i = Interval::new(-4.0, 6.0);
+        assert_eq!(format!("{i}"), "[-4, 6]");
+
+        let i = Interval::new(0.001, f64::INFINITY);
+        assert_eq!(format!("{i}"), "[0.001, inf]");
+    }
+
+    #[test]
+    fn test_copy() {
+        let a = Interval::new(-4.0, 6.0);
+        let b = a;
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn test_empty() {
         let i = Interval::empty();
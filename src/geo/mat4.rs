@@ -0,0 +1,293 @@
+use crate::geo::Aabb;
+use crate::geo::Point3;
+use crate::geo::Vec3;
+use std::ops;
+
+/// 4x4 affine transform acting on column vectors, `a * b` applies `b` first
+///
+/// e.g. `Mat4::translation(0.0, 1.0, 0.0) * Mat4::scale(2.0, 2.0, 2.0)`
+/// doubles the size of an object and then lifts it by one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    /// Matrix from its rows
+    pub const fn new(m: [[f64; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    pub fn translation(x: f64, y: f64, z: f64) -> Mat4 {
+        Mat4::new([
+            [1.0, 0.0, 0.0, x],
+            [0.0, 1.0, 0.0, y],
+            [0.0, 0.0, 1.0, z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(x: f64, y: f64, z: f64) -> Mat4 {
+        Mat4::new([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Counterclockwise rotation by `angle` degrees about `axis` through the
+    /// origin (right hand rule), see `Rotate`
+    pub fn rotation(axis: Vec3, angle: f64) -> Mat4 {
+        let k = axis.unit();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        // R = cos I + sin [k]x + (1 - cos) k k^T
+        Mat4::new([
+            [
+                cos + t * k.x * k.x,
+                t * k.x * k.y - sin * k.z,
+                t * k.x * k.z + sin * k.y,
+                0.0,
+            ],
+            [
+                t * k.y * k.x + sin * k.z,
+                cos + t * k.y * k.y,
+                t * k.y * k.z - sin * k.x,
+                0.0,
+            ],
+            [
+                t * k.z * k.x - sin * k.y,
+                t * k.z * k.y + sin * k.x,
+                cos + t * k.z * k.z,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rows(&self) -> [[f64; 4]; 4] {
+        self.m
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = self.m[c][r];
+            }
+        }
+        Mat4::new(m)
+    }
+
+    /// Inverse by Gauss-Jordan elimination, None when the matrix is singular
+    /// e.g. a scale of 0 along an axis
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inverse = Mat4::IDENTITY.m;
+
+        for col in 0..4 {
+            // partial pivoting, largest remaining value in the column
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inverse.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for c in 0..4 {
+                a[col][c] *= scale;
+                inverse[col][c] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for c in 0..4 {
+                    a[row][c] -= factor * a[col][c];
+                    inverse[row][c] -= factor * inverse[col][c];
+                }
+            }
+        }
+
+        Some(Mat4::new(inverse))
+    }
+
+    /// Transform a position, translation applies
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        let w = m[3][0] * x + m[3][1] * y + m[3][2] * z + m[3][3];
+
+        Point3::new(
+            (m[0][0] * x + m[0][1] * y + m[0][2] * z + m[0][3]) / w,
+            (m[1][0] * x + m[1][1] * y + m[1][2] * z + m[1][3]) / w,
+            (m[2][0] * x + m[2][1] * y + m[2][2] * z + m[2][3]) / w,
+        )
+    }
+
+    /// Transform a direction, translation does not apply. Normals need the
+    /// inverse transpose instead.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Box enclosing the 8 transformed corners of `bbox`
+    pub fn transform_aabb(&self, bbox: &Aabb) -> Aabb {
+        if bbox.is_empty() {
+            return *bbox;
+        }
+
+        let (min, max) = (bbox.min(), bbox.max());
+        let mut lo = [f64::INFINITY; 3];
+        let mut hi = [f64::NEG_INFINITY; 3];
+        for n in 0..8 {
+            let corner = self.transform_point(&Point3::new(
+                if n & 1 == 0 { min.x() } else { max.x() },
+                if n & 2 == 0 { min.y() } else { max.y() },
+                if n & 4 == 0 { min.z() } else { max.z() },
+            ));
+            for (axis, value) in Vec3::from(corner).into_iter().enumerate() {
+                lo[axis] = lo[axis].min(value);
+                hi[axis] = hi[axis].max(value);
+            }
+        }
+
+        Aabb::from_points(
+            Point3::new(lo[0], lo[1], lo[2]),
+            Point3::new(hi[0], hi[1], hi[2]),
+        )
+    }
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|n| self.m[r][n] * rhs.m[n][c]).sum();
+            }
+        }
+        Mat4::new(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::assert;
+
+    fn assert_mat4(a: &Mat4, b: &Mat4) {
+        for (row_a, row_b) in a.rows().iter().zip(b.rows()) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert::float(*x, y, 9);
+            }
+        }
+    }
+
+    fn assert_point(a: Point3, b: Point3) {
+        assert::float(a.x(), b.x(), 9);
+        assert::float(a.y(), b.y(), 9);
+        assert::float(a.z(), b.z(), 9);
+    }
+
+    #[test]
+    fn test_compose() {
+        // scale, then a quarter turn about z, then translate
+        let m = Mat4::translation(1.0, 2.0, 3.0)
+            * Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0)
+            * Mat4::scale(2.0, 3.0, 4.0);
+
+        // (1, 1, 1) -> (2, 3, 4) -> (-3, 2, 4) -> (-2, 4, 7)
+        let p = m.transform_point(&Point3::new(1.0, 1.0, 1.0));
+        assert_point(p, Point3::new(-2.0, 4.0, 7.0));
+
+        // (1, 0, 0) -> (2, 0, 0) -> (0, 2, 0) -> (1, 4, 3)
+        let p = m.transform_point(&Point3::new(1.0, 0.0, 0.0));
+        assert_point(p, Point3::new(1.0, 4.0, 3.0));
+
+        // vectors ignore the translation
+        let v = m.transform_vector(&Vec3::new(1.0, 0.0, 0.0));
+        assert_point(Point3::from(v), Point3::new(0.0, 2.0, 0.0));
+
+        // the other order translates first and scales the translation
+        let m = Mat4::scale(2.0, 3.0, 4.0) * Mat4::translation(1.0, 2.0, 3.0);
+        let p = m.transform_point(&Point3::new(0.0, 0.0, 0.0));
+        assert_point(p, Point3::new(2.0, 6.0, 12.0));
+    }
+
+    #[test]
+    fn test_rotation() {
+        // a third of a turn about (1, 1, 1) takes x to y
+        let m = Mat4::rotation(Vec3::new(1.0, 1.0, 1.0), 120.0);
+        let v = m.transform_vector(&Vec3::new(1.0, 0.0, 0.0));
+        assert_point(Point3::from(v), Point3::new(0.0, 1.0, 0.0));
+
+        // rotations are orthogonal
+        assert_mat4(&m.inverse().unwrap(), &m.transpose());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat4::translation(-1.0, 0.5, 2.0)
+            * Mat4::rotation(Vec3::new(1.0, -2.0, 0.5), 37.0)
+            * Mat4::scale(0.5, 2.0, 3.0);
+        let inverse = m.inverse().unwrap();
+
+        assert_mat4(&(m * inverse), &Mat4::IDENTITY);
+        assert_mat4(&(inverse * m), &Mat4::IDENTITY);
+
+        let p = Point3::new(0.3, -4.0, 1.5);
+        assert_point(inverse.transform_point(&m.transform_point(&p)), p);
+
+        assert_eq!(Mat4::default().inverse(), Some(Mat4::IDENTITY));
+        assert_eq!(Mat4::scale(1.0, 0.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn test_transform_aabb() {
+        let bbox = Aabb::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+        let m = Mat4::translation(1.0, 0.0, 0.0) * Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), 90.0);
+
+        let moved = m.transform_aabb(&bbox);
+        assert_point(moved.min(), Point3::new(-1.0, 0.0, 0.0));
+        assert_point(moved.max(), Point3::new(1.0, 1.0, 3.0));
+
+        assert!(m.transform_aabb(&Aabb::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let m = Mat4::translation(1.0, 2.0, 3.0);
+        assert_eq!(m.transpose().rows()[3], [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(m.transpose().transpose(), m);
+    }
+}
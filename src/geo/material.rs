@@ -1,8 +1,13 @@
+use std::sync::atomic;
+
 use crate::core::random_f64;
 use crate::core::Color;
+use crate::geo::onb;
+use crate::geo::random_hg_direction;
 use crate::geo::random_unit;
 use crate::geo::random_unit_normal_direction;
 use crate::geo::HitRecord;
+use crate::geo::Mat4;
 use crate::geo::Ray;
 use crate::geo::Vec3;
 
@@ -16,6 +21,11 @@ pub enum Type {
     Lambertian(Lambertian),
     Metal(Metal),
     Dielectric(Dielectric),
+    Portal(Portal),
+    OrenNayar(OrenNayar),
+    Coated(Coated),
+    DiffuseLight(DiffuseLight),
+    Isotropic(Isotropic),
 }
 
 impl Default for Type {
@@ -28,6 +38,11 @@ pub enum Params {
     Lambertian(LambertianParams),
     Metal(MetalParams),
     Dielectric(DielectricParams),
+    Portal(PortalParams),
+    OrenNayar(OrenNayarParams),
+    Coated(CoatedParams),
+    DiffuseLight(DiffuseLightParams),
+    Isotropic(IsotropicParams),
 }
 
 impl From<LambertianParams> for Params {
@@ -48,6 +63,36 @@ impl From<DielectricParams> for Params {
     }
 }
 
+impl From<PortalParams> for Params {
+    fn from(p: PortalParams) -> Self {
+        Params::Portal(p)
+    }
+}
+
+impl From<OrenNayarParams> for Params {
+    fn from(p: OrenNayarParams) -> Self {
+        Params::OrenNayar(p)
+    }
+}
+
+impl From<CoatedParams> for Params {
+    fn from(p: CoatedParams) -> Self {
+        Params::Coated(p)
+    }
+}
+
+impl From<DiffuseLightParams> for Params {
+    fn from(p: DiffuseLightParams) -> Self {
+        Params::DiffuseLight(p)
+    }
+}
+
+impl From<IsotropicParams> for Params {
+    fn from(p: IsotropicParams) -> Self {
+        Params::Isotropic(p)
+    }
+}
+
 impl Type {
     // Helper constructors for external use.
     pub fn empty() -> Self {
@@ -58,32 +103,167 @@ impl Type {
         Type::Debug(Debug {})
     }
 
+    /// True for unassigned materials, which absorb every ray
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Type::Empty(_))
+    }
+
+    /// True for glass-like materials, unlike a coated material which only
+    /// shares a refraction index
+    pub fn is_dielectric(&self) -> bool {
+        matches!(self, Type::Dielectric(_))
+    }
+
     pub fn from<P>(params: P) -> Self
     where
         P: Into<Params>,
     {
         match params.into() {
-            Params::Lambertian(params) => {
-                return Type::Lambertian(Lambertian {
+            Params::Lambertian(params) => Type::Lambertian(Lambertian {
+                albedo: params.albedo,
+                reflectance: params.reflectance,
+                uniform: params.uniform,
+            }),
+
+            Params::Metal(params) => Type::Metal(Metal {
+                albedo: params.albedo,
+                reflectance: params.reflectance,
+                fuzz: params.fuzz.min(1.0),
+                roughness: params.roughness.map(|r| r.clamp(0.0, 1.0)),
+            }),
+
+            Params::Dielectric(params) => Type::Dielectric(Dielectric {
+                refraction_index: params.refraction_index,
+                dispersion: params.dispersion,
+                exterior_index: params.exterior_index,
+                attenuation: params.attenuation,
+            }),
+
+            Params::Portal(params) => Type::Portal(Portal {
+                link_transform: params.link_transform,
+            }),
+
+            Params::OrenNayar(params) => {
+                let sigma2 = params.roughness * params.roughness;
+                Type::OrenNayar(OrenNayar {
                     albedo: params.albedo,
-                    reflectance: params.reflectance,
-                    uniform: params.uniform,
+                    roughness: params.roughness,
+                    a: 1.0 - 0.5 * sigma2 / (sigma2 + 0.33),
+                    b: 0.45 * sigma2 / (sigma2 + 0.09),
                 })
             }
 
-            Params::Metal(params) => {
-                return Type::Metal(Metal {
-                    albedo: params.albedo,
-                    reflectance: params.reflectance,
-                    fuzz: params.fuzz.min(1.0),
-                })
+            // a stack deeper than MAX_COATS cannot be represented, refuse it
+            // rather than drop a layer, see Type::try_from for the error
+            Params::Coated(params) => {
+                Type::Coated(Coated::new(params).unwrap_or_else(|error| panic!("{error}")))
             }
 
-            Params::Dielectric(params) => {
-                return Type::Dielectric(Dielectric {
-                    refraction_index: params.refraction_index,
-                })
+            Params::DiffuseLight(params) => Type::DiffuseLight(DiffuseLight {
+                emit: params.emit,
+                two_sided: params.two_sided,
+            }),
+
+            // g of +-1 is a delta, keep the sampling finite
+            Params::Isotropic(params) => Type::Isotropic(Isotropic {
+                albedo: params.albedo,
+                g: params.g.clamp(-0.999, 0.999),
+            }),
+        }
+    }
+}
+
+// read back material properties without scattering, e.g. for inspecting a hit
+impl Type {
+    pub fn albedo(&self) -> Option<Color> {
+        match self {
+            Type::Lambertian(m) => Some(m.albedo),
+            Type::Metal(m) => Some(m.albedo),
+            Type::OrenNayar(m) => Some(m.albedo),
+            Type::Coated(m) => m.base().albedo(),
+            Type::Isotropic(m) => Some(m.albedo),
+            _ => None,
+        }
+    }
+
+    pub fn reflectance(&self) -> Option<f64> {
+        match self {
+            Type::Lambertian(m) => Some(m.reflectance),
+            Type::Metal(m) => Some(m.reflectance),
+            _ => None,
+        }
+    }
+
+    pub fn fuzz(&self) -> Option<f64> {
+        match self {
+            Type::Metal(m) => Some(m.fuzz),
+            _ => None,
+        }
+    }
+
+    pub fn roughness(&self) -> Option<f64> {
+        match self {
+            Type::Metal(m) => m.roughness,
+            Type::OrenNayar(m) => Some(m.roughness),
+            Type::Coated(m) => Some(m.coats[0].roughness),
+            _ => None,
+        }
+    }
+
+    pub fn refraction_index(&self) -> Option<f64> {
+        match self {
+            Type::Dielectric(m) => Some(m.refraction_index),
+            Type::Coated(m) => Some(m.coats[0].refraction_index),
+            _ => None,
+        }
+    }
+
+    pub fn exterior_index(&self) -> Option<f64> {
+        match self {
+            Type::Dielectric(m) => Some(m.exterior_index),
+            _ => None,
+        }
+    }
+
+    /// Material under the outermost clear coat of a coated material, the
+    /// remaining coats over the base when coats are stacked
+    pub fn coat_base(&self) -> Option<Type> {
+        match self {
+            Type::Coated(m) => Some(m.under()),
+            _ => None,
+        }
+    }
+
+    /// Representative color for albedo AOVs and color swatches, e.g. the
+    /// albedo of a diffuse material, the tint of glass or the emitted color
+    /// of a light. Black for the empty material, the debug material's color
+    /// depends on the normal so it reads as mid gray.
+    pub fn base_color(&self) -> Color {
+        match self {
+            Type::Empty(_) => Color::BLACK,
+            Type::Debug(_) => Color::new(0.5, 0.5, 0.5),
+            Type::Lambertian(m) => m.albedo,
+            Type::Metal(m) => m.albedo,
+            Type::Dielectric(m) => m.attenuation,
+            Type::Portal(_) => Color::WHITE,
+            Type::OrenNayar(m) => m.albedo,
+            Type::Coated(m) => m.base().base_color(),
+            Type::DiffuseLight(m) => m.emit,
+            Type::Isotropic(m) => m.albedo,
+        }
+    }
+
+    /// Fraction of light arriving from `incoming` reflected towards
+    /// `outgoing` per steradian (BRDF), unit vectors on the normal's side.
+    /// Only for diffuse materials, used to add analytic lights directly.
+    pub fn diffuse_brdf(&self, normal: &Vec3, outgoing: &Vec3, incoming: &Vec3) -> Option<Color> {
+        match self {
+            Type::Lambertian(m) => Some(m.albedo / std::f64::consts::PI),
+            Type::OrenNayar(m) => {
+                let factor = m.factor(normal, outgoing, incoming);
+                Some(m.albedo * (factor / std::f64::consts::PI))
             }
+            _ => None,
         }
     }
 }
@@ -96,6 +276,20 @@ impl Type {
             Type::Lambertian(m) => m.scatter(ray, hit),
             Type::Metal(m) => m.scatter(ray, hit),
             Type::Dielectric(m) => m.scatter(ray, hit),
+            Type::Portal(m) => m.scatter(ray, hit),
+            Type::OrenNayar(m) => m.scatter(ray, hit),
+            Type::Coated(m) => m.scatter(ray, hit),
+            Type::DiffuseLight(m) => m.scatter(ray, hit),
+            Type::Isotropic(m) => m.scatter(ray, hit),
+        }
+    }
+
+    /// Light given off at the hit, black for every material but lights
+    pub fn emitted(&self, hit: &HitRecord) -> Color {
+        match self {
+            Type::DiffuseLight(m) => m.emitted(hit),
+            Type::Coated(m) => m.base().emitted(hit),
+            _ => Color::BLACK,
         }
     }
 }
@@ -120,6 +314,8 @@ pub struct MetalParams {
     pub albedo: Color,
     pub reflectance: f64,
     pub fuzz: f64,
+    /// GGX microfacet roughness in [0, 1], replaces `fuzz` when set
+    pub roughness: Option<f64>,
 }
 
 impl Default for MetalParams {
@@ -128,22 +324,158 @@ impl Default for MetalParams {
             albedo: Color::new(1.0, 0.0, 0.0),
             reflectance: 1.0,
             fuzz: 0.0,
+            roughness: None,
         }
     }
 }
 
 pub struct DielectricParams {
     pub refraction_index: f64,
+    /// Difference between the blue and red refraction index (0 for none)
+    pub dispersion: f64,
+    /// Refraction index of the medium around the object, e.g. 1.33 for a
+    /// glass sphere submerged in water, 1.0 (vacuum, close enough to air) by default
+    pub exterior_index: f64,
+    /// Constant tint multiplied into every bounce, e.g. red for cheap colored
+    /// glass, white (clear) by default. Unlike absorption it does not depend
+    /// on the distance travelled inside.
+    pub attenuation: Color,
 }
 
 impl Default for DielectricParams {
     fn default() -> Self {
         Self {
             refraction_index: 1.0,
+            dispersion: 0.0,
+            exterior_index: 1.0,
+            attenuation: Color::WHITE,
+        }
+    }
+}
+
+pub struct OrenNayarParams {
+    pub albedo: Color,
+    /// Standard deviation of microfacet slope angles in radians (sigma),
+    /// 0 is Lambertian
+    pub roughness: f64,
+}
+
+impl Default for OrenNayarParams {
+    fn default() -> Self {
+        Self {
+            albedo: Color::new(1.0, 0.0, 0.0),
+            roughness: 0.0,
+        }
+    }
+}
+
+/// Clear specular coat over another material, e.g. car paint or varnished
+/// wood, the coat reflects the Fresnel fraction of light and passes the rest
+/// on to the base
+///
+/// Coating a coated material stacks the new coat on top, up to `MAX_COATS`
+/// coats in all. `Type::from` panics on deeper stacks, `Type::try_from`
+/// returns the error.
+pub struct CoatedParams {
+    pub base: Type,
+    /// Refraction index of the coat, sets how much it reflects
+    pub refraction_index: f64,
+    /// Blur of the coat reflection in [0, 1], 0 is a perfect mirror
+    pub roughness: f64,
+}
+
+/// Most clear coats a single material can stack, see `CoatedParams`
+pub const MAX_COATS: usize = 3;
+
+/// Material parameters that cannot be built
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialError {
+    /// Coats in the stack, more than `MAX_COATS`
+    TooManyCoats(usize),
+}
+
+impl std::fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MaterialError::TooManyCoats(count) => {
+                write!(
+                    f,
+                    "{count} stacked coats, at most {MAX_COATS} are supported"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+impl TryFrom<CoatedParams> for Type {
+    type Error = MaterialError;
+
+    fn try_from(params: CoatedParams) -> Result<Self, Self::Error> {
+        Coated::new(params).map(Type::Coated)
+    }
+}
+
+impl Default for CoatedParams {
+    fn default() -> Self {
+        Self {
+            base: Type::from(LambertianParams::default()),
+            refraction_index: 1.5,
+            roughness: 0.0,
+        }
+    }
+}
+
+/// Surface giving off light of its own, e.g. an area light or a glowing
+/// object, it emits `emit` and absorbs everything arriving at it
+pub struct DiffuseLightParams {
+    /// Emitted radiance, may exceed 1 for bright lights
+    pub emit: Color,
+    /// Emit from both faces, otherwise only from the side the surface normal
+    /// points towards (the front face)
+    pub two_sided: bool,
+}
+
+impl Default for DiffuseLightParams {
+    fn default() -> Self {
+        Self {
+            emit: Color::WHITE,
+            two_sided: true,
+        }
+    }
+}
+
+/// Phase function of a participating medium, e.g. the fog or smoke inside a
+/// `ConstantMedium`, light scatters off in a new direction wherever it
+/// interacts instead of at a surface
+pub struct IsotropicParams {
+    pub albedo: Color,
+    /// Henyey-Greenstein anisotropy in (-1, 1), positive scatters forward
+    /// (smoke, haze), negative back, 0 is uniform in every direction
+    pub g: f64,
+}
+
+impl Default for IsotropicParams {
+    fn default() -> Self {
+        Self {
+            albedo: Color::WHITE,
+            g: 0.0,
         }
     }
 }
 
+/// Link from one portal surface to its pair, applied to rays that enter it
+///
+/// `link_transform` takes the entry point to the exit point and the ray's
+/// direction along with it, usually a rigid motion carrying one portal
+/// onto the other, e.g. `Mat4::translation(0.0, 5.0, 0.0) *
+/// Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), 90.0)` from a wall to a floor
+#[derive(Default)]
+pub struct PortalParams {
+    pub link_transform: Mat4,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ScatterRecord {
     pub ray: Ray,
@@ -151,6 +483,32 @@ pub struct ScatterRecord {
     pub color: Option<Color>,
 }
 
+impl ScatterRecord {
+    /// True if any channel of attenuation exceeds 1, the bounce adds energy
+    /// instead of only absorbing it, e.g. a Lambertian with reflectance < albedo
+    pub fn gains_energy(&self) -> bool {
+        let a = self.attenuation;
+        a.x() > 1.0 || a.y() > 1.0 || a.z() > 1.0
+    }
+}
+
+static ENERGY_WARNINGS: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+
+/// Report a scatter that gained energy, printing only the first occurrence
+/// so a mis-specified material does not flood stderr once per sample
+pub fn warn_energy_gain(material: &Type, attenuation: Color) {
+    if ENERGY_WARNINGS.fetch_add(1, atomic::Ordering::Relaxed) == 0 {
+        eprintln!(
+            "warning: attenuation {attenuation:?} exceeds 1.0, material adds energy: {material:?}"
+        );
+    }
+}
+
+/// Count of `warn_energy_gain` calls in this process
+pub fn energy_warnings() -> usize {
+    ENERGY_WARNINGS.load(atomic::Ordering::Relaxed)
+}
+
 pub trait Material {
     fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord>;
 }
@@ -171,7 +529,7 @@ impl Material for Debug {
     fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
         // color based on normal
         // normal is in range [-1, 1], add 1 ([0, 2]) and halving ([0, 1])
-        let normal_01 = 0.5 * (hit_record.normal + Vec3::new(1.0, 1.0, 1.0));
+        let normal_01 = 0.5 * (hit_record.normal + Vec3::ONE);
         let color = Color::from(normal_01);
 
         Some(ScatterRecord {
@@ -228,10 +586,81 @@ struct Metal {
     // randomize reflected direction by using small sphere centered on the original
     // endpoint choosing a random point from the surface of the sphere
     fuzz: f64,
+    // physically based alternative to fuzz, microfacets oriented by the
+    // ggx (trowbridge-reitz) distribution, 0 mirror to 1 very rough
+    roughness: Option<f64>,
+}
+
+impl Metal {
+    // importance sample a microfacet normal (half vector) from the ggx
+    // distribution and reflect about it, weighting by the smith shadowing term
+    //
+    // https://www.cs.cornell.edu/~srm/publications/EGSR07-btdf.pdf
+    fn ggx_scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: HitRecord,
+        roughness: f64,
+    ) -> Option<ScatterRecord> {
+        // same absorption as the fuzz path, see reflectance_scatter
+        if random_f64() > self.reflectance {
+            return None;
+        }
+
+        // alpha = roughness^2 is perceptually linear, keep it off zero to avoid 0/0
+        let alpha = (roughness * roughness).max(1e-6);
+        let alpha2 = alpha * alpha;
+
+        let normal = hit_record.normal;
+        let outgoing = -ray_in.direction().unit();
+        let n_dot_o = normal.dot(&outgoing);
+        if n_dot_o <= 0.0 {
+            return None;
+        }
+
+        let u1 = random_f64();
+        let u2 = random_f64();
+        let phi = 2.0 * std::f64::consts::PI * u2;
+        let cos_theta = ((1.0 - u1) / (1.0 + (alpha2 - 1.0) * u1)).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+        let (tangent, bitangent) = onb(&normal);
+
+        let half = sin_theta * phi.cos() * tangent
+            + sin_theta * phi.sin() * bitangent
+            + cos_theta * normal;
+
+        let o_dot_h = outgoing.dot(&half);
+        let incoming = 2.0 * o_dot_h * half - outgoing;
+        let n_dot_i = normal.dot(&incoming);
+
+        // reflected below the surface, absorbed
+        if o_dot_h <= 0.0 || n_dot_i <= 0.0 {
+            return None;
+        }
+
+        let smith_g1 = |n_dot_v: f64| {
+            2.0 * n_dot_v / (n_dot_v + (alpha2 + (1.0 - alpha2) * n_dot_v * n_dot_v).sqrt())
+        };
+        let g = smith_g1(n_dot_o) * smith_g1(n_dot_i);
+
+        // brdf * cos / pdf, the distribution term cancels with the sampling pdf
+        let weight = g * o_dot_h / (n_dot_o * normal.dot(&half));
+
+        Some(ScatterRecord {
+            ray: Ray::new(hit_record.p, incoming),
+            attenuation: self.albedo / self.reflectance * weight,
+            color: None,
+        })
+    }
 }
 
 impl Material for Metal {
     fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        if let Some(roughness) = self.roughness {
+            return self.ggx_scatter(ray_in, hit_record, roughness);
+        }
+
         let direction = ray_in.direction().reflect(&hit_record.normal);
 
         reflectance_scatter(ReflectanceScatterOptions {
@@ -251,14 +680,59 @@ struct Dielectric {
     // refraction index of material over refraction index of enclosing media
     // snell's law https://en.wikipedia.org/wiki/Snell%27s_law
     refraction_index: f64,
+    // spread of refraction index across wavelengths, blue index minus red index
+    // https://en.wikipedia.org/wiki/Dispersion_(optics)
+    dispersion: f64,
+    // refraction index of the medium the object sits in, a single fixed
+    // value rather than tracking the media a ray is nested in
+    exterior_index: f64,
+    // tint of every bounce, see DielectricParams
+    attenuation: Color,
+}
+
+impl Dielectric {
+    // refraction index, attenuation and traced color channel of a hit by a
+    // path already restricted to `channel`, if any
+    //
+    // a ray can only follow one refracted direction, so the first dispersive
+    // hit of a path picks one rgb channel at random, zeroes the others and
+    // weights the picked one by 3 so the expected color over many samples
+    // stays the same as without dispersion. The path keeps that channel, so
+    // later hits refract with the same channel's index and add no weight.
+    fn dispersed(&self, channel: Option<u8>) -> (f64, Color, Option<u8>) {
+        if self.dispersion == 0.0 {
+            return (self.refraction_index, Color::WHITE, channel);
+        }
+
+        let (channel, attenuation) = match channel {
+            Some(channel) => (channel, Color::WHITE),
+            None => {
+                let channel = ((random_f64() * 3.0) as u8).min(2);
+                let mut attenuation = Vec3::new(0.0, 0.0, 0.0);
+                attenuation[channel] = 3.0;
+                (channel, Color::from(attenuation))
+            }
+        };
+
+        let offset = [-0.5, 0.0, 0.5][channel as usize];
+        (
+            self.refraction_index + offset * self.dispersion,
+            attenuation,
+            Some(channel),
+        )
+    }
 }
 
 impl Material for Dielectric {
     fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        let (refraction_index, dispersed, channel) = self.dispersed(ray_in.channel());
+        let attenuation = self.attenuation * dispersed;
+
+        // relative index, medium the ray leaves over the medium it enters
         let refraction_index = if hit_record.front_face {
-            1.0 / self.refraction_index
+            self.exterior_index / refraction_index
         } else {
-            self.refraction_index
+            refraction_index / self.exterior_index
         };
 
         let incident_uv = ray_in.direction().unit();
@@ -271,20 +745,19 @@ impl Material for Dielectric {
         // eprintln!("  refraction_index={:?}", refraction_index);
 
         let cos_theta = incident_uv.cos_theta(&hit_record.normal);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-        let cannot_refract = refraction_index * sin_theta > 1.0;
 
         let reflectance_chance = reflectance(cos_theta, refraction_index);
         let must_reflect = reflectance_chance > random_f64();
 
-        let direction = if cannot_refract || must_reflect {
-            incident_uv.reflect(&hit_record.normal)
-        } else {
-            incident_uv.refract(&hit_record.normal, refraction_index)
+        // None under total internal reflection
+        let refracted = incident_uv.refract_checked(&hit_record.normal, refraction_index);
+
+        let direction = match refracted {
+            Some(refracted) if !must_reflect => refracted,
+            _ => incident_uv.reflect(&hit_record.normal),
         };
 
-        let ray = Ray::new(hit_record.p, direction);
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+        let ray = Ray::new(hit_record.p, direction).with_channel(channel);
 
         Some(ScatterRecord {
             ray,
@@ -294,6 +767,275 @@ impl Material for Dielectric {
     }
 }
 
+// rough diffuse surfaces such as clay, plaster, and the moon, which unlike
+// lambertian surfaces appear brighter toward the viewer at grazing angles
+// https://en.wikipedia.org/wiki/Oren%E2%80%93Nayar_reflectance_model
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct OrenNayar {
+    albedo: Color,
+    roughness: f64,
+    // qualitative model terms, only depend on roughness so computed once
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    // reflectance relative to lambertian for light arriving from `incoming`
+    // and leaving toward `outgoing`, all unit vectors on the normal's side
+    fn factor(&self, normal: &Vec3, outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        if self.b == 0.0 {
+            return self.a;
+        }
+
+        let cos_o = normal.dot(outgoing).clamp(-1.0, 1.0);
+        let cos_i = normal.dot(incoming).clamp(-1.0, 1.0);
+        let theta_o = cos_o.acos();
+        let theta_i = cos_i.acos();
+
+        // cosine of the azimuth between the directions projected onto the surface
+        let tangent_o = *outgoing - cos_o * *normal;
+        let tangent_i = *incoming - cos_i * *normal;
+        let lengths = tangent_o.length() * tangent_i.length();
+        let cos_phi = if lengths > 1e-12 {
+            (tangent_o.dot(&tangent_i) / lengths).max(0.0)
+        } else {
+            0.0
+        };
+
+        let alpha = theta_o.max(theta_i);
+        let beta = theta_o.min(theta_i);
+
+        self.a + self.b * cos_phi * alpha.sin() * beta.tan()
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        let normal = hit_record.normal;
+
+        // cosine weighted like lambertian, the cosine and pdf cancel and only
+        // the oren-nayar factor remains on top of the albedo
+        let mut direction = normal + random_unit();
+        if direction.near_zero() {
+            direction = normal;
+        }
+
+        let outgoing = -ray_in.direction().unit();
+        let incoming = direction.unit();
+        let factor = self.factor(&normal, &outgoing, &incoming);
+
+        Some(ScatterRecord {
+            ray: Ray::new(hit_record.p, direction),
+            attenuation: self.albedo * factor,
+            color: None,
+        })
+    }
+}
+
+// teleport rays to a linked location instead of scattering, e.g. a pair of
+// quads acting as doorways between two rooms
+//
+// the continuation ray counts as a bounce so max_depth bounds portals facing
+// each other the same way it bounds mirrors facing each other
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Portal {
+    link_transform: Mat4,
+}
+
+impl Material for Portal {
+    fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        let origin = self.link_transform.transform_point(&hit_record.p);
+        let direction = self.link_transform.transform_vector(ray_in.direction());
+
+        Some(ScatterRecord {
+            ray: Ray::new(origin, direction),
+            attenuation: Color::WHITE,
+            color: None,
+        })
+    }
+}
+
+// Type without the Coated variant, so a coat can hold its base by value and
+// Type stays Copy without boxing
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CoatBase {
+    Empty(Empty),
+    Debug(Debug),
+    Lambertian(Lambertian),
+    Metal(Metal),
+    Dielectric(Dielectric),
+    Portal(Portal),
+    OrenNayar(OrenNayar),
+    DiffuseLight(DiffuseLight),
+    Isotropic(Isotropic),
+}
+
+impl CoatBase {
+    // None for coated materials, whose coats are stacked instead
+    fn new(material: Type) -> Option<Self> {
+        match material {
+            Type::Empty(m) => Some(CoatBase::Empty(m)),
+            Type::Debug(m) => Some(CoatBase::Debug(m)),
+            Type::Lambertian(m) => Some(CoatBase::Lambertian(m)),
+            Type::Metal(m) => Some(CoatBase::Metal(m)),
+            Type::Dielectric(m) => Some(CoatBase::Dielectric(m)),
+            Type::Portal(m) => Some(CoatBase::Portal(m)),
+            Type::OrenNayar(m) => Some(CoatBase::OrenNayar(m)),
+            Type::DiffuseLight(m) => Some(CoatBase::DiffuseLight(m)),
+            Type::Isotropic(m) => Some(CoatBase::Isotropic(m)),
+            Type::Coated(_) => None,
+        }
+    }
+}
+
+// one clear coat of a stack
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Coat {
+    refraction_index: f64,
+    roughness: f64,
+}
+
+// layered clear coats, see CoatedParams
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Coated {
+    base: CoatBase,
+    // outermost first, only the first `count` are used and the rest default
+    // so equal stacks compare equal
+    coats: [Coat; MAX_COATS],
+    count: usize,
+}
+
+impl Coated {
+    fn new(params: CoatedParams) -> Result<Self, MaterialError> {
+        let coat = Coat {
+            refraction_index: params.refraction_index,
+            roughness: params.roughness.clamp(0.0, 1.0),
+        };
+
+        let (base, inner) = match CoatBase::new(params.base) {
+            Some(base) => (base, &[][..]),
+            None => match &params.base {
+                Type::Coated(m) => (m.base, &m.coats[..m.count]),
+                _ => unreachable!("only coated materials have no coat base"),
+            },
+        };
+
+        let count = inner.len() + 1;
+        if count > MAX_COATS {
+            return Err(MaterialError::TooManyCoats(count));
+        }
+
+        let mut coats = [Coat::default(); MAX_COATS];
+        coats[0] = coat;
+        coats[1..count].copy_from_slice(inner);
+
+        Ok(Coated { base, coats, count })
+    }
+
+    // what the outermost coat covers, the next coat down or the base
+    fn under(&self) -> Type {
+        if self.count == 1 {
+            return self.base();
+        }
+
+        let mut coats = [Coat::default(); MAX_COATS];
+        coats[..self.count - 1].copy_from_slice(&self.coats[1..self.count]);
+        Type::Coated(Coated {
+            base: self.base,
+            coats,
+            count: self.count - 1,
+        })
+    }
+
+    fn base(&self) -> Type {
+        match self.base {
+            CoatBase::Empty(m) => Type::Empty(m),
+            CoatBase::Debug(m) => Type::Debug(m),
+            CoatBase::Lambertian(m) => Type::Lambertian(m),
+            CoatBase::Metal(m) => Type::Metal(m),
+            CoatBase::Dielectric(m) => Type::Dielectric(m),
+            CoatBase::Portal(m) => Type::Portal(m),
+            CoatBase::OrenNayar(m) => Type::OrenNayar(m),
+            CoatBase::DiffuseLight(m) => Type::DiffuseLight(m),
+            CoatBase::Isotropic(m) => Type::Isotropic(m),
+        }
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        let incident_uv = ray_in.direction().unit();
+        let cos_theta = incident_uv.cos_theta(&hit_record.normal);
+
+        // pick each coat, outermost first, with the probability it reflects,
+        // so no branch needs reweighting, every layer only sees the light the
+        // coats above it let through
+        let Some(coat) = self.coats[..self.count]
+            .iter()
+            .find(|coat| reflectance(cos_theta, 1.0 / coat.refraction_index) > random_f64())
+        else {
+            return self.base().scatter(ray_in, hit_record);
+        };
+
+        let mirror = incident_uv.reflect(&hit_record.normal);
+        let mut direction = mirror + coat.roughness * random_unit();
+
+        // blurred below the surface, keep the mirror direction
+        if direction.dot(&hit_record.normal) <= 0.0 {
+            direction = mirror;
+        }
+
+        Some(ScatterRecord {
+            ray: Ray::new(hit_record.p, direction),
+            attenuation: Color::WHITE,
+            color: None,
+        })
+    }
+}
+
+// emits light instead of scattering it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct DiffuseLight {
+    emit: Color,
+    two_sided: bool,
+}
+
+impl DiffuseLight {
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        if self.two_sided || hit_record.front_face {
+            self.emit
+        } else {
+            Color::BLACK
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray_in: &Ray, _hit_record: HitRecord) -> Option<ScatterRecord> {
+        None
+    }
+}
+
+// scatters inside a volume, see IsotropicParams
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Isotropic {
+    albedo: Color,
+    g: f64,
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray_in: &Ray, hit_record: HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            ray: Ray::new(
+                hit_record.p,
+                random_hg_direction(self.g, ray_in.direction()),
+            ),
+            attenuation: self.albedo,
+            color: None,
+        })
+    }
+}
+
 pub struct ReflectanceScatterOptions {
     hit_record: HitRecord,
     direction: Vec3,
@@ -322,7 +1064,7 @@ fn reflectance_scatter(options: ReflectanceScatterOptions) -> Option<ScatterReco
 
     // divide by zero impossible, options.reflectance will never be zero
     // zero values are handled above since they they will always return None
-    let attenuation = Color::from(Vec3::from(options.albedo) / options.reflectance);
+    let attenuation = options.albedo / options.reflectance;
 
     Some(ScatterRecord {
         ray: scattered_ray,
@@ -338,3 +1080,792 @@ fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
     let r0 = r0 * r0;
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Hittable;
+    use crate::geo::HittableList;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+    use crate::geo::Triangle;
+    use crate::test::assert;
+
+    fn hit_material(material: Type) -> Type {
+        let sphere = Sphere::builder()
+            .center(0.0, 0.0, -1.0)
+            .radius(0.5)
+            .material(material)
+            .build();
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        sphere.hit(&ray, 0.0, 100.0).unwrap().material
+    }
+
+    #[test]
+    fn test_metal_properties() {
+        let material = hit_material(Type::from(MetalParams {
+            albedo: Color::new(0.7, 0.6, 0.5),
+            reflectance: 0.9,
+            fuzz: 0.3,
+            ..Default::default()
+        }));
+
+        assert_eq!(material.albedo(), Some(Color::new(0.7, 0.6, 0.5)));
+        assert_eq!(material.reflectance(), Some(0.9));
+        assert_eq!(material.fuzz(), Some(0.3));
+        assert_eq!(material.refraction_index(), None);
+    }
+
+    fn scatter_many(material: Type, direction: Vec3, count: usize) -> (Ray, Vec<ScatterRecord>) {
+        let sphere = Sphere::builder()
+            .center(0.0, 0.0, -1.0)
+            .radius(0.5)
+            .material(material)
+            .build();
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), direction);
+        let hit = sphere.hit(&ray, 0.0, 100.0).unwrap();
+        let records = (0..count)
+            .filter_map(|_| hit.material.scatter(&ray, hit))
+            .collect();
+
+        let mirror = Ray::new(hit.p, ray.direction().unit().reflect(&hit.normal));
+        (mirror, records)
+    }
+
+    fn coat_fraction(material: Type, direction: Vec3) -> f64 {
+        let count = 20_000;
+        let (mirror, records) = scatter_many(material, direction, count);
+
+        // lambertian base directions never land exactly on the mirror
+        let coat = records
+            .iter()
+            .filter(|r| r.ray.direction().unit() == mirror.direction().unit())
+            .count();
+        coat as f64 / count as f64
+    }
+
+    #[test]
+    fn test_coated_normal_incidence() {
+        let base = Type::from(LambertianParams {
+            albedo: Color::new(0.8, 0.1, 0.1),
+            ..Default::default()
+        });
+        let material = Type::from(CoatedParams {
+            base,
+            refraction_index: 1.5,
+            roughness: 0.0,
+        });
+
+        assert_eq!(material.coat_base(), Some(base));
+        assert_eq!(material.albedo(), Some(Color::new(0.8, 0.1, 0.1)));
+        assert_eq!(material.refraction_index(), Some(1.5));
+        assert_eq!(material.roughness(), Some(0.0));
+
+        // ((1 - 1.5) / (1 + 1.5))^2 = 4% reflected by the coat head on
+        let fraction = coat_fraction(material, Vec3::new(0.0, 0.0, -1.0));
+        assert!((fraction - 0.04).abs() < 0.01, "{fraction}");
+
+        // coat reflections are white, the rest carries the base albedo
+        let (_, records) = scatter_many(material, Vec3::new(0.0, 0.0, -1.0), 1000);
+        assert!(records
+            .iter()
+            .all(|r| r.attenuation == Color::WHITE || r.attenuation == Color::new(0.8, 0.1, 0.1)));
+    }
+
+    #[test]
+    fn test_coated_grazing() {
+        let material = Type::from(CoatedParams::default());
+
+        // fresnel reflection grows towards grazing angles
+        let head_on = coat_fraction(material, Vec3::new(0.0, 0.0, -1.0));
+        let grazing = coat_fraction(material, Vec3::new(0.0, 0.57, -1.0));
+        assert!((grazing - 0.5).abs() < 0.03, "{grazing}");
+        assert!(grazing > 5.0 * head_on, "{grazing} {head_on}");
+    }
+
+    #[test]
+    fn test_coated_rough() {
+        let material = Type::from(CoatedParams {
+            base: Type::empty(),
+            refraction_index: 1.5,
+            roughness: 0.5,
+        });
+
+        // empty base absorbs, only blurred coat reflections above the surface remain
+        let (mirror, records) = scatter_many(material, Vec3::new(0.0, 0.0, -1.0), 10_000);
+        assert!(!records.is_empty());
+        assert!(records.len() < 1000);
+        for record in records {
+            assert!(record.ray.direction().dot(mirror.direction()) > 0.0);
+            assert_eq!(record.attenuation, Color::WHITE);
+        }
+    }
+
+    #[test]
+    fn test_coated_stack() {
+        let coat = |base: Type, refraction_index: f64| CoatedParams {
+            base,
+            refraction_index,
+            roughness: 0.0,
+        };
+
+        let inner = Type::from(coat(Type::debug(), 1.5));
+        let outer = Type::from(coat(inner, 2.0));
+
+        // the outer coat sits on the inner one, which keeps its own settings
+        assert_eq!(outer.refraction_index(), Some(2.0));
+        assert_eq!(outer.coat_base(), Some(inner));
+        assert_eq!(inner.coat_base(), Some(Type::debug()));
+
+        // two coats reflect more head on than either alone
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        let single = coat_fraction(Type::from(coat(Type::debug(), 2.0)), direction);
+        let stacked = coat_fraction(outer, direction);
+        assert!(stacked > single + 0.02, "{stacked} > {single}");
+
+        // one coat too many is refused rather than losing a layer
+        let full = Type::from(coat(outer, 1.3));
+        assert_eq!(
+            Type::try_from(coat(full, 1.4)),
+            Err(MaterialError::TooManyCoats(MAX_COATS + 1))
+        );
+        assert_eq!(Type::try_from(coat(outer, 1.3)), Ok(full));
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 3 are supported")]
+    fn test_coated_too_deep() {
+        let mut material = Type::debug();
+        for _ in 0..=MAX_COATS {
+            material = Type::from(CoatedParams {
+                base: material,
+                ..Default::default()
+            });
+        }
+    }
+
+    #[test]
+    fn test_diffuse_light_sides() {
+        // faces +z, towards a ray travelling -z
+        let light = |two_sided| {
+            Triangle::new(
+                Point3::new(-1.0, -1.0, -1.0),
+                Point3::new(1.0, -1.0, -1.0),
+                Point3::new(0.0, 1.0, -1.0),
+                Type::from(DiffuseLightParams {
+                    emit: Color::new(4.0, 4.0, 4.0),
+                    two_sided,
+                }),
+            )
+        };
+
+        let front = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0));
+
+        for (two_sided, back_emit) in [(false, Color::BLACK), (true, Color::new(4.0, 4.0, 4.0))] {
+            let triangle = light(two_sided);
+
+            let hit = triangle.hit(&front, 0.001, 100.0).unwrap();
+            assert!(hit.front_face);
+            assert_eq!(hit.material.emitted(&hit), Color::new(4.0, 4.0, 4.0));
+            assert!(hit.material.scatter(&front, hit).is_none());
+
+            let hit = triangle.hit(&back, 0.001, 100.0).unwrap();
+            assert!(!hit.front_face);
+            assert_eq!(hit.material.emitted(&hit), back_emit);
+        }
+
+        // other materials emit nothing
+        let hit = light(true).hit(&front, 0.001, 100.0).unwrap();
+        assert_eq!(Type::debug().emitted(&hit), Color::BLACK);
+    }
+
+    #[test]
+    fn test_metal_ggx_smooth_is_mirror() {
+        let albedo = Color::new(0.8, 0.6, 0.4);
+        let material = Type::from(MetalParams {
+            albedo,
+            roughness: Some(0.0),
+            ..Default::default()
+        });
+        assert_eq!(material.roughness(), Some(0.0));
+
+        let (mirror, records) = scatter_many(material, Vec3::new(0.2, 0.1, -1.0), 100);
+        assert_eq!(records.len(), 100);
+
+        for record in records {
+            let direction = record.ray.direction().unit();
+            let expected = mirror.direction().unit();
+            for n in 0..3 {
+                assert::float(direction[n], expected[n], 3);
+                assert::float(record.attenuation[n], albedo[n], 3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_metal_ggx_rough_spreads() {
+        let spread = |roughness| {
+            let material = Type::from(MetalParams {
+                albedo: Color::WHITE,
+                roughness: Some(roughness),
+                ..Default::default()
+            });
+            let (mirror, records) = scatter_many(material, Vec3::new(0.2, 0.1, -1.0), 2000);
+            let mirror = mirror.direction().unit();
+
+            // mean angle cosine to the mirror direction, 1 when all are mirrored
+            let alignment: f64 = records
+                .iter()
+                .map(|r| r.ray.direction().unit().dot(&mirror))
+                .sum::<f64>()
+                / records.len() as f64;
+
+            // single samples may exceed 1 but on average energy is never gained,
+            // allow for noise in the mean of samples that are all close to 1
+            let mean: f64 = records.iter().map(|r| r.attenuation.x()).sum::<f64>() / 2000.0;
+            assert!(mean <= 1.02, "{mean}");
+            alignment
+        };
+
+        let smooth = spread(0.05);
+        let medium = spread(0.5);
+        let rough = spread(1.0);
+        assert!(smooth > 0.99, "{smooth}");
+        assert!(
+            smooth > medium && medium > rough,
+            "{smooth} {medium} {rough}"
+        );
+        assert!(rough < 0.8, "{rough}");
+    }
+
+    #[test]
+    fn test_metal_roughness_clamped() {
+        let material = Type::from(MetalParams {
+            roughness: Some(3.0),
+            ..Default::default()
+        });
+        assert_eq!(material.roughness(), Some(1.0));
+        assert_eq!(Type::from(MetalParams::default()).roughness(), None);
+    }
+
+    #[test]
+    fn test_metal_fuzz_clamped() {
+        let material = Type::from(MetalParams {
+            fuzz: 4.0,
+            ..Default::default()
+        });
+
+        assert_eq!(material.fuzz(), Some(1.0));
+    }
+
+    #[test]
+    fn test_dielectric_properties() {
+        let material = hit_material(Type::from(DielectricParams {
+            refraction_index: 1.5,
+            ..Default::default()
+        }));
+
+        assert_eq!(material.refraction_index(), Some(1.5));
+        assert_eq!(material.albedo(), None);
+        assert_eq!(material.fuzz(), None);
+    }
+
+    #[test]
+    fn test_dielectric_dispersion() {
+        let material = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            dispersion: 0.2,
+            ..Default::default()
+        });
+
+        let hit_record = HitRecord {
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            front_face: true,
+            material,
+        };
+
+        // oblique incidence so each channel bends by a different amount
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+
+        let mut refracted = [None; 3];
+        for _ in 0..1000 {
+            let record = material.scatter(&ray, hit_record).unwrap();
+
+            // skip reflections, only refracted rays continue below the surface
+            if record.ray.direction().y() >= 0.0 {
+                continue;
+            }
+
+            let attenuation = Vec3::from(record.attenuation);
+            let channel = (0..3).find(|c| attenuation[*c] > 0.0).unwrap();
+            assert_eq!(attenuation[channel], 3.0);
+            assert_eq!(attenuation.length_squared(), 9.0);
+
+            refracted[channel as usize] = Some(record.ray.direction().unit());
+        }
+
+        let [red, green, blue] = refracted.map(|direction| direction.unwrap());
+
+        // higher index bends toward the normal, blue most and red least
+        assert!(blue.x() < green.x());
+        assert!(green.x() < red.x());
+    }
+
+    #[test]
+    fn test_dielectric_dispersion_keeps_channel() {
+        let material = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            dispersion: 0.2,
+            ..Default::default()
+        });
+        let hit = |front_face: bool| HitRecord {
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            front_face,
+            material,
+        };
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+
+        for _ in 0..200 {
+            // entering picks the channel and weights it once
+            let enter = material.scatter(&ray, hit(true)).unwrap();
+            let channel = enter.ray.channel().unwrap();
+            let mut expected = Vec3::new(0.0, 0.0, 0.0);
+            expected[channel] = 3.0;
+            assert_eq!(enter.attenuation, Color::from(expected));
+
+            // leaving keeps it, so the path is never zeroed or weighted twice
+            let exit = material.scatter(&enter.ray, hit(false)).unwrap();
+            assert_eq!(exit.ray.channel(), Some(channel));
+            assert_eq!(exit.attenuation, Color::WHITE);
+        }
+
+        // non dispersive glass passes the path's channel on untouched
+        let plain = Type::from(DielectricParams::default());
+        let record = plain
+            .scatter(&ray.with_channel(Some(1)), hit(true))
+            .unwrap();
+        assert_eq!(record.ray.channel(), Some(1));
+        assert_eq!(record.attenuation, Color::WHITE);
+    }
+
+    #[test]
+    fn test_dielectric_no_dispersion() {
+        let material = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            ..Default::default()
+        });
+
+        let hit_record = HitRecord {
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            front_face: true,
+            material,
+        };
+
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let record = material.scatter(&ray, hit_record).unwrap();
+        assert_eq!(record.attenuation, Color::WHITE);
+    }
+
+    #[test]
+    fn test_dielectric_attenuation() {
+        let red = Color::new(1.0, 0.2, 0.2);
+        let material = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            attenuation: red,
+            ..Default::default()
+        });
+
+        let hit_record = HitRecord {
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            front_face: true,
+            material,
+        };
+
+        // reflected and refracted alike are tinted
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let mut transmitted = 0;
+        for _ in 0..200 {
+            let record = material.scatter(&ray, hit_record).unwrap();
+            assert_eq!(record.attenuation, red);
+            transmitted += (record.ray.direction().y() < 0.0) as usize;
+        }
+        assert!(transmitted > 0);
+
+        // the tint scales the dispersed channel weights
+        let material = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            dispersion: 0.2,
+            attenuation: red,
+            ..Default::default()
+        });
+        let record = material.scatter(
+            &ray,
+            HitRecord {
+                material,
+                ..hit_record
+            },
+        );
+        let attenuation = Vec3::from(record.unwrap().attenuation);
+        let length = attenuation.length();
+        assert!(
+            (length - 3.0).abs() < 1e-9 || (length - 0.6).abs() < 1e-9,
+            "{attenuation:?}"
+        );
+    }
+
+    #[test]
+    fn test_dielectric_exterior_index() {
+        // sine of the angle from the normal of every refracted (not reflected)
+        // ray for a 45 degree hit
+        let refracted_sines = |material: Type, front_face: bool| {
+            // the recorded normal always opposes the ray, only the side differs
+            let hit_record = HitRecord {
+                p: Point3::new(0.0, 0.0, 0.0),
+                normal: Vec3::new(0.0, 1.0, 0.0),
+                t: 1.0,
+                front_face,
+                material,
+            };
+
+            let ray = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+            let mut sines = vec![];
+            for _ in 0..200 {
+                let direction = material
+                    .scatter(&ray, hit_record)
+                    .unwrap()
+                    .ray
+                    .direction()
+                    .unit();
+                if direction.y() < 0.0 {
+                    sines.push(direction.x());
+                }
+            }
+            assert!(!sines.is_empty());
+            sines
+        };
+
+        let sin_45 = std::f64::consts::FRAC_1_SQRT_2;
+
+        // glass submerged in water bends less than glass in air
+        let glass_in_water = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            exterior_index: 1.33,
+            ..Default::default()
+        });
+        assert_eq!(glass_in_water.exterior_index(), Some(1.33));
+
+        for sine in refracted_sines(glass_in_water, true) {
+            assert::float(sine, 1.33 / 1.5 * sin_45, 9);
+        }
+
+        // leaving the glass into the water from inside
+        for sine in refracted_sines(glass_in_water, false) {
+            assert::float(sine, 1.5 / 1.33 * sin_45, 9);
+        }
+
+        // matching indices do not bend at all
+        let matched = Type::from(DielectricParams {
+            refraction_index: 1.33,
+            exterior_index: 1.33,
+            ..Default::default()
+        });
+        for sine in refracted_sines(matched, true) {
+            assert::float(sine, sin_45, 9);
+        }
+
+        // the default exterior is vacuum
+        let glass = Type::from(DielectricParams {
+            refraction_index: 1.5,
+            ..Default::default()
+        });
+        assert_eq!(glass.exterior_index(), Some(1.0));
+        for sine in refracted_sines(glass, true) {
+            assert::float(sine, sin_45 / 1.5, 9);
+        }
+    }
+
+    #[test]
+    fn test_dielectric_glass_in_water_sphere() {
+        let sphere = |radius: f64, refraction_index: f64, exterior_index: f64| {
+            Sphere::builder()
+                .center(0.0, 0.0, 0.0)
+                .radius(radius)
+                .material(Type::from(DielectricParams {
+                    refraction_index,
+                    exterior_index,
+                    ..Default::default()
+                }))
+                .build()
+        };
+
+        // a glass ball inside a ball of water, the glass surrounded by water
+        let mut world = HittableList::new();
+        world.add(sphere(2.0, 1.33, 1.0));
+        world.add(sphere(1.0, 1.5, 1.33));
+
+        // Bouguer's invariant: refracting through concentric spheres keeps
+        // n |p x d| along the path, p from the center and d a unit direction
+        let invariant = |ray: &Ray, index: f64| {
+            index
+                * Vec3::from(*ray.origin())
+                    .cross(&ray.direction().unit())
+                    .length()
+        };
+
+        let mut ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert::float(invariant(&ray, 1.0), 0.5, 12);
+
+        // into the water, the glass, back into the water and out into the air
+        for index in [1.33, 1.5, 1.33, 1.0] {
+            let hit = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            let incoming = ray.direction().dot(&hit.normal);
+
+            // retry until fresnel picks refraction, which crosses the surface
+            ray = (0..100)
+                .map(|_| hit.material.scatter(&ray, hit).unwrap().ray)
+                .find(|scattered| scattered.direction().dot(&hit.normal) * incoming > 0.0)
+                .unwrap();
+            assert::float(invariant(&ray, index), 0.5, 9);
+        }
+
+        assert!(world.hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_lambertian_properties() {
+        let material = hit_material(Type::from(LambertianParams {
+            albedo: Color::new(0.5, 0.5, 0.5),
+            reflectance: 1.0,
+            uniform: false,
+        }));
+
+        assert_eq!(material.albedo(), Some(Color::new(0.5, 0.5, 0.5)));
+        assert_eq!(material.reflectance(), Some(1.0));
+        assert_eq!(material.fuzz(), None);
+    }
+
+    #[test]
+    fn test_gains_energy() {
+        let hit = |material: Type| {
+            let sphere = Sphere::builder()
+                .center(0.0, 0.0, -1.0)
+                .radius(0.5)
+                .material(material)
+                .build();
+            let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            let hit = sphere.hit(&ray, 0.0, 100.0).unwrap();
+
+            // reflectance may absorb the ray, retry until it scatters
+            (0..100)
+                .find_map(|_| hit.material.scatter(&ray, hit))
+                .unwrap()
+        };
+
+        let record = hit(Type::from(LambertianParams {
+            albedo: Color::new(0.9, 0.9, 0.9),
+            reflectance: 0.5,
+            ..Default::default()
+        }));
+        assert::float(record.attenuation.x(), 1.8, 9);
+        assert!(record.gains_energy());
+
+        let record = hit(Type::from(LambertianParams {
+            albedo: Color::new(0.4, 0.4, 0.4),
+            reflectance: 0.5,
+            ..Default::default()
+        }));
+        assert!(!record.gains_energy());
+    }
+
+    #[test]
+    fn test_portal() {
+        // quad in the z = -1 plane linked to a quad lying flat around y = 5,
+        // turned a quarter about x then lifted
+        let portal = Type::from(PortalParams {
+            link_transform: Mat4::translation(0.0, 5.0, 0.0)
+                * Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), 90.0),
+        });
+
+        let mut world = HittableList::new();
+        let [a, b, c, d] = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .map(|(x, y)| Point3::new(x, y, -1.0));
+        world.add(Triangle::new(a, b, c, portal));
+        world.add(Triangle::new(a, c, d, portal));
+
+        let ray = Ray::new(Point3::new(0.5, 0.25, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let record = hit.material.scatter(&ray, hit).unwrap();
+
+        // entry point (0.5, 0.25, -1) rotated 90 degrees around x is (0.5, 1, 0.25)
+        let origin = record.ray.origin();
+        assert::float(origin.x, 0.5, 9);
+        assert::float(origin.y, 6.0, 9);
+        assert::float(origin.z, 0.25, 9);
+
+        // heading -z rotated 90 degrees around x heads up, out of the floor
+        let direction = record.ray.direction();
+        assert::float(direction.x, 0.0, 9);
+        assert::float(direction.y, 1.0, 9);
+        assert::float(direction.z, 0.0, 9);
+
+        assert_eq!(record.attenuation, Color::WHITE);
+        assert_eq!(record.color, None);
+    }
+
+    #[test]
+    fn test_oren_nayar_zero_roughness_is_lambertian() {
+        let albedo = Color::new(0.6, 0.5, 0.4);
+        let oren_nayar = Type::from(OrenNayarParams {
+            albedo,
+            roughness: 0.0,
+        });
+        let lambertian = Type::from(LambertianParams {
+            albedo,
+            reflectance: 1.0,
+            uniform: false,
+        });
+
+        for direction in [Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.4, 0.3, -1.0)] {
+            let (_, oren_nayar) = scatter_many(oren_nayar, direction, 200);
+            let (_, lambertian) = scatter_many(lambertian, direction, 200);
+
+            assert_eq!(oren_nayar.len(), 200);
+            assert!(oren_nayar.iter().all(|r| r.attenuation == albedo));
+            assert!(lambertian.iter().all(|r| r.attenuation == albedo));
+        }
+
+        assert_eq!(oren_nayar.albedo(), Some(albedo));
+        assert_eq!(oren_nayar.roughness(), Some(0.0));
+    }
+
+    #[test]
+    fn test_oren_nayar_backscatter() {
+        let Type::OrenNayar(material) = Type::from(OrenNayarParams {
+            albedo: Color::WHITE,
+            roughness: 0.5,
+        }) else {
+            unreachable!()
+        };
+
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let grazing = Vec3::new(1.0, 0.2, 0.0).unit();
+        let opposite = Vec3::new(-1.0, 0.2, 0.0).unit();
+
+        // light and viewer on the same grazing side reflect more than lambertian
+        let back = material.factor(&normal, &grazing, &grazing);
+        let forward = material.factor(&normal, &grazing, &opposite);
+        assert!(back > 1.0, "{back}");
+        assert!(forward < 1.0, "{forward}");
+        assert_eq!(forward, material.a);
+    }
+
+    #[test]
+    fn test_empty_properties() {
+        let material = Type::empty();
+        assert!(material.is_empty());
+        assert!(!Type::debug().is_empty());
+        assert_eq!(material.albedo(), None);
+        assert_eq!(material.reflectance(), None);
+        assert_eq!(material.fuzz(), None);
+        assert_eq!(material.refraction_index(), None);
+    }
+
+    #[test]
+    fn test_base_color() {
+        let gray = Color::new(0.2, 0.4, 0.6);
+        let hit = |material: Type| HitRecord {
+            t: 1.0,
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            front_face: true,
+            material,
+        };
+
+        let cases = [
+            (Type::empty(), Color::BLACK),
+            (Type::debug(), Color::new(0.5, 0.5, 0.5)),
+            (
+                Type::from(LambertianParams {
+                    albedo: gray,
+                    ..Default::default()
+                }),
+                gray,
+            ),
+            (
+                Type::from(MetalParams {
+                    albedo: gray,
+                    ..Default::default()
+                }),
+                gray,
+            ),
+            (Type::from(DielectricParams::default()), Color::WHITE),
+            (
+                Type::from(DielectricParams {
+                    attenuation: gray,
+                    ..Default::default()
+                }),
+                gray,
+            ),
+            (
+                Type::from(PortalParams {
+                    link_transform: Mat4::translation(1.0, 0.0, 0.0),
+                }),
+                Color::WHITE,
+            ),
+            (
+                Type::from(OrenNayarParams {
+                    albedo: gray,
+                    ..Default::default()
+                }),
+                gray,
+            ),
+            (
+                Type::from(CoatedParams {
+                    base: Type::from(LambertianParams {
+                        albedo: Color::RED,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                Color::RED,
+            ),
+            (
+                Type::from(DiffuseLightParams {
+                    emit: Color::new(4.0, 4.0, 4.0),
+                    ..Default::default()
+                }),
+                Color::new(4.0, 4.0, 4.0),
+            ),
+            (
+                Type::from(IsotropicParams {
+                    albedo: gray,
+                    ..Default::default()
+                }),
+                gray,
+            ),
+        ];
+
+        for (material, expected) in cases {
+            assert_eq!(material.base_color(), expected, "{material:?}");
+
+            // only lights emit, and what they emit is their base color
+            let emitted = material.emitted(&hit(material));
+            if matches!(material, Type::DiffuseLight(_)) {
+                assert_eq!(emitted, expected);
+            } else {
+                assert_eq!(emitted, Color::BLACK, "{material:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::core::random_f64;
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Participating medium of uniform density filling a closed boundary, e.g.
+/// fog, smoke or mist
+///
+/// A ray crossing the boundary scatters after an exponentially distributed
+/// distance, or passes through untouched if that distance lies beyond the
+/// far side. Where it scatters the `phase` material, usually `Isotropic`,
+/// picks the new direction.
+///
+/// https://raytracing.github.io/books/RayTracingTheNextWeek.html#volumes
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f64,
+    phase: material::Type,
+}
+
+impl ConstantMedium {
+    /// `density` is the chance of scattering per unit distance, the boundary
+    /// must be closed and convex so a ray enters and leaves it once
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, phase: material::Type) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase,
+        }
+    }
+
+    pub fn density(&self) -> f64 {
+        -1.0 / self.neg_inv_density
+    }
+
+    pub fn phase(&self) -> material::Type {
+        self.phase
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        // entry and exit along the whole ray, so a ray starting inside the
+        // medium still finds the far side
+        let enter = self.boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY)?;
+        let exit = self.boundary.hit(ray, enter.t + 0.0001, f64::INFINITY)?;
+
+        let enter_t = enter.t.max(t_min).max(0.0);
+        let exit_t = exit.t.min(t_max);
+        if enter_t >= exit_t {
+            return None;
+        }
+
+        let ray_length = ray.direction().length();
+        let distance_inside = (exit_t - enter_t) * ray_length;
+        let hit_distance = self.neg_inv_density * random_f64().ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = enter_t + hit_distance / ray_length;
+
+        // a volume has no surface, the normal and face are arbitrary
+        Some(hittable::HitRecord {
+            t,
+            p: ray.at(t),
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            material: self.phase,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.boundary.memory_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::IsotropicParams;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+
+    fn fog(density: f64) -> ConstantMedium {
+        ConstantMedium::new(
+            Box::new(Sphere::builder().radius(1.0).build()),
+            density,
+            material::Type::from(IsotropicParams::default()),
+        )
+    }
+
+    #[test]
+    fn test_transmission() {
+        // through the diameter a fraction exp(-2 density) passes untouched
+        let medium = fog(0.5);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+
+        let n = 20_000;
+        let mut passed = 0;
+        for _ in 0..n {
+            match medium.hit(&ray, 0.001, f64::INFINITY) {
+                Some(hit) => assert!(hit.p.z() <= 1.0 && hit.p.z() >= -1.0, "{hit:?}"),
+                None => passed += 1,
+            }
+        }
+
+        let expected = (-2.0 * 0.5_f64).exp();
+        let actual = passed as f64 / n as f64;
+        assert!((actual - expected).abs() < 0.02, "{actual} {expected}");
+    }
+
+    #[test]
+    fn test_inside_and_outside() {
+        let medium = fog(1e6);
+
+        // starting inside scatters almost immediately
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = medium.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(hit.t < 0.01, "{}", hit.t);
+
+        // missing the boundary, or stopping before it, never scatters
+        let ray = Ray::new(Point3::new(0.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(medium.hit(&ray, 0.001, f64::INFINITY).is_none());
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(medium.hit(&ray, 0.001, 3.0).is_none());
+    }
+
+    #[test]
+    fn test_phase_scatter() {
+        // forward scattering keeps going roughly the way it came
+        let phase = material::Type::from(IsotropicParams {
+            g: 0.9,
+            ..Default::default()
+        });
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = hittable::HitRecord {
+            t: 5.0,
+            p: Point3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            material: phase,
+        };
+
+        let n = 2000;
+        let mean = (0..n)
+            .map(|_| {
+                let scattered = phase.scatter(&ray, hit).unwrap();
+                assert_eq!(scattered.attenuation, crate::core::Color::WHITE);
+                -scattered.ray.direction().unit().z
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!(mean > 0.8, "{mean}");
+        assert_eq!(phase.albedo(), Some(crate::core::Color::WHITE));
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::triangle;
+use crate::geo::Aabb;
+use crate::geo::BvhNode;
+use crate::geo::Hittable;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Triangles indexing into one shared vertex buffer, kept in a BVH
+///
+/// In a connected mesh every vertex is shared by around six triangles, so
+/// storing each position once and three indices per face takes a fraction
+/// of the memory of a separate `Triangle` per face, at the cost of
+/// computing edges and normals during intersection instead of up front.
+pub struct TriangleMesh {
+    bvh: BvhNode,
+    buffers: Arc<MeshBuffers>,
+    // kept by the mesh rather than its faces, so it can change without
+    // touching the buffers the faces share
+    material: material::Type,
+}
+
+struct MeshBuffers {
+    vertices: Vec<Point3>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// Mesh of the faces in `indices`, each three indices into `vertices`
+    /// with counter-clockwise winding facing outward
+    ///
+    /// Panics if an index is out of range
+    pub fn new(vertices: Vec<Point3>, indices: Vec<[usize; 3]>, material: material::Type) -> Self {
+        if let Some(index) = indices.iter().flatten().find(|&&i| i >= vertices.len()) {
+            panic!(
+                "vertex index {index} out of range for {} vertices",
+                vertices.len()
+            );
+        }
+
+        let buffers = Arc::new(MeshBuffers { vertices, indices });
+
+        let triangles = (0..buffers.indices.len())
+            .map(|face| {
+                Box::new(MeshTriangle {
+                    buffers: Arc::clone(&buffers),
+                    face,
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+
+        TriangleMesh {
+            bvh: BvhNode::new(triangles),
+            buffers,
+            material,
+        }
+    }
+
+    pub fn vertices(&self) -> &[Point3] {
+        &self.buffers.vertices
+    }
+
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.buffers.indices
+    }
+
+    pub fn material(&self) -> material::Type {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: material::Type) {
+        self.material = material;
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let hit = self.bvh.hit(ray, t_min, t_max)?;
+        Some(hittable::HitRecord {
+            material: self.material,
+            ..hit
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material(material);
+        1
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() - std::mem::size_of::<BvhNode>()
+            + self.bvh.memory_bytes()
+            + std::mem::size_of::<MeshBuffers>()
+            + self.buffers.vertices.capacity() * std::mem::size_of::<Point3>()
+            + self.buffers.indices.capacity() * std::mem::size_of::<[usize; 3]>()
+    }
+}
+
+/// One face of a `TriangleMesh`, a reference to the shared buffers and the
+/// face's position in the index buffer. Hits carry the empty material, the
+/// mesh fills in its own.
+pub struct MeshTriangle {
+    buffers: Arc<MeshBuffers>,
+    face: usize,
+}
+
+impl MeshTriangle {
+    pub fn face(&self) -> usize {
+        self.face
+    }
+
+    pub fn vertices(&self) -> [Point3; 3] {
+        self.buffers.indices[self.face].map(|i| self.buffers.vertices[i])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let [a, b, c] = self.vertices();
+        let edge_ab = Vec3::from(b) - Vec3::from(a);
+        let edge_ac = Vec3::from(c) - Vec3::from(a);
+
+        let (t, _, _) = triangle::intersect(&a, &edge_ab, &edge_ac, ray, t_min, t_max)?;
+
+        let mut hit_record = hittable::HitRecord {
+            t,
+            p: ray.at(t),
+            normal: edge_ab.cross(&edge_ac).unit(),
+            front_face: false,
+            material: material::Type::empty(),
+        };
+
+        hit_record.set_face_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices().map(Vec3::from);
+        let min = Vec3::new(
+            a.x.min(b.x).min(c.x),
+            a.y.min(b.y).min(c.y),
+            a.z.min(b.z).min(c.z),
+        );
+        let max = Vec3::new(
+            a.x.max(b.x).max(c.x),
+            a.y.max(b.y).max(c.y),
+            a.z.max(b.z).max(c.z),
+        );
+        Aabb::from_points(Point3::from(min), Point3::from(max))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench;
+    use crate::geo::Triangle;
+
+    fn separate(vertices: &[Point3], indices: &[[usize; 3]]) -> BvhNode {
+        BvhNode::new(
+            indices
+                .iter()
+                .map(|face| {
+                    let [a, b, c] = face.map(|i| vertices[i]);
+                    Box::new(Triangle::new(a, b, c, material::Type::debug())) as Box<dyn Hittable>
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_matches_separate_triangles() {
+        let (vertices, indices) = bench::sphere_mesh(12, 24);
+        let triangles = separate(&vertices, &indices);
+        let mesh = TriangleMesh::new(vertices, indices, material::Type::debug());
+        assert_eq!(mesh.bounding_box(), triangles.bounding_box());
+
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let origin = Point3::from(Vec3::random_range(-3.0, 3.0));
+            let toward = Vec3::new(0.0, 1.0, 0.0) - Vec3::from(origin);
+            let ray = Ray::new(origin, toward + Vec3::random_range(-0.5, 0.5));
+
+            let expected = triangles.hit(&ray, 0.001, f64::INFINITY);
+            assert_eq!(mesh.hit(&ray, 0.001, f64::INFINITY), expected, "{ray:?}");
+            hits += expected.is_some() as usize;
+        }
+
+        assert!(hits > 500, "{hits}");
+    }
+
+    #[test]
+    fn test_memory_bytes() {
+        let (vertices, indices) = bench::sphere_mesh(24, 48);
+        let triangles = separate(&vertices, &indices);
+        let mesh = TriangleMesh::new(vertices, indices, material::Type::debug());
+
+        // the BVH nodes are the same, the leaves shrink to a pointer and an
+        // index and every vertex is stored once instead of about six times
+        let (separate, shared) = (triangles.memory_bytes(), mesh.memory_bytes());
+        assert!(shared * 3 < separate * 2, "{shared} < {separate} * 2 / 3");
+    }
+
+    #[test]
+    fn test_accessors() {
+        let vertices = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+        ];
+        let mesh = TriangleMesh::new(
+            vertices,
+            vec![[0, 1, 2], [1, 3, 2]],
+            material::Type::debug(),
+        );
+
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.indices(), [[0, 1, 2], [1, 3, 2]]);
+
+        // counter-clockwise seen from +z, the front
+        let ray = Ray::new(Point3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_index_out_of_range() {
+        TriangleMesh::new(
+            vec![Point3::new(0.0, 0.0, 0.0); 3],
+            vec![[0, 1, 3]],
+            material::Type::debug(),
+        );
+    }
+}
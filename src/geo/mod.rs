@@ -1,17 +1,48 @@
+pub mod aabb;
+pub mod bvh;
+pub mod cone;
 pub mod core;
+pub mod csg;
+pub mod ellipsoid;
+pub mod grid;
+pub mod heightfield;
 pub mod hittable;
 pub mod interval;
+pub mod mat4;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod obj;
 pub mod point3;
 pub mod ray;
+pub mod rotate;
+pub mod scale;
+pub mod scenes;
 pub mod sphere;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
 
+pub use aabb::*;
+pub use bvh::*;
+pub use cone::*;
 pub use core::*;
+pub use csg::*;
+pub use ellipsoid::*;
+pub use grid::*;
+pub use heightfield::*;
 pub use hittable::*;
 pub use interval::*;
+pub use mat4::*;
 pub use material::*;
+pub use medium::*;
+pub use mesh::*;
+pub use obj::*;
 pub use point3::*;
 pub use ray::*;
+pub use rotate::*;
+pub use scale::*;
 pub use sphere::*;
+pub use transform::*;
+pub use triangle::*;
 pub use vec3::*;
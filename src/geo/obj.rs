@@ -0,0 +1,315 @@
+//! Wavefront OBJ export and import of triangle geometry, e.g. to inspect a
+//! generated scene in Blender
+//!
+//! https://en.wikipedia.org/wiki/Wavefront_.obj_file
+
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+
+use crate::geo::material;
+use crate::geo::Hittable;
+use crate::geo::HittableList;
+use crate::geo::Point3;
+use crate::geo::Sphere;
+use crate::geo::Triangle;
+use crate::geo::TriangleMesh;
+
+/// Bands and segments spheres are tessellated with on export
+pub const SPHERE_LAT: u32 = 16;
+pub const SPHERE_LON: u32 = 32;
+
+// one exported object, indices local to its own vertices
+struct Part {
+    vertices: Vec<Point3>,
+    faces: Vec<[usize; 3]>,
+    material: material::Type,
+}
+
+impl HittableList {
+    /// Write every triangle, triangle mesh and (tessellated) sphere to one
+    /// `.obj` at `path` and their materials to a `.mtl` next to it, nested
+    /// lists included. Other objects cannot be triangulated and are skipped
+    /// with a warning.
+    ///
+    /// Returns the number of objects written
+    pub fn export_obj(&self, path: &str) -> Result<usize, std::io::Error> {
+        let mut parts = vec![];
+        let mut skipped = 0;
+        collect(self, &mut parts, &mut skipped);
+
+        if skipped > 0 {
+            eprintln!(
+                "warning: export_obj skipped {skipped} objects, only triangles, meshes and \
+                 spheres are exported"
+            );
+        }
+
+        // one mtl entry per distinct material
+        let mut materials: Vec<material::Type> = vec![];
+        for part in &parts {
+            if !materials.contains(&part.material) {
+                materials.push(part.material);
+            }
+        }
+
+        let mtl_path = Path::new(path).with_extension("mtl");
+        let mut mtl = std::io::BufWriter::new(std::fs::File::create(&mtl_path)?);
+        for (index, material) in materials.iter().enumerate() {
+            write_material(&mut mtl, index, material)?;
+        }
+        mtl.flush()?;
+
+        let mut obj = std::io::BufWriter::new(std::fs::File::create(path)?);
+        if let Some(name) = mtl_path.file_name() {
+            writeln!(obj, "mtllib {}", name.to_string_lossy())?;
+        }
+
+        // obj indices are 1-based and count vertices across all objects
+        let mut offset = 1;
+        for (index, part) in parts.iter().enumerate() {
+            let material = materials.iter().position(|m| *m == part.material).unwrap();
+            writeln!(obj, "o object_{index}")?;
+            writeln!(obj, "usemtl material_{material}")?;
+            for v in &part.vertices {
+                writeln!(obj, "v {} {} {}", v.x(), v.y(), v.z())?;
+            }
+            for [a, b, c] in &part.faces {
+                writeln!(obj, "f {} {} {}", a + offset, b + offset, c + offset)?;
+            }
+            offset += part.vertices.len();
+        }
+        obj.flush()?;
+
+        Ok(parts.len())
+    }
+}
+
+fn collect(object: &dyn Hittable, parts: &mut Vec<Part>, skipped: &mut usize) {
+    let any = object.as_any();
+
+    if let Some(list) = any.downcast_ref::<HittableList>() {
+        for object in list.iter() {
+            collect(object.as_ref(), parts, skipped);
+        }
+    } else if let Some(triangle) = any.downcast_ref::<Triangle>() {
+        parts.push(Part {
+            vertices: triangle.vertices().to_vec(),
+            faces: vec![[0, 1, 2]],
+            material: triangle.material(),
+        });
+    } else if let Some(mesh) = any.downcast_ref::<TriangleMesh>() {
+        parts.push(mesh_part(mesh));
+    } else if let Some(sphere) = any.downcast_ref::<Sphere>() {
+        parts.push(mesh_part(&sphere.tessellate(SPHERE_LAT, SPHERE_LON)));
+    } else {
+        *skipped += 1;
+    }
+}
+
+fn mesh_part(mesh: &TriangleMesh) -> Part {
+    Part {
+        vertices: mesh.vertices().to_vec(),
+        faces: mesh.indices().to_vec(),
+        material: mesh.material(),
+    }
+}
+
+// diffuse color and refraction index where the material has them
+fn write_material<W: Write>(
+    writer: &mut W,
+    index: usize,
+    material: &material::Type,
+) -> Result<(), std::io::Error> {
+    writeln!(writer, "newmtl material_{index}")?;
+    if let Some(albedo) = material.albedo() {
+        writeln!(writer, "Kd {} {} {}", albedo.x(), albedo.y(), albedo.z())?;
+    }
+    if let Some(refraction_index) = material.refraction_index() {
+        writeln!(writer, "Ni {refraction_index}")?;
+    }
+    writeln!(writer)
+}
+
+/// Read the vertices and faces of an `.obj` into one mesh of `material`,
+/// polygons are split into triangle fans. Texture coordinates, normals,
+/// groups and materials are ignored.
+pub fn load_obj(path: &str, material: material::Type) -> Result<TriangleMesh, std::io::Error> {
+    let invalid = |line: usize, message: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{path}:{line}: {message}"),
+        )
+    };
+
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut vertices = vec![];
+    let mut faces = vec![];
+
+    for (number, line) in file.lines().enumerate() {
+        let line = line?;
+        let number = number + 1;
+        let mut fields = line.split_whitespace();
+
+        match fields.next() {
+            Some("v") => {
+                let coordinates = fields
+                    .take(3)
+                    .map(|field| field.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid(number, "invalid vertex"))?;
+                let [x, y, z] = coordinates[..] else {
+                    return Err(invalid(number, "vertex needs 3 coordinates"));
+                };
+                vertices.push(Point3::new(x, y, z));
+            }
+            Some("f") => {
+                // `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative counts back
+                // from the last vertex read
+                let corners = fields
+                    .map(|field| {
+                        let index = field.split('/').next().unwrap_or_default();
+                        let index: isize =
+                            index.parse().map_err(|_| invalid(number, "invalid face"))?;
+                        let resolved = if index < 0 {
+                            vertices.len() as isize + index
+                        } else {
+                            index - 1
+                        };
+                        if resolved < 0 || resolved as usize >= vertices.len() {
+                            return Err(invalid(number, "face index out of range"));
+                        }
+                        Ok(resolved as usize)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(invalid(number, "face needs at least 3 vertices"));
+                }
+                for n in 1..corners.len() - 1 {
+                    faces.push([corners[0], corners[n], corners[n + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TriangleMesh::new(vertices, faces, material))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Color;
+    use crate::geo::Ray;
+    use crate::geo::Vec3;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("ray-tracer-{name}-{}.obj", std::process::id()))
+    }
+
+    fn quad() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.1),
+            ],
+            vec![[0, 1, 2], [1, 3, 2]],
+            material::Type::debug(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("round-trip");
+        let path = path.to_str().unwrap();
+
+        let mut world = HittableList::new();
+        world.add(quad());
+        assert_eq!(world.export_obj(path).unwrap(), 1);
+
+        let mesh = load_obj(path, material::Type::debug()).unwrap();
+        assert_eq!(mesh.vertices(), quad().vertices());
+        assert_eq!(mesh.indices(), quad().indices());
+
+        let ray = Ray::new(Point3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            mesh.hit(&ray, 0.001, f64::INFINITY),
+            quad().hit(&ray, 0.001, f64::INFINITY)
+        );
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(Path::new(path).with_extension("mtl")).unwrap();
+    }
+
+    #[test]
+    fn test_indices_across_objects() {
+        let path = temp_path("objects");
+        let path = path.to_str().unwrap();
+
+        let red = material::Type::from(material::LambertianParams {
+            albedo: Color::RED,
+            ..Default::default()
+        });
+        let mut nested = HittableList::new();
+        nested.add(Triangle::new(
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(1.0, 0.0, 2.0),
+            Point3::new(0.0, 1.0, 2.0),
+            red,
+        ));
+        nested.add(crate::geo::Cone::builder().build());
+
+        let mut world = HittableList::new();
+        world.add(quad());
+        world.add(nested);
+        world.add(Sphere::builder().radius(1.0).material(red).build());
+        assert_eq!(world.export_obj(path).unwrap(), 3);
+
+        let mesh = load_obj(path, material::Type::debug()).unwrap();
+        let sphere_vertices = (SPHERE_LON * (SPHERE_LAT - 1) + 2) as usize;
+        assert_eq!(mesh.vertices().len(), 4 + 3 + sphere_vertices);
+        assert_eq!(mesh.indices()[2], [4, 5, 6]);
+        assert_eq!(mesh.vertices()[6], Point3::new(0.0, 1.0, 2.0));
+        assert!(mesh
+            .indices()
+            .iter()
+            .flatten()
+            .all(|&i| i < mesh.vertices().len()));
+
+        // the quad and triangle share the debug material, the triangle and
+        // sphere are red
+        let mtl = std::fs::read_to_string(Path::new(path).with_extension("mtl")).unwrap();
+        assert_eq!(mtl.matches("newmtl").count(), 2);
+        assert!(mtl.contains("Kd 1 0 0"), "{mtl}");
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(Path::new(path).with_extension("mtl")).unwrap();
+    }
+
+    #[test]
+    fn test_load_polygons() {
+        let path = temp_path("polygons");
+        std::fs::write(
+            &path,
+            "# square\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1 -1//1\n",
+        )
+        .unwrap();
+
+        let mesh = load_obj(path.to_str().unwrap(), material::Type::debug()).unwrap();
+        assert_eq!(mesh.indices(), [[0, 1, 2], [0, 2, 3]]);
+
+        std::fs::write(&path, "v 0 0 0\nf 1 2 3\n").unwrap();
+        let error = load_obj(path.to_str().unwrap(), material::Type::debug())
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(
+            error.to_string().ends_with(":2: face index out of range"),
+            "{error}"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,11 +5,30 @@ use crate::geo::Vec3;
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    // rgb channel the path is restricted to, see `channel`
+    channel: Option<u8>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            channel: None,
+        }
+    }
+
+    /// Single rgb channel (0 red, 1 green, 2 blue) the path this ray belongs
+    /// to carries since it first hit a dispersive dielectric, None while it
+    /// carries all three. Every later dielectric hit refracts with the same
+    /// channel's index.
+    pub fn channel(&self) -> Option<u8> {
+        self.channel
+    }
+
+    pub fn with_channel(mut self, channel: Option<u8>) -> Self {
+        self.channel = channel;
+        self
     }
 
     pub fn origin(&self) -> &Point3 {
@@ -52,4 +71,15 @@ mod tests {
         assert_eq!(a.at(0.5), Point3::new(3.0, 4.5, 6.0));
         assert_eq!(a.at(4.0), Point3::new(17.0, 22.0, 27.0));
     }
+
+    #[test]
+    fn test_channel() {
+        let a = Ray::new(Point3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0));
+        assert_eq!(a.channel(), None);
+
+        let b = a.with_channel(Some(2));
+        assert_eq!(b.channel(), Some(2));
+        assert_eq!(b.at(1.0), a.at(1.0));
+        assert_ne!(b, a);
+    }
 }
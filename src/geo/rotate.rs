@@ -0,0 +1,227 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Instance of an object rotated about an axis through the origin
+///
+/// Rays are rotated into the object's space and the hit point and normal
+/// back out, a rotation keeps lengths so t is the same in both spaces.
+///
+/// https://en.wikipedia.org/wiki/Rodrigues%27_rotation_formula
+pub struct Rotate {
+    object: Box<dyn Hittable>,
+    // rows of the object to world rotation, world to object is its transpose
+    rows: [Vec3; 3],
+    bbox: Aabb,
+}
+
+impl Rotate {
+    /// Rotate `object` counterclockwise by `angle` degrees about `axis` (right
+    /// hand rule), `axis` need not be unit length
+    pub fn new(object: Box<dyn Hittable>, axis: Vec3, angle: f64) -> Self {
+        let k = axis.unit();
+        let (sin, cos) = angle.to_radians().sin_cos();
+
+        // R = cos I + sin [k]x + (1 - cos) k k^T
+        let row = |n: usize| {
+            let (identity, cross) = match n {
+                0 => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -k.z, k.y)),
+                1 => (Vec3::new(0.0, 1.0, 0.0), Vec3::new(k.z, 0.0, -k.x)),
+                _ => (Vec3::new(0.0, 0.0, 1.0), Vec3::new(-k.y, k.x, 0.0)),
+            };
+            cos * identity + sin * cross + (1.0 - cos) * k.to_array()[n] * k
+        };
+        let rows = [row(0), row(1), row(2)];
+
+        let bbox = rotated_bbox(&rows, &object.bounding_box());
+
+        Rotate { object, rows, bbox }
+    }
+
+    // object to world
+    fn forward(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.rows[0].dot(&v),
+            self.rows[1].dot(&v),
+            self.rows[2].dot(&v),
+        )
+    }
+
+    // world to object
+    fn inverse(&self, v: Vec3) -> Vec3 {
+        v.x * self.rows[0] + v.y * self.rows[1] + v.z * self.rows[2]
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            Point3::from(self.inverse(Vec3::from(*ray.origin()))),
+            self.inverse(*ray.direction()),
+        )
+    }
+
+    fn world_hit(&self, mut hit: hittable::HitRecord) -> hittable::HitRecord {
+        hit.p = Point3::from(self.forward(Vec3::from(hit.p)));
+        hit.normal = self.forward(hit.normal);
+        hit
+    }
+}
+
+// box around the 8 rotated corners
+fn rotated_bbox(rows: &[Vec3; 3], bbox: &Aabb) -> Aabb {
+    if bbox.is_empty() {
+        return *bbox;
+    }
+
+    let (min, max) = (bbox.min(), bbox.max());
+    let corners: Vec<Vec3> = (0..8)
+        .map(|n| {
+            let corner = Vec3::new(
+                if n & 1 == 0 { min.x() } else { max.x() },
+                if n & 2 == 0 { min.y() } else { max.y() },
+                if n & 4 == 0 { min.z() } else { max.z() },
+            );
+            Vec3::new(
+                rows[0].dot(&corner),
+                rows[1].dot(&corner),
+                rows[2].dot(&corner),
+            )
+        })
+        .collect();
+
+    // one box from the extremes, unioning boxes of single points would pad
+    // each of them
+    let mut lo = [f64::INFINITY; 3];
+    let mut hi = [f64::NEG_INFINITY; 3];
+    for corner in corners {
+        for (axis, value) in corner.into_iter().enumerate() {
+            lo[axis] = lo[axis].min(value);
+            hi[axis] = hi[axis].max(value);
+        }
+    }
+
+    Aabb::from_points(
+        Point3::new(lo[0], lo[1], lo[2]),
+        Point3::new(hi[0], hi[1], hi[2]),
+    )
+}
+
+impl Hittable for Rotate {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        self.object
+            .hit(&self.object_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.object.set_materials(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.object.memory_bytes()
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<hittable::Span> {
+        self.object
+            .spans(&self.object_ray(ray))
+            .into_iter()
+            .map(|span| hittable::Span {
+                enter: self.world_hit(span.enter),
+                exit: self.world_hit(span.exit),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Sphere;
+    use crate::test::assert;
+
+    fn sphere(x: f64, y: f64, z: f64) -> Sphere {
+        Sphere::builder().center(x, y, z).radius(0.5).build()
+    }
+
+    #[test]
+    fn test_rotate_y() {
+        // a quarter turn about y takes +x to -z
+        let rotated = Rotate::new(
+            Box::new(sphere(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 2.0, 0.0),
+            90.0,
+        );
+        let expected = sphere(0.0, 0.0, -1.0);
+        assert::same_hits(&rotated, &expected, *expected.center());
+
+        // the standard rotation matrix about y
+        let (sin, cos) = 30_f64.to_radians().sin_cos();
+        let rotated = Rotate::new(
+            Box::new(sphere(1.0, 0.5, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+            30.0,
+        );
+        let expected = sphere(cos, 0.5, -sin);
+        assert::same_hits(&rotated, &expected, *expected.center());
+    }
+
+    #[test]
+    fn test_rotate_diagonal() {
+        // a third of a turn about (1, 1, 1) takes x to y, y to z and z to x
+        let axis = Vec3::new(1.0, 1.0, 1.0);
+        let rotated = Rotate::new(Box::new(sphere(1.0, 0.0, 0.0)), axis, 120.0);
+        let expected = sphere(0.0, 1.0, 0.0);
+        assert::same_hits(&rotated, &expected, *expected.center());
+
+        let rotated = Rotate::new(Box::new(sphere(0.0, 0.0, 2.0)), axis, -120.0);
+        let expected = sphere(0.0, 2.0, 0.0);
+        assert::same_hits(&rotated, &expected, *expected.center());
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let rotated = Rotate::new(
+            Box::new(sphere(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 0.0, 1.0),
+            90.0,
+        );
+        let bbox = rotated.bounding_box();
+        assert::vec3(Vec3::from(bbox.min()), Vec3::new(-0.5, 0.5, -0.5), 9);
+        assert::vec3(Vec3::from(bbox.max()), Vec3::new(0.5, 1.5, 0.5), 9);
+
+        // corners of a box turned 45 degrees stick out further
+        let rotated = Rotate::new(
+            Box::new(sphere(0.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+            45.0,
+        );
+        assert::float(rotated.bounding_box().max().x(), 0.5_f64.hypot(0.5), 9);
+    }
+
+    #[test]
+    fn test_spans() {
+        let rotated = Rotate::new(
+            Box::new(sphere(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let spans = rotated.spans(&ray);
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].enter.t, 0.5, 9);
+        assert::float(spans[0].exit.t, 1.5, 9);
+        assert::vec3(spans[0].enter.normal, Vec3::new(0.0, 0.0, 1.0), 9);
+    }
+}
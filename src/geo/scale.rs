@@ -0,0 +1,210 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+/// Instance of an object scaled per axis about the origin, e.g. a sphere
+/// stretched into an ellipsoid
+///
+/// Cheaper than a `Transform` holding only a scale. The ray direction is
+/// divided along with the origin and not renormalized, so t is the same in
+/// both spaces.
+pub struct Scale {
+    object: Box<dyn Hittable>,
+    factors: Vec3,
+    bbox: Aabb,
+}
+
+impl Scale {
+    /// Panics if a factor is 0, negative factors mirror the object, see
+    /// `try_new`
+    pub fn new(object: Box<dyn Hittable>, factors: Vec3) -> Self {
+        Scale::try_new(object, factors)
+            .unwrap_or_else(|| panic!("scale factors must be non-zero, got {factors}"))
+    }
+
+    /// Same as `new` but None if a factor is 0, e.g. for factors read from a
+    /// scene file
+    pub fn try_new(object: Box<dyn Hittable>, factors: Vec3) -> Option<Self> {
+        if factors.x == 0.0 || factors.y == 0.0 || factors.z == 0.0 {
+            return None;
+        }
+
+        let bbox = object.bounding_box();
+        let bbox = if bbox.is_empty() {
+            bbox
+        } else {
+            Aabb::from_points(
+                Point3::from(Vec3::from(bbox.min()) * factors),
+                Point3::from(Vec3::from(bbox.max()) * factors),
+            )
+        };
+
+        Some(Scale {
+            object,
+            factors,
+            bbox,
+        })
+    }
+
+    pub fn factors(&self) -> Vec3 {
+        self.factors
+    }
+
+    fn divide(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            v.x / self.factors.x,
+            v.y / self.factors.y,
+            v.z / self.factors.z,
+        )
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            Point3::from(self.divide(Vec3::from(*ray.origin()))),
+            self.divide(*ray.direction()),
+        )
+    }
+
+    // the inverse transpose of a scale is the inverse scale, normals are
+    // divided by the factors rather than multiplied
+    fn world_hit(&self, mut hit: hittable::HitRecord) -> hittable::HitRecord {
+        hit.p = Point3::from(Vec3::from(hit.p) * self.factors);
+        hit.normal = self.divide(hit.normal).unit();
+        hit
+    }
+}
+
+impl Hittable for Scale {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        self.object
+            .hit(&self.object_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.object.set_materials(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.object.memory_bytes()
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<hittable::Span> {
+        self.object
+            .spans(&self.object_ray(ray))
+            .into_iter()
+            .map(|span| hittable::Span {
+                enter: self.world_hit(span.enter),
+                exit: self.world_hit(span.exit),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Ellipsoid;
+    use crate::geo::Sphere;
+    use crate::test::assert;
+
+    fn scaled_sphere(factors: Vec3) -> Scale {
+        Scale::new(Box::new(Sphere::builder().radius(1.0).build()), factors)
+    }
+
+    #[test]
+    fn test_sphere_to_ellipsoid() {
+        let scale = scaled_sphere(Vec3::new(3.0, 1.0, 0.5));
+        let ellipsoid = Ellipsoid::builder().radii(3.0, 1.0, 0.5).build();
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = Point3::from(Vec3::random_range(-4.0, 4.0));
+            let direction = -Vec3::from(origin) + Vec3::random_range(-1.0, 1.0);
+            let ray = Ray::new(origin, direction);
+
+            let actual = scale.hit(&ray, 0.001, f64::INFINITY);
+            let expected = ellipsoid.hit(&ray, 0.001, f64::INFINITY);
+            assert_eq!(actual.is_some(), expected.is_some(), "{ray:?}");
+
+            let (Some(actual), Some(expected)) = (actual, expected) else {
+                continue;
+            };
+            hits += 1;
+
+            assert::float(actual.t, expected.t, 9);
+            assert_eq!(actual.front_face, expected.front_face);
+
+            // on the surface x^2 / 9 + y^2 + z^2 / 0.25 = 1
+            let p = actual.p;
+            assert::float(
+                p.x() * p.x() / 9.0 + p.y() * p.y() + p.z() * p.z() / 0.25,
+                1.0,
+                9,
+            );
+
+            // perpendicular to two tangents at the hit, the gradient crossed
+            // with any other direction
+            let gradient = Vec3::new(p.x() / 9.0, p.y(), p.z() / 0.25);
+            for other in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 1.0)] {
+                let tangent = gradient.cross(&other);
+                assert::float(actual.normal.dot(&tangent), 0.0, 9);
+            }
+            assert::float(actual.normal.length(), 1.0, 9);
+            assert::float(actual.normal.dot(&expected.normal), 1.0, 9);
+        }
+
+        assert!(hits > 100, "{hits}");
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let sphere = Sphere::builder().center(1.0, 1.0, 1.0).radius(1.0).build();
+        let scale = Scale::new(Box::new(sphere), Vec3::new(2.0, -1.0, 0.5));
+
+        let bbox = scale.bounding_box();
+        assert_eq!(bbox.min(), Point3::new(0.0, -2.0, 0.0));
+        assert_eq!(bbox.max(), Point3::new(4.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_mirror() {
+        // mirrored in x the sphere moves to -2, normals still face the ray
+        let sphere = Sphere::builder().center(2.0, 0.0, 0.0).radius(1.0).build();
+        let scale = Scale::new(Box::new(sphere), Vec3::new(-1.0, 1.0, 1.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = scale.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.p, Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_try_new() {
+        let sphere = || -> Box<dyn Hittable> { Box::new(Sphere::builder().radius(1.0).build()) };
+        assert!(Scale::try_new(sphere(), Vec3::new(1.0, 1.0, 0.0)).is_none());
+
+        let scale = Scale::try_new(sphere(), Vec3::new(2.0, -1.0, 1.0)).unwrap();
+        assert_eq!(scale.factors(), Vec3::new(2.0, -1.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero")]
+    fn test_zero_factor() {
+        scaled_sphere(Vec3::new(1.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,262 @@
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::core::Camera;
+use crate::core::Color;
+use crate::geo::material;
+use crate::geo::HittableList;
+use crate::geo::Sphere;
+use crate::geo::Vec3;
+
+/// Minimal starting point for examples and tests, a single diffuse sphere
+/// resting on a large ground sphere seen straight on
+pub fn hello_world() -> (HittableList, Camera) {
+    let mut world = HittableList::new();
+
+    world.add(
+        Sphere::builder()
+            .center(0.0, -100.5, -1.0)
+            .radius(100.0)
+            .material(material::Type::from(material::LambertianParams {
+                albedo: Color::new(0.8, 0.8, 0.0),
+                ..Default::default()
+            }))
+            .build(),
+    );
+
+    world.add(
+        Sphere::builder()
+            .center(0.0, 0.0, -1.0)
+            .radius(0.5)
+            .material(material::Type::from(material::LambertianParams {
+                albedo: Color::new(0.1, 0.2, 0.5),
+                ..Default::default()
+            }))
+            .build(),
+    );
+
+    let camera = Camera::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_height(225)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vertical_fov(90.0)
+        .look_from(0.0, 0.0, 0.0)
+        .look_at(0.0, 0.0, -1.0)
+        .initialize();
+
+    (world, camera)
+}
+
+/// Final scene of Ray Tracing in One Weekend, a field of small random spheres
+/// around three large ones (glass center, diffuse left, metal right)
+///
+/// The same `seed` always produces the same scene, drawn from a generator of
+/// its own so the calling thread's generator is left untouched
+///
+/// https://raytracing.github.io/books/RayTracingInOneWeekend.html#wherenext?/afinalrender
+pub fn rtiow_final(seed: u64) -> (HittableList, Camera) {
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    let mut world = HittableList::new();
+
+    world.add(
+        Sphere::builder()
+            .center(0.0, -1000.0, 0.0)
+            .radius(1000.0)
+            .material(material::Type::from(material::LambertianParams {
+                albedo: Color::new(0.5, 0.5, 0.5),
+                ..Default::default()
+            }))
+            .collision(false)
+            .build(),
+    );
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_material: f64 = rng.random();
+            let center = Vec3::new(
+                a as f64 + 0.9 * rng.random::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.random::<f64>(),
+            );
+
+            // keep clear of the big metal sphere
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() <= 0.9 {
+                continue;
+            }
+
+            let material = if choose_material < 0.8 {
+                material::Type::from(material::LambertianParams {
+                    albedo: Color::from(
+                        random_vec3(&mut rng, 0.0, 1.0) * random_vec3(&mut rng, 0.0, 1.0),
+                    ),
+                    ..Default::default()
+                })
+            } else if choose_material < 0.95 {
+                material::Type::from(material::MetalParams {
+                    albedo: Color::from(random_vec3(&mut rng, 0.5, 1.0)),
+                    fuzz: rng.random_range(0.0..0.5),
+                    ..Default::default()
+                })
+            } else {
+                material::Type::from(material::DielectricParams {
+                    refraction_index: 1.5,
+                    ..Default::default()
+                })
+            };
+
+            world.add(
+                Sphere::builder()
+                    .center(center.x, center.y, center.z)
+                    .radius(0.2)
+                    .material(material)
+                    .build(),
+            );
+        }
+    }
+
+    world.add(
+        Sphere::builder()
+            .center(0.0, 1.0, 0.0)
+            .radius(1.0)
+            .material(material::Type::from(material::DielectricParams {
+                refraction_index: 1.5,
+                ..Default::default()
+            }))
+            .build(),
+    );
+
+    world.add(
+        Sphere::builder()
+            .center(-4.0, 1.0, 0.0)
+            .radius(1.0)
+            .material(material::Type::from(material::LambertianParams {
+                albedo: Color::new(0.4, 0.2, 0.1),
+                ..Default::default()
+            }))
+            .build(),
+    );
+
+    world.add(
+        Sphere::builder()
+            .center(4.0, 1.0, 0.0)
+            .radius(1.0)
+            .material(material::Type::from(material::MetalParams {
+                albedo: Color::new(0.7, 0.6, 0.5),
+                fuzz: 0.0,
+                ..Default::default()
+            }))
+            .build(),
+    );
+
+    let camera = Camera::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_height(675)
+        .samples_per_pixel(500)
+        .max_depth(50)
+        .vertical_fov(20.0)
+        .look_from(13.0, 2.0, 3.0)
+        .look_at(0.0, 0.0, 0.0)
+        .vup(0.0, 1.0, 0.0)
+        .defocus_angle(0.6)
+        .focus_distance(10.0)
+        .initialize();
+
+    (world, camera)
+}
+
+fn random_vec3(rng: &mut SmallRng, min: f64, max: f64) -> Vec3 {
+    Vec3::new(
+        rng.random_range(min..max),
+        rng.random_range(min..max),
+        rng.random_range(min..max),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Point3;
+    use crate::test::assert;
+
+    fn spheres(world: &HittableList) -> Vec<Sphere> {
+        world
+            .objects()
+            .iter()
+            .filter_map(|object| object.as_any().downcast_ref::<Sphere>())
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_hello_world() {
+        let (world, camera) = hello_world();
+        assert_eq!(world.objects().len(), 2);
+
+        let (width, height) = (camera.image_width() as u32, camera.image_height() as u32);
+        assert_eq!((width, height), (400, 225));
+
+        // the shipped framing: the blue sphere in the middle of the image,
+        // resting on the yellow ground with open sky above it
+        let center = camera.trace_pixel(&world, width / 2, height / 2).unwrap();
+        assert::vec3(*center.p, Vec3::new(0.0, 0.0, -0.5), 2);
+        assert_eq!(center.material.albedo(), Some(Color::new(0.1, 0.2, 0.5)));
+
+        let bottom = camera.trace_pixel(&world, width / 2, height - 1).unwrap();
+        assert_eq!(bottom.material.albedo(), Some(Color::new(0.8, 0.8, 0.0)));
+
+        assert!(camera.trace_pixel(&world, width / 2, 0).is_none());
+        assert!(camera.trace_pixel(&world, 0, 0).is_none());
+    }
+
+    #[test]
+    fn test_rtiow_final_hero_spheres() {
+        let (world, camera) = rtiow_final(7);
+        let spheres = spheres(&world);
+
+        let hero = |x: f64| {
+            spheres
+                .iter()
+                .find(|s| s.radius() == 1.0 && *s.center() == Point3::new(x, 1.0, 0.0))
+                .unwrap()
+                .material()
+        };
+
+        assert_eq!(hero(0.0).refraction_index(), Some(1.5));
+        assert_eq!(hero(-4.0).albedo(), Some(Color::new(0.4, 0.2, 0.1)));
+        assert_eq!(hero(4.0).albedo(), Some(Color::new(0.7, 0.6, 0.5)));
+        assert_eq!(hero(4.0).fuzz(), Some(0.0));
+
+        assert_eq!(camera.image_width(), 1200);
+        assert_eq!(camera.image_height(), 675);
+    }
+
+    #[test]
+    fn test_rtiow_final_deterministic() {
+        let (a, _) = rtiow_final(7);
+        let (b, _) = rtiow_final(7);
+
+        let a = spheres(&a);
+        let b = spheres(&b);
+
+        // ground + small spheres + three heroes
+        let small = a.iter().filter(|s| s.radius() == 0.2).count();
+        assert!(small > 0 && small <= 22 * 22);
+        assert_eq!(a.len(), small + 4);
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_rtiow_final_keeps_thread_rng() {
+        // building the scene neither reseeds nor advances the caller's generator
+        crate::core::seed(3);
+        let expected = crate::core::random_f64();
+
+        crate::core::seed(3);
+        rtiow_final(7);
+        assert_eq!(crate::core::random_f64(), expected);
+    }
+}
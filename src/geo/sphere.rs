@@ -1,8 +1,13 @@
+use crate::core::random_f64;
 use crate::geo::hittable;
 use crate::geo::material;
+use crate::geo::onb;
+use crate::geo::random_unit;
+use crate::geo::Aabb;
 use crate::geo::Interval;
 use crate::geo::Point3;
 use crate::geo::Ray;
+use crate::geo::TriangleMesh;
 use crate::geo::Vec3;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -73,9 +78,133 @@ impl Sphere {
         self.material
     }
 
+    pub fn set_material(&mut self, material: material::Type) {
+        self.material = material;
+    }
+
     pub fn collision(&self) -> bool {
         self.collision
     }
+
+    /// Triangle mesh approximating the sphere with the same material, e.g.
+    /// for exporting to mesh based tools, `lat` bands from pole to pole (at
+    /// least 2) of `lon` segments around (at least 3)
+    ///
+    /// Each pole is a single vertex with a fan of triangles around it, so
+    /// there are `lon * (lat - 1) + 2` vertices and `2 * lon * (lat - 1)`
+    /// counter-clockwise faces, none of them degenerate.
+    pub fn tessellate(&self, lat: u32, lon: u32) -> TriangleMesh {
+        let (lat, lon) = (lat.max(2) as usize, lon.max(3) as usize);
+        let center = Vec3::from(self.center);
+        let point = |theta: f64, phi: f64| {
+            let direction = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            Point3::from(center + self.radius * direction)
+        };
+
+        // north pole, the rings between the poles, south pole
+        let mut vertices = Vec::with_capacity(lon * (lat - 1) + 2);
+        vertices.push(point(0.0, 0.0));
+        for ring in 1..lat {
+            let theta = std::f64::consts::PI * ring as f64 / lat as f64;
+            for segment in 0..lon {
+                let phi = 2.0 * std::f64::consts::PI * segment as f64 / lon as f64;
+                vertices.push(point(theta, phi));
+            }
+        }
+        vertices.push(point(std::f64::consts::PI, 0.0));
+
+        let north = 0;
+        let south = vertices.len() - 1;
+        let index = |ring: usize, segment: usize| 1 + (ring - 1) * lon + segment % lon;
+
+        let mut indices = Vec::with_capacity(2 * lon * (lat - 1));
+        for segment in 0..lon {
+            indices.push([north, index(1, segment + 1), index(1, segment)]);
+        }
+        for ring in 1..lat - 1 {
+            for segment in 0..lon {
+                let a = index(ring, segment);
+                let b = index(ring + 1, segment);
+                let c = index(ring + 1, segment + 1);
+                let d = index(ring, segment + 1);
+                indices.push([a, d, c]);
+                indices.push([a, c, b]);
+            }
+        }
+        for segment in 0..lon {
+            indices.push([index(lat - 1, segment), index(lat - 1, segment + 1), south]);
+        }
+
+        TriangleMesh::new(vertices, indices, self.material)
+    }
+
+    /// Probability density per steradian of `random_toward` from `origin`
+    /// picking `direction`, 0 for directions missing the sphere
+    ///
+    /// https://raytracing.github.io/books/RayTracingTheRestOfYourLife.html#cleaninguppdfmanagement/handlingsurfaceacneandspheresamplingvisualartifacts
+    pub fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let to_center = Vec3::from(self.center) - Vec3::from(*origin);
+        let distance_squared = to_center.length_squared();
+        let radius_squared = self.radius * self.radius;
+
+        // inside the sphere every direction hits it
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * std::f64::consts::PI);
+        }
+
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        if direction.unit().dot(&to_center.unit()) < cos_theta_max {
+            return 0.0;
+        }
+
+        let solid_angle = 2.0 * std::f64::consts::PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    /// Unit direction from `origin` uniformly distributed over the cone of
+    /// directions that hit the sphere, e.g. to sample an emissive sphere as
+    /// a light, see `pdf_value`
+    pub fn random_toward(&self, origin: &Point3) -> Vec3 {
+        let to_center = Vec3::from(self.center) - Vec3::from(*origin);
+        let distance_squared = to_center.length_squared();
+        let radius_squared = self.radius * self.radius;
+
+        if distance_squared <= radius_squared {
+            return random_unit();
+        }
+
+        // cos theta uniform in [cos_theta_max, 1] is uniform in solid angle
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let cos_theta = 1.0 + random_f64() * (cos_theta_max - 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * random_f64();
+
+        let w = to_center.unit();
+        let (u, v) = onb(&w);
+
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f64) -> hittable::HitRecord {
+        let p = ray.at(t);
+        let normal = (Vec3::from(p) - Vec3::from(self.center)) / self.radius;
+
+        let mut hit_record = hittable::HitRecord {
+            t,
+            p,
+            normal,
+            front_face: false,
+            material: self.material,
+        };
+
+        hit_record.set_face_normal(ray);
+
+        hit_record
+    }
 }
 
 impl hittable::Hittable for Sphere {
@@ -105,25 +234,43 @@ impl hittable::Hittable for Sphere {
             }
         }
 
-        let p = ray.at(root);
-        let normal = (Vec3::from(p) - Vec3::from(self.center)) / self.radius;
+        Some(self.hit_record(ray, root))
+    }
 
-        let mut hit_record = hittable::HitRecord {
-            t: root,
-            p,
-            normal,
-            front_face: false,
-            material: self.material,
-        };
+    // both roots regardless of t range, the ray enters at the nearer
+    fn spans(&self, ray: &Ray) -> Vec<hittable::Span> {
+        let oc = Vec3::from(self.center) - Vec3::from(ray.origin());
+        let a = ray.direction().length_squared();
+        let h = ray.direction().dot(&oc);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = h * h - a * c;
 
-        hit_record.set_face_normal(ray);
+        if discriminant <= 0.0 || a == 0.0 {
+            return vec![];
+        }
+
+        let sqrtd = discriminant.sqrt();
 
-        Some(hit_record)
+        vec![hittable::Span {
+            enter: self.hit_record(ray, (h - sqrtd) / a),
+            exit: self.hit_record(ray, (h + sqrtd) / a),
+        }]
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        let center = Vec3::from(self.center);
+        Aabb::from_points(Point3::from(center - r), Point3::from(center + r))
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material(material);
+        1
+    }
 }
 
 #[cfg(test)]
@@ -131,6 +278,101 @@ mod tests {
     use super::*;
     use crate::core::Color;
     use crate::geo::Hittable;
+    use crate::test::assert;
+
+    #[test]
+    fn test_tessellate() {
+        let material = material::Type::from(material::LambertianParams::default());
+        let sphere = Sphere::builder()
+            .center(1.0, -2.0, 0.5)
+            .radius(1.0)
+            .material(material)
+            .build();
+        let mesh = sphere.tessellate(8, 12);
+
+        assert_eq!(mesh.material(), material);
+        assert_eq!(mesh.vertices().len(), 12 * 7 + 2);
+        assert_eq!(mesh.indices().len(), 2 * 12 * 7);
+
+        let center = Vec3::from(*sphere.center());
+        for vertex in mesh.vertices() {
+            let distance = (Vec3::from(*vertex) - center).length();
+            assert!((distance - 1.0).abs() < 1e-12, "{distance}");
+        }
+
+        // no degenerate faces, every one wound to face outward
+        for face in mesh.indices() {
+            let [a, b, c] = face.map(|i| Vec3::from(mesh.vertices()[i]));
+            let normal = (b - a).cross(&(c - a));
+            assert!(normal.length() > 1e-6, "{face:?}");
+            assert!(normal.dot(&((a + b + c) / 3.0 - center)) > 0.0, "{face:?}");
+        }
+
+        // rays through the center hit close to the analytic surface, from outside
+        for direction in [Vec3::new(0.3, -1.0, 0.2), Vec3::new(0.1, 0.05, -1.0)] {
+            let origin = Point3::from(center - 5.0 * direction.unit());
+            let ray = Ray::new(origin, direction);
+            let expected = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            let actual = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert!(actual.front_face);
+            assert!(
+                (actual.t - expected.t).abs() < 0.1,
+                "{} {}",
+                actual.t,
+                expected.t
+            );
+        }
+    }
+
+    #[test]
+    fn test_pdf_value_integrates_to_one() {
+        let sphere = Sphere::builder().center(0.5, 3.0, -1.0).radius(0.8).build();
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        // expected value of pdf / (1 / 4 pi) over uniform directions
+        let n = 200_000;
+        let integral = (0..n)
+            .map(|_| sphere.pdf_value(&origin, &random_unit()))
+            .sum::<f64>()
+            * 4.0
+            * std::f64::consts::PI
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.05, "{integral}");
+
+        // and from inside, where every direction hits
+        let inside = sphere.pdf_value(sphere.center(), &Vec3::new(0.0, 0.0, 1.0));
+        assert::float(inside * 4.0 * std::f64::consts::PI, 1.0, 12);
+    }
+
+    #[test]
+    fn test_random_toward() {
+        let sphere = Sphere::builder().center(0.5, 3.0, -1.0).radius(0.8).build();
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let pdf = sphere.pdf_value(&origin, &(Vec3::from(*sphere.center())));
+        assert!(pdf > 0.0);
+
+        for _ in 0..1000 {
+            let direction = sphere.random_toward(&origin);
+            assert::float(direction.length(), 1.0, 9);
+
+            // every sampled direction hits, at the same density
+            let ray = Ray::new(origin, direction);
+            assert!(
+                sphere.hit(&ray, 0.001, f64::INFINITY).is_some(),
+                "{direction:?}"
+            );
+            assert_eq!(sphere.pdf_value(&origin, &direction), pdf);
+        }
+
+        assert_eq!(sphere.pdf_value(&origin, &Vec3::new(0.0, -1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_tessellate_minimum() {
+        let mesh = Sphere::builder().radius(2.0).build().tessellate(0, 0);
+        assert_eq!(mesh.vertices().len(), 3 + 2);
+        assert_eq!(mesh.indices().len(), 6);
+    }
 
     #[test]
     fn test_sphere_default() {
@@ -158,6 +400,28 @@ mod tests {
         assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
     }
 
+    #[test]
+    fn test_sphere_hit_second_root() {
+        // first root (t = 2) is before t_min, the exit at t = 4 is used instead
+        let sphere = Sphere::builder().center(0.0, 0.0, -3.0).radius(1.0).build();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let record = sphere.hit(&ray, 2.5, 100.0).unwrap();
+        assert_eq!(record.t, 4.0);
+        assert_eq!(record.p, Point3::new(0.0, 0.0, -4.0));
+        assert_eq!(record.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(!record.front_face);
+
+        // ray starting inside the sphere, first root is behind the origin
+        let ray = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, -1.0));
+        let record = sphere.hit(&ray, 0.001, 100.0).unwrap();
+        assert_eq!(record.t, 1.0);
+        assert_eq!(record.p, Point3::new(0.0, 0.0, -4.0));
+
+        // both roots out of range
+        assert!(sphere.hit(&ray, 1.5, 100.0).is_none());
+    }
+
     #[test]
     fn test_sphere_miss() {
         let sphere = Sphere::builder().center(0.0, 0.0, -1.0).radius(0.5).build();
@@ -167,6 +431,20 @@ mod tests {
         assert!(hit.is_none());
     }
 
+    #[test]
+    fn test_sphere_bounding_box() {
+        let sphere = Sphere::builder().radius(1.0).build();
+        let bbox = sphere.bounding_box();
+        for axis in 0..3 {
+            assert_eq!(bbox.axis(axis), Interval::new(-1.0, 1.0));
+        }
+
+        let sphere = Sphere::builder().center(1.0, -2.0, 3.0).radius(0.5).build();
+        let bbox = sphere.bounding_box();
+        assert_eq!(bbox.min(), Point3::new(0.5, -2.5, 2.5));
+        assert_eq!(bbox.max(), Point3::new(1.5, -1.5, 3.5));
+    }
+
     #[test]
     fn test_sphere_material() {
         let material = material::Type::from(material::LambertianParams {
@@ -186,4 +464,21 @@ mod tests {
         let hit = sphere.hit(&ray, 0.0, 100.0);
         assert!(hit.is_some());
     }
+
+    #[test]
+    fn test_sphere_set_material() {
+        let mut sphere = Sphere::builder()
+            .center(0.0, 0.0, -1.0)
+            .radius(0.5)
+            .material(material::Type::debug())
+            .build();
+        let metal = material::Type::from(material::MetalParams::default());
+
+        sphere.set_material(metal);
+        assert_eq!(sphere.material(), metal);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = sphere.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(hit.material, metal);
+    }
 }
@@ -0,0 +1,180 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Hittable;
+use crate::geo::Mat4;
+use crate::geo::Ray;
+
+/// Instance of an object placed by an affine transform, e.g. a composition
+/// of translations, rotations and scales
+///
+/// Rays are taken into the object's space by the inverse transform. The
+/// direction is not renormalized so t is the same in both spaces, hit points
+/// come back out through the transform and normals through its inverse
+/// transpose, which keeps them perpendicular under non-uniform scales.
+pub struct Transform {
+    object: Box<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    // inverse transpose for normals
+    normal_matrix: Mat4,
+    bbox: Aabb,
+}
+
+impl Transform {
+    /// Panics if `matrix` is not invertible, e.g. a scale of 0, see `try_new`
+    pub fn new(object: Box<dyn Hittable>, matrix: Mat4) -> Self {
+        Transform::try_new(object, matrix).expect("transform matrix must be invertible")
+    }
+
+    /// Same as `new` but None if `matrix` is not invertible, e.g. for
+    /// matrices read from a scene file
+    pub fn try_new(object: Box<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        let bbox = matrix.transform_aabb(&object.bounding_box());
+
+        Some(Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox,
+        })
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+        )
+    }
+
+    // the normal keeps its side of the surface, n' . d' = n . d, so
+    // front_face carries over
+    fn world_hit(&self, mut hit: hittable::HitRecord) -> hittable::HitRecord {
+        hit.p = self.matrix.transform_point(&hit.p);
+        hit.normal = self.normal_matrix.transform_vector(&hit.normal).unit();
+        hit
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        self.object
+            .hit(&self.object_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.object.set_materials(material)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.object.memory_bytes()
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<hittable::Span> {
+        self.object
+            .spans(&self.object_ray(ray))
+            .into_iter()
+            .map(|span| hittable::Span {
+                enter: self.world_hit(span.enter),
+                exit: self.world_hit(span.exit),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Ellipsoid;
+    use crate::geo::Point3;
+    use crate::geo::Sphere;
+    use crate::geo::Vec3;
+    use crate::test::assert;
+
+    fn unit_sphere() -> Box<dyn Hittable> {
+        Box::new(Sphere::builder().radius(1.0).build())
+    }
+
+    #[test]
+    fn test_translate_scale() {
+        let matrix = Mat4::translation(1.0, -0.5, 2.0) * Mat4::scale(0.5, 0.5, 0.5);
+        let transform = Transform::new(unit_sphere(), matrix);
+
+        let sphere = Sphere::builder().center(1.0, -0.5, 2.0).radius(0.5).build();
+        assert::same_hits(&transform, &sphere, Point3::new(1.0, -0.5, 2.0));
+    }
+
+    #[test]
+    fn test_non_uniform_scale() {
+        // the inverse transpose gives the ellipsoid's gradient normals
+        let matrix = Mat4::translation(0.0, 1.0, 0.0) * Mat4::scale(2.0, 1.0, 0.5);
+        let transform = Transform::new(unit_sphere(), matrix);
+
+        let ellipsoid = Ellipsoid::builder()
+            .center(0.0, 1.0, 0.0)
+            .radii(2.0, 1.0, 0.5)
+            .build();
+        assert::same_hits(&transform, &ellipsoid, Point3::new(0.0, 1.0, 0.0));
+
+        // rotating an ellipsoid about its long axis changes nothing
+        let matrix = matrix * Mat4::rotation(Vec3::new(1.0, 0.0, 0.0), 180.0);
+        let transform = Transform::new(unit_sphere(), matrix);
+        assert::same_hits(&transform, &ellipsoid, Point3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_composed() {
+        // scale to radius 0.5 around (2, 0, 0), turn a quarter about y to
+        // (0, 0, -2), lift to (0, 1, -2)
+        let matrix = Mat4::translation(0.0, 1.0, 0.0)
+            * Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), 90.0)
+            * Mat4::scale(0.5, 0.5, 0.5);
+        let sphere = Sphere::builder().center(4.0, 0.0, 0.0).radius(1.0).build();
+        let transform = Transform::new(Box::new(sphere), matrix);
+
+        let expected = Sphere::builder().center(0.0, 1.0, -2.0).radius(0.5).build();
+        assert::same_hits(&transform, &expected, Point3::new(0.0, 1.0, -2.0));
+
+        let bbox = transform.bounding_box();
+        assert::vec3(Vec3::from(bbox.min()), Vec3::new(-0.5, 0.5, -2.5), 9);
+        assert::vec3(Vec3::from(bbox.max()), Vec3::new(0.5, 1.5, -1.5), 9);
+
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let spans = transform.spans(&ray);
+        assert_eq!(spans.len(), 1);
+        assert::float(spans[0].enter.t, 1.5, 9);
+        assert::float(spans[0].exit.t, 2.5, 9);
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(Transform::try_new(unit_sphere(), Mat4::scale(1.0, 0.0, 1.0)).is_none());
+
+        let transform = Transform::try_new(unit_sphere(), Mat4::translation(0.0, 2.0, 0.0));
+        assert_eq!(
+            transform.unwrap().matrix(),
+            Mat4::translation(0.0, 2.0, 0.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invertible")]
+    fn test_singular() {
+        Transform::new(unit_sphere(), Mat4::scale(1.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::geo::hittable;
+use crate::geo::material;
+use crate::geo::Aabb;
+use crate::geo::Interval;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Triangle {
+    a: Point3,
+    b: Point3,
+    c: Point3,
+    // edges and geometric normal are computed once at construction
+    // static meshes never change so there is no reason to redo the
+    // cross product for every ray that is tested against the triangle
+    edge_ab: Vec3,
+    edge_ac: Vec3,
+    normal: Vec3,
+    material: material::Type,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: material::Type) -> Self {
+        let edge_ab = Vec3::from(b) - Vec3::from(a);
+        let edge_ac = Vec3::from(c) - Vec3::from(a);
+        let normal = edge_ab.cross(&edge_ac).unit();
+
+        Triangle {
+            a,
+            b,
+            c,
+            edge_ab,
+            edge_ac,
+            normal,
+            material,
+        }
+    }
+
+    pub fn vertices(&self) -> [Point3; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    /// Unit geometric normal, counter-clockwise winding (a, b, c) faces outward
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn material(&self) -> material::Type {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: material::Type) {
+        self.material = material;
+    }
+
+    /// Möller–Trumbore ray-triangle intersection, (t, u, v) with barycentric
+    /// coordinates (u, v) of the hit weighting vertices b and c
+    pub(crate) fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        intersect(&self.a, &self.edge_ab, &self.edge_ac, ray, t_min, t_max)
+    }
+}
+
+/// Möller–Trumbore intersection of the triangle at `a` spanned by the two
+/// edges from it, shared by triangles that store their vertices differently
+///
+/// https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+pub(crate) fn intersect(
+    a: &Point3,
+    edge_ab: &Vec3,
+    edge_ac: &Vec3,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let t_interval = Interval::new(t_min, t_max);
+
+    let p = ray.direction().cross(edge_ac);
+    let determinant = edge_ab.dot(&p);
+
+    // ray is parallel to the triangle plane
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_determinant = 1.0 / determinant;
+
+    // barycentric coordinates (u, v) of the hit inside the triangle
+    let s = Vec3::from(ray.origin()) - Vec3::from(a);
+    let u = s.dot(&p) * inv_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge_ab);
+    let v = ray.direction().dot(&q) * inv_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge_ac.dot(&q) * inv_determinant;
+    if !t_interval.surrounds(t) {
+        return None;
+    }
+
+    Some((t, u, v))
+}
+
+impl hittable::Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<hittable::HitRecord> {
+        let (t, _, _) = self.intersect(ray, t_min, t_max)?;
+
+        let mut hit_record = hittable::HitRecord {
+            t,
+            p: ray.at(t),
+            normal: self.normal,
+            front_face: false,
+            material: self.material,
+        };
+
+        hit_record.set_face_normal(ray);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = [self.a, self.b, self.c].map(Vec3::from);
+        let min = Vec3::new(
+            a.x.min(b.x).min(c.x),
+            a.y.min(b.y).min(c.y),
+            a.z.min(b.z).min(c.z),
+        );
+        let max = Vec3::new(
+            a.x.max(b.x).max(c.x),
+            a.y.max(b.y).max(c.y),
+            a.z.max(b.z).max(c.z),
+        );
+        Aabb::from_points(Point3::from(min), Point3::from(max))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn set_materials(&mut self, material: material::Type) -> usize {
+        self.set_material(material);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Hittable;
+    use crate::test::assert;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(-1.0, -1.0, -2.0),
+            Point3::new(1.0, -1.0, -2.0),
+            Point3::new(0.0, 1.0, -2.0),
+            material::Type::debug(),
+        )
+    }
+
+    #[test]
+    fn test_cached_normal() {
+        let triangle = triangle();
+        let [a, b, c] = triangle.vertices();
+
+        let edge_ab = Vec3::from(b) - Vec3::from(a);
+        let edge_ac = Vec3::from(c) - Vec3::from(a);
+        let normal = edge_ab.cross(&edge_ac).unit();
+
+        assert_eq!(triangle.normal(), normal);
+        assert_eq!(triangle.normal(), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let aabb = triangle().bounding_box();
+        assert_eq!(aabb.axis(0), Interval::new(-1.0, 1.0));
+        assert_eq!(aabb.axis(1), Interval::new(-1.0, 1.0));
+        // flat in z, padded so rays still hit it
+        assert!(aabb.axis(2).contains(-2.0));
+        assert!(aabb.axis(2).size() > 0.0);
+    }
+
+    #[test]
+    fn test_triangle_hit() {
+        let triangle = triangle();
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let record = triangle.hit(&ray, 0.0, 100.0).unwrap();
+        assert_eq!(record.t, 2.0);
+        assert_eq!(record.p, Point3::new(0.0, 0.0, -2.0));
+        assert_eq!(record.normal, triangle.normal());
+        assert!(record.front_face);
+        assert_eq!(record.material, material::Type::debug());
+    }
+
+    #[test]
+    fn test_triangle_hit_back_face() {
+        let triangle = triangle();
+        let ray = Ray::new(Point3::new(0.5, -0.5, -4.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let record = triangle.hit(&ray, 0.0, 100.0).unwrap();
+        assert::float(record.t, 2.0, 9);
+        assert_eq!(record.normal, -triangle.normal());
+        assert!(!record.front_face);
+    }
+
+    #[test]
+    fn test_triangle_miss() {
+        let triangle = triangle();
+
+        // outside the edges
+        let ray = Ray::new(Point3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&ray, 0.0, 100.0).is_none());
+
+        // parallel to the plane
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(triangle.hit(&ray, 0.0, 100.0).is_none());
+
+        // beyond t_max
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&ray, 0.0, 1.0).is_none());
+    }
+}
@@ -9,7 +9,10 @@ pub struct Vec3 {
 }
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+
+    pub const fn new(x: f64, y: f64, z: f64) -> Vec3 {
         Vec3 { x, y, z }
     }
 
@@ -49,6 +52,10 @@ impl Vec3 {
         self.z
     }
 
+    pub fn to_array(&self) -> [f64; 3] {
+        [self.x, self.y, self.z]
+    }
+
     pub fn length_squared(&self) -> f64 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
@@ -89,8 +96,7 @@ impl Vec3 {
     //
     pub fn reflect(&self, normal: &Vec3) -> Vec3 {
         let nv = self.dot(normal) * *normal;
-        let r = *self - (2.0 * nv);
-        r
+        *self - (2.0 * nv)
     }
 
     pub fn cos_theta(&self, normal: &Vec3) -> f64 {
@@ -102,9 +108,35 @@ impl Vec3 {
     pub fn refract(&self, normal: &Vec3, refraction_index: f64) -> Vec3 {
         let cos_theta = self.cos_theta(normal);
         let r_out_perp = refraction_index * (*self + (cos_theta * *normal));
-        let r_out_para = (-1.0 * (1.0 - r_out_perp.length_squared()).abs().sqrt()) * *normal;
+        let r_out_para = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * *normal;
         r_out_perp + r_out_para
     }
+
+    /// Refracted direction of this unit vector, None when the angle is past
+    /// the critical angle and all the light is reflected (total internal
+    /// reflection), unlike `refract` which returns a direction regardless
+    pub fn refract_checked(&self, normal: &Vec3, refraction_index: f64) -> Option<Vec3> {
+        let cos_theta = self.cos_theta(normal);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+        if refraction_index * sin_theta > 1.0 {
+            return None;
+        }
+
+        Some(self.refract(normal, refraction_index))
+    }
+
+    /// This vector rotated counterclockwise by `angle` radians about `axis`
+    /// through the origin (right hand rule), `axis` need not be unit length
+    ///
+    /// https://en.wikipedia.org/wiki/Rodrigues%27_rotation_formula
+    pub fn rotate_around(&self, axis: &Vec3, angle: f64) -> Vec3 {
+        let k = axis.unit();
+        let (sin, cos) = angle.sin_cos();
+
+        // v cos + (k x v) sin + k (k . v)(1 - cos)
+        cos * *self + sin * k.cross(self) + (1.0 - cos) * k.dot(self) * k
+    }
 }
 
 impl std::fmt::Display for Vec3 {
@@ -115,6 +147,45 @@ impl std::fmt::Display for Vec3 {
 
 impl Eq for Vec3 {}
 
+impl From<[f64; 3]> for Vec3 {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<(f64, f64, f64)> for Vec3 {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for [f64; 3] {
+    fn from(v: Vec3) -> Self {
+        v.to_array()
+    }
+}
+
+impl IntoIterator for Vec3 {
+    type Item = f64;
+    type IntoIter = std::array::IntoIter<f64, 3>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.to_array().into_iter()
+    }
+}
+
+impl std::iter::Sum for Vec3 {
+    fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Self {
+        iter.fold(Vec3::ZERO, |sum, v| sum + v)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Vec3> for Vec3 {
+    fn sum<I: Iterator<Item = &'a Vec3>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
 impl ops::Add<Vec3> for Vec3 {
     type Output = Vec3;
 
@@ -445,7 +516,7 @@ mod tests {
     #[test]
     fn test_near_zero() {
         let a = Vec3::new(0.000000003, 0.0000000000000921, 0.0000000000000000375);
-        assert_eq!(a.near_zero(), true);
+        assert!(a.near_zero());
     }
 
     #[test]
@@ -524,4 +595,118 @@ mod tests {
         assert::float(result.y, -0.35355, 5);
         assert::float(result.z, 0.0, 5);
     }
+
+    #[test]
+    fn test_rotate_around() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let close = |a: Vec3, b: Vec3| {
+            assert::float(a.x, b.x, 9);
+            assert::float(a.y, b.y, 9);
+            assert::float(a.z, b.z, 9);
+        };
+
+        // a quarter turn about y takes x to -z
+        close(
+            x.rotate_around(&y, std::f64::consts::FRAC_PI_2),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        close(
+            x.rotate_around(&(3.0 * y), -std::f64::consts::FRAC_PI_2),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+
+        let v = Vec3::new(0.3, -1.2, 2.5);
+        let axis = Vec3::new(1.0, 1.0, -0.5);
+        close(v.rotate_around(&axis, std::f64::consts::TAU), v);
+        assert::float(v.rotate_around(&axis, 1.0).length(), v.length(), 9);
+
+        // the axis itself stays put
+        close(axis.rotate_around(&axis, 1.0), axis);
+    }
+
+    #[test]
+    fn test_refract_checked() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let at = |degrees: f64| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            Vec3::new(sin, -cos, 0.0)
+        };
+
+        // glass to air, critical angle asin(1 / 1.5) ~ 41.81 degrees
+        let ratio: f64 = 1.5;
+        let critical = (1.0 / ratio).asin().to_degrees();
+
+        let below = at(critical - 0.01);
+        let refracted = below.refract_checked(&normal, ratio).unwrap();
+        assert_eq!(refracted, below.refract(&normal, ratio));
+        assert::float(refracted.length(), 1.0, 9);
+
+        assert!(at(critical + 0.01)
+            .refract_checked(&normal, ratio)
+            .is_none());
+        assert!(at(89.0).refract_checked(&normal, ratio).is_none());
+
+        // straight through at normal incidence
+        let straight = at(0.0).refract_checked(&normal, ratio).unwrap();
+        assert_eq!(straight, Vec3::new(0.0, -1.0, 0.0));
+
+        // air to glass never reflects totally
+        for degrees in [0.0, 45.0, 89.9] {
+            assert!(at(degrees).refract_checked(&normal, 1.0 / ratio).is_some());
+        }
+    }
+
+    #[test]
+    fn test_array_round_trip() {
+        let a = Vec3::new(1.5, -2.0, 0.25);
+        assert_eq!(a.to_array(), [1.5, -2.0, 0.25]);
+        assert_eq!(Vec3::from(a.to_array()), a);
+
+        let array: [f64; 3] = a.into();
+        assert_eq!(array, [1.5, -2.0, 0.25]);
+        assert_eq!(Vec3::from(array), a);
+    }
+
+    #[test]
+    fn test_from_tuple() {
+        let a = Vec3::from((1.5, -2.0, 0.25));
+        assert_eq!(a, Vec3::new(1.5, -2.0, 0.25));
+
+        let b: Vec3 = (0.0, 1.0, 2.0).into();
+        assert_eq!(b.to_array(), [0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(Vec3::ZERO, Vec3::default());
+        assert_eq!(Vec3::ONE, Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(Vec3::ONE - Vec3::ONE, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_sum() {
+        let vs = [
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(-0.5, 0.25, 4.0),
+            Vec3::new(0.1, 0.2, 0.3),
+        ];
+
+        let mut expected = Vec3::ZERO;
+        for v in vs {
+            expected += v;
+        }
+
+        assert_eq!(vs.iter().copied().sum::<Vec3>(), expected);
+        assert_eq!(vs.iter().sum::<Vec3>(), expected);
+        assert_eq!(std::iter::empty::<Vec3>().sum::<Vec3>(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!(v.into_iter().collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+        assert_eq!(v.into_iter().sum::<f64>(), 6.0);
+        assert_eq!(v.into_iter().fold(f64::NEG_INFINITY, f64::max), 3.0);
+    }
 }
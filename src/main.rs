@@ -2,6 +2,7 @@ use ray_tracer::core::random_f64;
 use ray_tracer::core::random_f64_range;
 use ray_tracer::core::Camera;
 use ray_tracer::core::Color;
+use ray_tracer::core::WeightedChooser;
 use ray_tracer::geo::material;
 use ray_tracer::geo::HittableList;
 use ray_tracer::geo::Sphere;
@@ -47,6 +48,7 @@ async fn main() {
             .radius(radius)
             .material(material::Type::from(material::DielectricParams {
                 refraction_index: 1.5,
+                ..Default::default()
             }))
             .build(),
     );
@@ -60,6 +62,7 @@ async fn main() {
                 albedo: Color::new(0.7, 0.6, 0.5),
                 reflectance: 1.0,
                 fuzz: 0.0,
+                ..Default::default()
             }))
             .build(),
     );
@@ -80,7 +83,7 @@ async fn main() {
             let mut include = true;
 
             // only add sphere if its far enough away from existing larger spheres
-            for object in world.objects() {
+            for object in &world {
                 // detect object is sphere
                 if let Some(object_sphere) = object.as_any().downcast_ref::<Sphere>() {
                     // skip spheres that are not collision (i.e. ground)
@@ -142,6 +145,13 @@ struct RandomSphereParams {
     metal_chance: f64,
     lambertian_chance: f64,
 }
+
+enum SphereMaterial {
+    Glass,
+    Metal,
+    Lambertian,
+}
+
 fn random_sphere(params: RandomSphereParams) -> Sphere {
     let radius = random_f64_range(0.1, 0.3);
 
@@ -151,32 +161,29 @@ fn random_sphere(params: RandomSphereParams) -> Sphere {
         params.z + 0.9 * random_f64(),
     );
 
-    let glass_chance = 1.0 - params.glass_chance;
-    let metal_chance = glass_chance - params.metal_chance;
-    let lambertian_chance = metal_chance - params.lambertian_chance;
-
-    // dbg!(glass_chance, metal_chance, lambertian_chance);
-
-    let material_chance = random_f64();
+    let materials = WeightedChooser::new(vec![
+        (params.glass_chance, SphereMaterial::Glass),
+        (params.metal_chance, SphereMaterial::Metal),
+        (params.lambertian_chance, SphereMaterial::Lambertian),
+    ])
+    .expect("at least one material chance must be positive");
 
-    let material = if material_chance > glass_chance {
-        material::Type::from(material::DielectricParams {
+    let material = match materials.choose() {
+        SphereMaterial::Glass => material::Type::from(material::DielectricParams {
             refraction_index: 1.5,
-        })
-    } else if material_chance > metal_chance {
-        material::Type::from(material::MetalParams {
+            ..Default::default()
+        }),
+        SphereMaterial::Metal => material::Type::from(material::MetalParams {
             albedo: Color::from(Vec3::random_range(0.5, 1.0)),
             reflectance: 1.0,
             fuzz: random_f64_range(0.0, 0.5),
-        })
-    } else if material_chance > lambertian_chance {
-        material::Type::from(material::LambertianParams {
+            ..Default::default()
+        }),
+        SphereMaterial::Lambertian => material::Type::from(material::LambertianParams {
             albedo: Color::from(Vec3::random() * Vec3::random()),
             reflectance: 1.0,
             uniform: false,
-        })
-    } else {
-        panic!("material chance not handled ({material_chance})");
+        }),
     };
 
     Sphere::builder()
@@ -1,3 +1,8 @@
+use crate::geo::Hittable;
+use crate::geo::Point3;
+use crate::geo::Ray;
+use crate::geo::Vec3;
+
 pub fn float(a: f64, b: f64, digits: i32) {
     let epsilon = 0.1_f64.powi(digits);
     assert!(
@@ -8,3 +13,34 @@ pub fn float(a: f64, b: f64, digits: i32) {
         epsilon
     );
 }
+
+pub fn vec3(a: Vec3, b: Vec3, digits: i32) {
+    float(a.x, b.x, digits);
+    float(a.y, b.y, digits);
+    float(a.z, b.z, digits);
+}
+
+/// Random rays towards `target` hit both objects the same way, e.g. an
+/// instanced object and the same object built where the instance puts it
+pub fn same_hits(actual: &dyn Hittable, expected: &dyn Hittable, target: Point3) {
+    let mut hits = 0;
+    for _ in 0..500 {
+        let origin = Point3::from(Vec3::random_range(-4.0, 4.0));
+        let toward = Vec3::from(target) - Vec3::from(origin);
+        let ray = Ray::new(origin, toward + Vec3::random_range(-0.5, 0.5));
+
+        let a = actual.hit(&ray, 0.001, f64::INFINITY);
+        let b = expected.hit(&ray, 0.001, f64::INFINITY);
+        assert_eq!(a.is_some(), b.is_some(), "{ray:?}");
+
+        if let (Some(a), Some(b)) = (a, b) {
+            float(a.t, b.t, 9);
+            vec3(Vec3::from(a.p), Vec3::from(b.p), 9);
+            vec3(a.normal, b.normal, 9);
+            assert_eq!(a.front_face, b.front_face);
+            hits += 1;
+        }
+    }
+
+    assert!(hits > 100, "{hits}");
+}